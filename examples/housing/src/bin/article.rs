@@ -60,7 +60,7 @@ pub fn main() {
     let mut kfold = KFolds::new(4);
     let (preds_and_ids, model_eval) = kfold
         .all_epochs_validation()
-        .all_epochs_r2()
+        .all_epochs_metrics()
         .compute_best_model()
         .run(&model, &data);
 
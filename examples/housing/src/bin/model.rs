@@ -29,7 +29,7 @@ pub fn main() {
         //     println!("Perf report: {:2} {:4} {:#?}", fold, epoch, report)
         // })
         .all_epochs_validation()
-        .all_epochs_r2()
+        .all_epochs_metrics()
         .compute_best_model()
         // .compute_avg_model()
         .run(&model, &data);
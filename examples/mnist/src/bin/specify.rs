@@ -43,7 +43,7 @@ fn main() {
         .end()
         .epochs(20)
         .batch_size(128)
-        .loss(Losses::BCE)
+        .loss(Losses::CategoricalCrossEntropy { label_smoothing: 0.0 })
         .build();
 
     //println!("{:#?}", model);
@@ -44,7 +44,7 @@ pub fn main() {
         .end()
         .epochs(20)
         .batch_size(128)
-        .loss(Losses::BCE)
+        .loss(Losses::CategoricalCrossEntropy { label_smoothing: 0.0 })
         .build();
 
     // training without installing a dedicated k-folds crate 
@@ -0,0 +1,40 @@
+use super::ActivationLayer;
+use crate::linalg::{Matrix, MatrixTrait, Scalar};
+
+// Keeps the variances strictly positive
+const MIN_VARIANCE: Scalar = 1e-6;
+
+fn softplus(x: Scalar) -> Scalar {
+    // ln(1 + e^x) computed without overflowing for large x
+    x.max(0.) + (-x.abs()).exp().ln_1p()
+}
+
+fn sigmoid(x: Scalar) -> Scalar {
+    1. / (1. + (-x).exp())
+}
+
+/// Activation of the outputs of a Gaussian head: the even rows (means) are left as is
+/// and a softplus is applied to the odd rows (variances).
+pub fn new() -> ActivationLayer {
+    ActivationLayer::new(
+        |m| {
+            Matrix::from_fn(m.dim().0, m.dim().1, |i, j| {
+                let x = m.index(i, j);
+                if i % 2 == 0 {
+                    x
+                } else {
+                    softplus(x) + MIN_VARIANCE
+                }
+            })
+        },
+        |m| {
+            Matrix::from_fn(m.dim().0, m.dim().1, |i, j| {
+                if i % 2 == 0 {
+                    1.
+                } else {
+                    sigmoid(m.index(i, j))
+                }
+            })
+        },
+    )
+}
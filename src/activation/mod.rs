@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use crate::{layer::Layer, linalg::Matrix};
 
 pub mod linear;
+pub mod mean_variance;
 pub mod relu;
 pub mod sigmoid;
 pub mod softmax;
@@ -25,6 +26,7 @@ pub struct ActivationLayer {
     output: Option<Matrix>,
     activation: ActivationFn,
     derivative: ActivationFnPrime,
+    softmax: bool,
 }
 
 impl ActivationLayer {
@@ -34,6 +36,7 @@ impl ActivationLayer {
             output: None,
             activation,
             derivative: ActivationFnPrime::ActivationFn(derivative),
+            softmax: false,
         }
     }
 
@@ -43,8 +46,21 @@ impl ActivationLayer {
             output: None,
             activation,
             derivative: ActivationFnPrime::GradDepActivationFn(derivative),
+            softmax: false,
         }
     }
+
+    /// Marks the activation as being a softmax, allowing losses to skip its jacobian (see `LossFunction::softmax_loss_prime`).
+    pub fn as_softmax(self) -> Self {
+        Self {
+            softmax: true,
+            ..self
+        }
+    }
+
+    pub fn is_softmax(&self) -> bool {
+        self.softmax
+    }
 }
 
 impl Layer for ActivationLayer {
@@ -77,7 +93,9 @@ pub enum Activation {
     Sigmoid,
     ReLU,
     Linear,
-    Softmax
+    Softmax,
+    /// Identity on the means (even outputs) and softplus on the variances (odd outputs) of a Gaussian head
+    MeanVariance,
 }
 
 impl Activation {
@@ -88,6 +106,7 @@ impl Activation {
             Self::Sigmoid => sigmoid::new(),
             Self::ReLU => relu::new(),
             Self::Softmax => softmax::new(),
+            Self::MeanVariance => mean_variance::new(),
         }
     }
 }
@@ -103,5 +103,5 @@ fn softmax_prime(m: &Matrix, output_gradient: &Matrix) -> Matrix {
 }
 
 pub fn new() -> ActivationLayer {
-    ActivationLayer::new_grad_dep(stablesoftmax, softmax_prime)
+    ActivationLayer::new_grad_dep(stablesoftmax, softmax_prime).as_softmax()
 }
@@ -1,12 +1,28 @@
-use std::{fs::File, io::Write};
+use std::{collections::BTreeMap, fs::File, io::Write};
 
 use serde::{Deserialize, Serialize};
 
-use crate::linalg::Scalar;
+use crate::{linalg::Scalar, vec_utils::class_of};
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct ModelEvaluation {
     pub folds: Vec<TrainingEvaluation>,
+    /// Candidate selected for each fold by a nested cross validation (see `NestedCrossValidation`), in the folds' order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub selected_candidates: Vec<SelectedCandidate>,
+}
+
+/// Candidate model winning the inner cross validation of an outer fold of a nested cross validation.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SelectedCandidate {
+    /// Index of the candidate in the candidates list
+    pub candidate: usize,
+    /// `Model::hashed_repr` of the candidate
+    pub model_hash: String,
+    /// Name of the metric the candidates were compared on
+    pub metric: String,
+    /// Average of the metric over the inner folds
+    pub inner_score: Scalar,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
@@ -14,23 +30,89 @@ pub struct TrainingEvaluation {
     pub epochs: Vec<EpochEvaluation>,
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LearningRateFinderReport {
+    pub learning_rates: Vec<Scalar>,
+    pub losses: Vec<Scalar>,
+    pub smoothed_losses: Vec<Scalar>,
+    pub suggested_learning_rate: Scalar,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct EpochEvaluation {
     pub train_loss: Scalar,
     pub test_loss_avg: Scalar,
     pub test_loss_std: Scalar,
+    /// R2 score of the validation predictions (the `r2` metric if the trainer computes it), -1 when the metrics aren't computed at this epoch.
     pub r2: Scalar,
+    /// Validation loss of each predicted feature (in the order of the dataset), on its known values only.
+    ///
+    /// Empty when the validation is not computed at this epoch.
+    #[serde(default)]
+    pub test_targets_losses: Vec<Scalar>,
+    /// Values of the trainer's metrics (see `metrics::Metric`) by name, when they are computed at this epoch.
+    #[serde(default)]
+    pub metrics: BTreeMap<String, Scalar>,
+    /// Validation metrics of classification models, when the validation is computed at this epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classification: Option<ClassificationMetrics>,
+}
+
+/// Metrics of a classifier on the validation set.
+///
+/// A sample's class is the index of its largest target value (one-hot encoded classes),
+/// or its target value if there is only one (class index, see `vec_utils::class_of`).
+///
+/// The predictions are the probabilities of each class, or the probability of the class 1
+/// if there is only one (binary classification).
+///
+/// The metrics are weighted by the samples' weights if any, except the confusion matrix counting the samples
+/// and the areas under the curves.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ClassificationMetrics {
+    pub accuracy: Scalar,
+    pub top_k: usize,
+    /// Proportion of samples whose class is among the `top_k` most probable predicted ones
+    pub top_k_accuracy: Scalar,
+    pub precision_macro: Scalar,
+    pub recall_macro: Scalar,
+    pub f1_macro: Scalar,
+    pub precision_micro: Scalar,
+    pub recall_micro: Scalar,
+    pub f1_micro: Scalar,
+    /// `confusion_matrix[i][j]` is the number of samples of class `i` predicted as class `j`
+    pub confusion_matrix: Vec<Vec<usize>>,
+    /// Area under the ROC curve (one-vs-rest macro average if there are more than two classes)
+    ///
+    /// None if it is undefined, when the validation set holds a single class.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roc_auc: Option<Scalar>,
+    /// Area under the precision-recall curve, as the average precision (one-vs-rest macro average if there are more than two classes)
+    ///
+    /// None if it is undefined, when the validation set holds a single class.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pr_auc: Option<Scalar>,
+    pub log_loss: Scalar,
 }
 
 impl ModelEvaluation {
     pub fn new_empty() -> Self {
-        Self { folds: vec![] }
+        Self {
+            folds: vec![],
+            selected_candidates: vec![],
+        }
     }
 
     pub fn add_fold(&mut self, fold: TrainingEvaluation) {
         self.folds.push(fold);
     }
 
+    /// Adds the fold of a nested cross validation along with its selected candidate.
+    pub fn add_selected_fold(&mut self, fold: TrainingEvaluation, selected: SelectedCandidate) {
+        self.folds.push(fold);
+        self.selected_candidates.push(selected);
+    }
+
     pub fn epochs_avg_train_loss(&self) -> Vec<Scalar> {
         let mut avg = vec![0.0; self.folds[0].epochs.len()];
         for fold in &self.folds {
@@ -114,6 +196,14 @@ impl TrainingEvaluation {
         self.epochs.push(epoch);
     }
 
+    /// Replaces the evaluation of the last epoch, when the final network is validated again after the training.
+    pub fn replace_final_epoch(&mut self, epoch: EpochEvaluation) {
+        match self.epochs.last_mut() {
+            Some(last) => *last = epoch,
+            None => self.epochs.push(epoch),
+        }
+    }
+
     pub fn get_final_epoch(&self) -> EpochEvaluation {
         self.epochs[self.epochs.len() - 1].clone()
     }
@@ -129,6 +219,14 @@ impl TrainingEvaluation {
     pub fn get_final_r2(&self) -> Scalar {
         self.get_final_epoch().r2
     }
+
+    pub fn get_final_metric(&self, name: &str) -> Option<Scalar> {
+        self.get_final_epoch().get_metric(name)
+    }
+
+    pub fn get_final_classification(&self) -> Option<ClassificationMetrics> {
+        self.get_final_epoch().classification
+    }
 }
 
 impl EpochEvaluation {
@@ -143,6 +241,334 @@ impl EpochEvaluation {
             test_loss_avg,
             test_loss_std,
             r2,
+            test_targets_losses: vec![],
+            classification: None,
+            metrics: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the metrics' values, as well as the `r2` field if the R2 score is among them.
+    pub fn with_metrics(self, metrics: BTreeMap<String, Scalar>) -> Self {
+        Self {
+            r2: metrics.get("r2").copied().unwrap_or(self.r2),
+            metrics,
+            ..self
+        }
+    }
+
+    /// Value of the metric named `name`, if computed at this epoch.
+    pub fn get_metric(&self, name: &str) -> Option<Scalar> {
+        self.metrics.get(name).copied()
+    }
+
+    pub fn with_classification(self, classification: ClassificationMetrics) -> Self {
+        Self {
+            classification: Some(classification),
+            ..self
+        }
+    }
+
+    pub fn with_test_targets_losses(self, test_targets_losses: Vec<Scalar>) -> Self {
+        Self {
+            test_targets_losses,
+            ..self
+        }
+    }
+}
+
+impl LearningRateFinderReport {
+    pub fn new(
+        learning_rates: Vec<Scalar>,
+        losses: Vec<Scalar>,
+        smoothed_losses: Vec<Scalar>,
+        suggested_learning_rate: Scalar,
+    ) -> Self {
+        Self {
+            learning_rates,
+            losses,
+            smoothed_losses,
+            suggested_learning_rate,
         }
     }
+
+    pub fn from_json_file<S: AsRef<str>>(path: S) -> Self {
+        let file = File::open(path.as_ref()).unwrap();
+        serde_json::from_reader(file).unwrap()
+    }
+
+    pub fn to_json_file<S: AsRef<str>>(&self, path: S) {
+        let mut file = File::create(path.as_ref()).unwrap();
+        let json_string = serde_json::to_string_pretty(self).unwrap();
+        file.write_all(json_string.as_bytes()).unwrap();
+    }
+
+    pub fn get_n_steps(&self) -> usize {
+        self.learning_rates.len()
+    }
+}
+
+// Predicted probability of each class, a single prediction being the probability of the class 1
+fn classes_probabilities(y_pred: &[Scalar]) -> Vec<Scalar> {
+    if y_pred.len() == 1 {
+        vec![1. - y_pred[0], y_pred[0]]
+    } else {
+        y_pred.to_vec()
+    }
+}
+
+impl ClassificationMetrics {
+    /// Computes the metrics from the targets and predictions of each sample, ignoring the samples with missing (NaN) targets.
+    ///
+    /// `weights` holds the weight of each sample, if they are weighted.
+    ///
+    /// None if no sample has known targets.
+    pub fn compute(
+        y_true: &[Vec<Scalar>],
+        y_pred: &[Vec<Scalar>],
+        weights: Option<&[Scalar]>,
+        top_k: usize,
+    ) -> Option<Self> {
+        assert!(y_true.len() == y_pred.len());
+
+        let (classes, probabilities, weights) = classes_and_probabilities(y_true, y_pred, weights);
+        if classes.is_empty() {
+            return None;
+        }
+        let n_classes = probabilities.first().map_or(0, |p| p.len());
+        let predicted = probabilities.iter().map(|p| class_of(p)).collect::<Vec<_>>();
+
+        let (precision_macro, recall_macro, f1_macro) =
+            macro_precision_recall_f1(&weighted_confusion_matrix(&classes, &predicted, n_classes, &weights));
+        // Each sample has exactly one class and one prediction, so the micro averages are the accuracy
+        let accuracy = accuracy(&classes, &predicted, &weights);
+
+        Some(Self {
+            accuracy,
+            top_k,
+            top_k_accuracy: top_k_accuracy(&classes, &probabilities, top_k, &weights),
+            precision_macro,
+            recall_macro,
+            f1_macro,
+            precision_micro: accuracy,
+            recall_micro: accuracy,
+            f1_micro: accuracy,
+            confusion_matrix: confusion_matrix(&classes, &predicted, n_classes),
+            roc_auc: one_vs_rest(&classes, &probabilities, roc_auc),
+            pr_auc: one_vs_rest(&classes, &probabilities, average_precision),
+            log_loss: log_loss(&classes, &probabilities, &weights),
+        })
+    }
+}
+
+/// Classes of the samples, predicted probabilities of each class (see `ClassificationMetrics`)
+/// and weights of the samples (1 if they are not weighted), leaving out the samples with missing (NaN) targets.
+///
+/// Panics if a class is not one of the predicted ones, such as a class 2 with a single (binary) output.
+pub fn classes_and_probabilities(
+    y_true: &[Vec<Scalar>],
+    y_pred: &[Vec<Scalar>],
+    weights: Option<&[Scalar]>,
+) -> (Vec<usize>, Vec<Vec<Scalar>>, Vec<Scalar>) {
+    let mut classes = vec![];
+    let mut probabilities = vec![];
+    let mut samples_weights = vec![];
+    for (i, (y, p)) in y_true.iter().zip(y_pred.iter()).enumerate() {
+        if y.iter().any(|v| v.is_nan()) {
+            continue;
+        }
+        let class = class_of(y);
+        let sample_probabilities = classes_probabilities(p);
+        assert!(
+            class < sample_probabilities.len(),
+            "The class {} of a sample is not one of the {} predicted classes",
+            class,
+            sample_probabilities.len()
+        );
+        classes.push(class);
+        probabilities.push(sample_probabilities);
+        samples_weights.push(weights.map_or(1., |weights| weights[i]));
+    }
+    (classes, probabilities, samples_weights)
+}
+
+/// Computes a binary curve metric (such as `roc_auc`) on the class 1 if there are two classes,
+/// or averages it over each class against the others.
+///
+/// None if the curve of no class is defined, which needs both positive and negative samples.
+pub fn one_vs_rest(
+    classes: &[usize],
+    probabilities: &[Vec<Scalar>],
+    metric: fn(&[Scalar], &[bool]) -> Scalar,
+) -> Option<Scalar> {
+    let n_classes = probabilities.first().map_or(0, |p| p.len());
+    let binary_metric = |class: usize| {
+        let scores = probabilities.iter().map(|p| p[class]).collect::<Vec<_>>();
+        let positives = classes.iter().map(|c| *c == class).collect::<Vec<_>>();
+        // A class absent from the validation set has no defined curve
+        if positives.iter().any(|p| *p) && positives.iter().any(|p| !*p) {
+            Some(metric(&scores, &positives))
+        } else {
+            None
+        }
+    };
+
+    if n_classes == 2 {
+        binary_metric(1)
+    } else {
+        let values = (0..n_classes).filter_map(binary_metric).collect::<Vec<_>>();
+        if values.is_empty() {
+            None
+        } else {
+            Some(mean(&values))
+        }
+    }
+}
+
+fn mean(values: &[Scalar]) -> Scalar {
+    values.iter().sum::<Scalar>() / values.len() as Scalar
+}
+
+fn check_class(class: usize, n_classes: usize) {
+    assert!(class < n_classes, "The class {} is not one of the {} classes", class, n_classes);
+}
+
+fn weighted_mean(values: impl Iterator<Item = Scalar>, weights: &[Scalar]) -> Scalar {
+    values.zip(weights.iter()).map(|(v, w)| v * w).sum::<Scalar>() / weights.iter().sum::<Scalar>()
+}
+
+/// Weighted proportion of the predicted classes equal to the true ones.
+pub fn accuracy(classes: &[usize], predicted: &[usize], weights: &[Scalar]) -> Scalar {
+    let correct = classes
+        .iter()
+        .zip(predicted.iter())
+        .map(|(c, p)| if c == p { 1. } else { 0. });
+    weighted_mean(correct, weights)
+}
+
+/// Weighted proportion of the true classes among the `k` most probable classes of each sample.
+pub fn top_k_accuracy(classes: &[usize], probabilities: &[Vec<Scalar>], k: usize, weights: &[Scalar]) -> Scalar {
+    let correct = classes.iter().zip(probabilities.iter()).map(|(class, probabilities)| {
+        check_class(*class, probabilities.len());
+        let class_probability = probabilities[*class];
+        // The class is in the top k if less than k classes are strictly more probable
+        if probabilities.iter().filter(|p| **p > class_probability).count() < k {
+            1.
+        } else {
+            0.
+        }
+    });
+    weighted_mean(correct, weights)
+}
+
+/// `confusion_matrix[i][j]` is the number of samples of class `i` predicted as class `j`.
+pub fn confusion_matrix(classes: &[usize], predicted: &[usize], n_classes: usize) -> Vec<Vec<usize>> {
+    let mut matrix = vec![vec![0; n_classes]; n_classes];
+    for (class, prediction) in classes.iter().zip(predicted.iter()) {
+        check_class(*class, n_classes);
+        check_class(*prediction, n_classes);
+        matrix[*class][*prediction] += 1;
+    }
+    matrix
+}
+
+/// Same as `confusion_matrix` but `confusion_matrix[i][j]` is the total weight of the samples of class `i` predicted as class `j`.
+pub fn weighted_confusion_matrix(
+    classes: &[usize],
+    predicted: &[usize],
+    n_classes: usize,
+    weights: &[Scalar],
+) -> Vec<Vec<Scalar>> {
+    let mut matrix = vec![vec![0.; n_classes]; n_classes];
+    for ((class, prediction), weight) in classes.iter().zip(predicted.iter()).zip(weights.iter()) {
+        check_class(*class, n_classes);
+        check_class(*prediction, n_classes);
+        matrix[*class][*prediction] += weight;
+    }
+    matrix
+}
+
+/// Precision, recall and F1 score of each class averaged over the classes, from a (weighted) confusion matrix.
+///
+/// The score of a class that is never predicted (or never present) is 0.
+pub fn macro_precision_recall_f1(confusion_matrix: &[Vec<Scalar>]) -> (Scalar, Scalar, Scalar) {
+    let mut precisions = vec![];
+    let mut recalls = vec![];
+    let mut f1s = vec![];
+    for (class, row) in confusion_matrix.iter().enumerate() {
+        let true_positives = row[class];
+        let predicted = confusion_matrix.iter().map(|r| r[class]).sum::<Scalar>();
+        let actual = row.iter().sum::<Scalar>();
+
+        let precision = if predicted > 0. { true_positives / predicted } else { 0. };
+        let recall = if actual > 0. { true_positives / actual } else { 0. };
+        let f1 = if precision + recall > 0. {
+            2. * precision * recall / (precision + recall)
+        } else {
+            0.
+        };
+        precisions.push(precision);
+        recalls.push(recall);
+        f1s.push(f1);
+    }
+    (mean(&precisions), mean(&recalls), mean(&f1s))
+}
+
+// Indices of the samples sorted by decreasing score
+fn ranked(scores: &[Scalar]) -> Vec<usize> {
+    let mut indices = (0..scores.len()).collect::<Vec<_>>();
+    indices.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+    indices
+}
+
+/// Area under the ROC curve of scores predicting the `positives`, computed from the ranks of the scores (ties averaged).
+pub fn roc_auc(scores: &[Scalar], positives: &[bool]) -> Scalar {
+    let mut indices = (0..scores.len()).collect::<Vec<_>>();
+    indices.sort_by(|a, b| scores[*a].total_cmp(&scores[*b]));
+
+    // Average rank (starting at 1) of each group of equal scores
+    let mut ranks = vec![0.; scores.len()];
+    let mut i = 0;
+    while i < indices.len() {
+        let mut j = i;
+        while j + 1 < indices.len() && scores[indices[j + 1]] == scores[indices[i]] {
+            j += 1;
+        }
+        let rank = (i + j) as Scalar / 2. + 1.;
+        for index in &indices[i..=j] {
+            ranks[*index] = rank;
+        }
+        i = j + 1;
+    }
+
+    let n_positives = positives.iter().filter(|p| **p).count() as Scalar;
+    let n_negatives = positives.len() as Scalar - n_positives;
+    let positives_ranks = ranks
+        .iter()
+        .zip(positives.iter())
+        .filter(|(_, p)| **p)
+        .map(|(r, _)| r)
+        .sum::<Scalar>();
+    (positives_ranks - n_positives * (n_positives + 1.) / 2.) / (n_positives * n_negatives)
+}
+
+/// Average precision of scores predicting the `positives`: the mean of the precisions at the rank of each positive.
+pub fn average_precision(scores: &[Scalar], positives: &[bool]) -> Scalar {
+    let mut true_positives = 0.;
+    let mut precisions_sum = 0.;
+    for (rank, index) in ranked(scores).iter().enumerate() {
+        if positives[*index] {
+            true_positives += 1.;
+            precisions_sum += true_positives / (rank + 1) as Scalar;
+        }
+    }
+    precisions_sum / true_positives
+}
+
+/// Weighted mean negative log of the probability predicted for the true class.
+pub fn log_loss(classes: &[usize], probabilities: &[Vec<Scalar>], weights: &[Scalar]) -> Scalar {
+    let losses = classes
+        .iter()
+        .zip(probabilities.iter())
+        .map(|(class, probabilities)| -probabilities[*class].clamp(1e-7, 1.).ln());
+    weighted_mean(losses, weights)
 }
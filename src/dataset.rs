@@ -106,7 +106,12 @@ impl Dataset {
     pub fn in_features_names(&self) -> Vec<&str> {
         let mut names = Vec::new();
         for feature in &self.features {
-            if !feature.predicted && !feature.is_id && !feature.date_format.is_some() {
+            if !feature.predicted
+                && !feature.is_id
+                && !feature.sample_weight
+                && !feature.group
+                && !feature.date_format.is_some()
+            {
                 names.push(feature.name.as_str());
             }
         }
@@ -212,6 +217,24 @@ impl Dataset {
         }
         None
     }
+
+    pub fn get_sample_weight_column(&self) -> Option<&str> {
+        for feature in &self.features {
+            if feature.sample_weight {
+                return Some(feature.name.as_str());
+            }
+        }
+        None
+    }
+
+    pub fn get_group_column(&self) -> Option<&str> {
+        for feature in &self.features {
+            if feature.group {
+                return Some(feature.name.as_str());
+            }
+        }
+        None
+    }
 }
 
 /// A structure that holds metadata of a _feature_ (aka. a "column") of a data table.
@@ -246,6 +269,10 @@ pub struct Feature {
     pub one_hot_encoded: bool,
     #[serde(default)]
     pub is_id: bool,
+    #[serde(default)]
+    pub sample_weight: bool,
+    #[serde(default)]
+    pub group: bool,
 }
 
 impl Feature {
@@ -291,6 +318,8 @@ impl Feature {
 /// - `UsedInModel`: Disables the pruning of the feature at the end of the pipeline. Features are all not pruned by default.
 /// - `Predicted`: Sets the feature as a _predicted feature_. Features are all not _predicted features_ by default.
 /// - `IsId`: Identifies the feature as an id. Features are all not ids by default.
+/// - `SampleWeight`: Identifies the feature as the weight of each sample in the loss and the validation metrics. It is not an input of the model.
+/// - `Group`: Identifies the feature as the group of each sample, whose samples are kept in the same fold by a group k-fold. It is not an input of the model.
 /// - `DateFormat`: The date format to use for date/time features.
 ///
 /// **Feature replacement/mapping tags**:
@@ -333,6 +362,8 @@ pub enum FeatureTags<'a> {
     /// The `Name` tag configurationifies the name of the feature.
     Name(&'a str),
     /// The `Predicted` tag configurationifies that the feature is an output feature.
+    ///
+    /// Its missing (null or NaN) values are masked out of the loss, so that rows may lack some of the predicted features.
     Predicted,
     /// The `DateFormat` tag configurationifies the date format to use for date/time features.
     DateFormat(&'a str),
@@ -354,6 +385,10 @@ pub enum FeatureTags<'a> {
     UsedInModel,
     /// The `IsId` tag identifies the feature as an id.
     IsId,
+    /// The `SampleWeight` tag identifies the feature as the weight of each sample in the loss and the validation metrics.
+    SampleWeight,
+    /// The `Group` tag identifies the feature as the group of each sample for the group k-fold cross validation.
+    Group,
     /// The `AddExtractedMonth` tag enables the extracted month feature extraction from that feature.
     AddExtractedMonth,
     /// The `AddExtractedTimestamp` tag enables the extracted Unix timestamp feature extraction from that feature.
@@ -423,6 +458,8 @@ impl<'a> FeatureTags<'a> {
             FeatureTags::OneHotEncode => feature.one_hot_encoded = value,
             FeatureTags::UsedInModel => feature.used_in_model = value,
             FeatureTags::IsId => feature.is_id = value,
+            FeatureTags::SampleWeight => feature.sample_weight = value,
+            FeatureTags::Group => feature.group = value,
             FeatureTags::AddFeatureExtractedMonth(with_extracted_month) => {
                 feature.with_extracted_month =
                     Some(Box::new(Feature::from_tags(with_extracted_month)))
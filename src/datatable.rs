@@ -6,6 +6,7 @@ use std::{
 };
 
 use crate::linalg::Scalar;
+use crate::random::gen_seed;
 
 use polars::prelude::*;
 
@@ -314,10 +315,22 @@ impl DataTable {
         self.sample(None, true)
     }
 
+    /// Samples `n` rows (all of them by default) using the library's random number generator.
+    ///
+    /// See `random::set_seed` to make it reproducible.
     pub fn sample(&self, n: Option<usize>, shuffle: bool) -> Self {
         let columns = self
             .0
-            .sample_n(n.unwrap_or(self.0.shape().0), false, shuffle, None)
+            .sample_n(n.unwrap_or(self.0.shape().0), false, shuffle, Some(gen_seed()))
+            .unwrap();
+        Self(columns)
+    }
+
+    /// Samples `n` rows with replacement, so that `n` may exceed the number of rows.
+    pub fn resample(&self, n: usize) -> Self {
+        let columns = self
+            .0
+            .sample_n(n, true, true, Some(gen_seed()))
             .unwrap();
         Self(columns)
     }
@@ -361,14 +374,14 @@ impl DataTable {
             .set_at_idx(
                 series
                     .into_iter()
-                    .filter(|v| {
+                    .enumerate()
+                    .filter(|(_, v)| {
                         if let Some(v) = v {
                             !f(*v as Scalar)
                         } else {
                             true
                         }
                     })
-                    .enumerate()
                     .map(|(i, _)| i as u32),
                 Some(false),
             )
@@ -407,6 +420,23 @@ impl DataTable {
         (train, validation)
     }
 
+    /// Selects the rows at the given indices, in that order.
+    pub fn take_rows(&self, indices: &[usize]) -> Self {
+        let indices = IdxCa::from_vec("indices", indices.iter().map(|i| *i as IdxSize).collect());
+        Self(self.0.take(&indices).unwrap())
+    }
+
+    /// Values of the column converted to strings, nulls being empty strings.
+    pub fn column_to_strings(&self, column: &str) -> Vec<String> {
+        let series = self.0.column(column).unwrap().cast(&DataType::Utf8).unwrap();
+        series
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .map(|v| v.unwrap_or("").to_string())
+            .collect()
+    }
+
     pub fn split_ratio(&self, ratio: Scalar) -> (Self, Self) {
         let nrows = self.0.shape().0;
         let rows_train = (nrows as Scalar * ratio) as usize;
@@ -428,12 +458,21 @@ impl DataTable {
     }
 
     /// Returns a vector of vectors where each vector is a row of the dataset
+    ///
+    /// Panics if a value is missing (null), see `to_vectors_with_missing`.
     pub fn to_vectors(&self) -> Vec<Vec<Scalar>> {
-        let mut columns_vec = vec![];
-        let columns = self.0.get_column_names();
-        for column in columns {
-            columns_vec.push(self.column_to_vector(column));
-        }
+        self.rows_vectors(Self::series_as_vector)
+    }
+
+    /// Same as `to_vectors` but the missing (null) values become NaN.
+    ///
+    /// Meant for the predicted features only, whose missing values are masked out of the losses.
+    pub fn to_vectors_with_missing(&self) -> Vec<Vec<Scalar>> {
+        self.rows_vectors(Self::series_as_vector_with_missing)
+    }
+
+    fn rows_vectors(&self, series_as_vector: fn(&Series) -> Vec<Scalar>) -> Vec<Vec<Scalar>> {
+        let columns_vec = self.0.iter().map(series_as_vector).collect::<Vec<_>>();
 
         let mut vectors = vec![vec![0.0; self.0.shape().1]; self.0.shape().0];
         let mut x_id = 0;
@@ -470,13 +509,28 @@ impl DataTable {
     }
 
     fn series_as_vector(series: &Series) -> Vec<Scalar> {
+        let values = series.cast(&DataType::Float64).unwrap();
+
+        values
+            .f64()
+            .unwrap()
+            .into_iter()
+            .map(|p| {
+                p.unwrap_or_else(|| panic!("The column {} has missing values", series.name()))
+                    as Scalar
+            })
+            .collect()
+    }
+
+    // Null values become NaN, which marks missing targets for the losses
+    fn series_as_vector_with_missing(series: &Series) -> Vec<Scalar> {
         let series = series.cast(&DataType::Float64).unwrap();
 
         series
             .f64()
             .unwrap()
             .into_iter()
-            .map(|p| p.unwrap() as Scalar)
+            .map(|p| p.map_or(Scalar::NAN, |p| p as Scalar))
             .collect()
     }
 
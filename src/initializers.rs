@@ -1,39 +1,145 @@
+use rand_distr::{Distribution, Normal};
 use serde::{Deserialize, Serialize};
 
-use crate::linalg::{Matrix, MatrixTrait, Scalar};
+use crate::{
+    linalg::{Matrix, MatrixTrait, Scalar},
+    random::with_rng,
+};
 
+// Standard deviation of a unit normal distribution truncated at two standard deviations.
+// Used to compensate the truncation in variance scaling initializers (same as keras).
+const TRUNCATED_NORMAL_STD_CORRECTION: Scalar = 0.879_625_7;
+
+// About variance scaling initializers:
+// Glorot: http://proceedings.mlr.press/v9/glorot10a/glorot10a.pdf
+// He: https://arxiv.org/pdf/1502.01852.pdf
+// LeCun: http://yann.lecun.com/exdb/publis/pdf/lecun-98b.pdf
+// Orthogonal: https://arxiv.org/pdf/1312.6120.pdf
 #[derive(Serialize, Debug, Deserialize, Clone)]
 pub enum Initializers {
     Zeros,
+    Constant(Scalar),
     Uniform,
     UniformSigned,
     GlorotUniform,
+    GlorotNormal,
+    HeUniform,
+    HeNormal,
+    LeCunUniform,
+    LeCunNormal,
+    /// Normal distribution whose values further than two standard deviations from the mean are redrawn.
+    TruncatedNormal { mean: Scalar, std_dev: Scalar },
+    Orthogonal,
 }
 
 impl Initializers {
+    /// Generates the `(nrow, ncol)` weights of a layer mapping `ncol` inputs to `nrow` outputs.
     pub fn gen_matrix(&self, nrow: usize, ncol: usize) -> Matrix {
+        self.gen_matrix_with_fans(nrow, ncol, ncol, nrow)
+    }
+
+    pub fn gen_vector(&self, nrow: usize) -> Matrix {
+        // not configurationified on vectors in the original papers
+        // but taken from keras' implementation
+        self.gen_matrix_with_fans(nrow, 1, nrow, nrow)
+    }
+
+    /// Generates a `(nrow, ncol)` matrix of parameters.
+    ///
+    /// `fan_in` and `fan_out` are the number of inputs and outputs each parameter is connected to.
+    /// They are only used by the variance scaling initializers (Glorot, He, LeCun).
+    pub fn gen_matrix_with_fans(
+        &self,
+        nrow: usize,
+        ncol: usize,
+        fan_in: usize,
+        fan_out: usize,
+    ) -> Matrix {
+        let fan_in = fan_in as Scalar;
+        let fan_out = fan_out as Scalar;
+
         match self {
             Initializers::Zeros => Matrix::zeros(nrow, ncol),
+            Initializers::Constant(value) => Matrix::constant(nrow, ncol, *value),
             Initializers::Uniform => Matrix::random_uniform(nrow, ncol, 0.0, 1.0),
             Initializers::UniformSigned => Matrix::random_uniform(nrow, ncol, -1.0, 1.0),
             Initializers::GlorotUniform => {
-                let limit = (6. / (ncol + nrow) as Scalar).sqrt();
+                let limit = (6. / (fan_in + fan_out)).sqrt();
+                Matrix::random_uniform(nrow, ncol, -limit, limit)
+            }
+            Initializers::GlorotNormal => {
+                let std_dev = (2. / (fan_in + fan_out)).sqrt() / TRUNCATED_NORMAL_STD_CORRECTION;
+                truncated_normal(nrow, ncol, 0.0, std_dev)
+            }
+            Initializers::HeUniform => {
+                let limit = (6. / fan_in).sqrt();
+                Matrix::random_uniform(nrow, ncol, -limit, limit)
+            }
+            Initializers::HeNormal => {
+                let std_dev = (2. / fan_in).sqrt() / TRUNCATED_NORMAL_STD_CORRECTION;
+                truncated_normal(nrow, ncol, 0.0, std_dev)
+            }
+            Initializers::LeCunUniform => {
+                let limit = (3. / fan_in).sqrt();
                 Matrix::random_uniform(nrow, ncol, -limit, limit)
             }
+            Initializers::LeCunNormal => {
+                let std_dev = (1. / fan_in).sqrt() / TRUNCATED_NORMAL_STD_CORRECTION;
+                truncated_normal(nrow, ncol, 0.0, std_dev)
+            }
+            Initializers::TruncatedNormal { mean, std_dev } => {
+                truncated_normal(nrow, ncol, *mean, *std_dev)
+            }
+            Initializers::Orthogonal => orthogonal(nrow, ncol),
         }
     }
+}
 
-    pub fn gen_vector(&self, nrow: usize) -> Matrix {
-        match self {
-            Initializers::Zeros => Matrix::zeros(nrow, 1),
-            Initializers::Uniform => Matrix::random_uniform(nrow, 1, 0.0, 1.0),
-            Initializers::UniformSigned => Matrix::random_uniform(nrow, 1, -1.0, 1.0),
-            Initializers::GlorotUniform => {
-                // not configurationified on vectors in the original paper
-                // but taken from keras' implementation
-                let limit = (6. / (nrow) as Scalar).sqrt();
-                Matrix::random_uniform(nrow, 1, -limit, limit)
+fn truncated_normal(nrow: usize, ncol: usize, mean: Scalar, std_dev: Scalar) -> Matrix {
+    let normal = Normal::new(mean, std_dev).unwrap();
+    with_rng(|rng| {
+        Matrix::from_fn(nrow, ncol, |_, _| loop {
+            let value = normal.sample(rng);
+            if (value - mean).abs() <= 2. * std_dev {
+                break value;
             }
+        })
+    })
+}
+
+/// Generates a matrix whose rows or columns (whichever are fewer) are orthonormal,
+/// by applying the Gram-Schmidt process to normally distributed vectors.
+fn orthogonal(nrow: usize, ncol: usize) -> Matrix {
+    let (n_vectors, dim) = if nrow >= ncol {
+        (ncol, nrow)
+    } else {
+        (nrow, ncol)
+    };
+
+    let normal = Normal::new(0.0, 1.0).unwrap();
+    let mut vectors: Vec<Vec<Scalar>> = Vec::with_capacity(n_vectors);
+
+    while vectors.len() < n_vectors {
+        let mut vector: Vec<Scalar> =
+            with_rng(|rng| (0..dim).map(|_| normal.sample(rng)).collect());
+        for other in vectors.iter() {
+            let projection: Scalar = vector.iter().zip(other).map(|(a, b)| a * b).sum();
+            vector
+                .iter_mut()
+                .zip(other)
+                .for_each(|(a, b)| *a -= projection * b);
         }
+        let norm = vector.iter().map(|a| a * a).sum::<Scalar>().sqrt();
+        // a (very unlikely) linearly dependent draw is simply redrawn
+        if norm > 1e-6 {
+            vector.iter_mut().for_each(|a| *a /= norm);
+            vectors.push(vector);
+        }
+    }
+
+    if nrow >= ncol {
+        Matrix::from_column_leading_vector2(&vectors)
+    } else {
+        Matrix::from_row_leading_vector2(&vectors)
     }
 }
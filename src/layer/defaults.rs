@@ -1,5 +1,7 @@
 use crate::{
+    activation::Activation,
     initializers::Initializers,
+    linalg::Scalar,
     optimizer::{sgd, Optimizers},
 };
 
@@ -11,6 +13,24 @@ pub fn default_weights_initializer() -> Initializers {
     Initializers::GlorotUniform
 }
 
+/// He initialization for ReLU layers, Glorot initialization for the others.
+pub fn default_weights_initializer_for(activation: Activation) -> Initializers {
+    match activation {
+        Activation::ReLU => Initializers::HeUniform,
+        Activation::Tanh
+        | Activation::Sigmoid
+        | Activation::Linear
+        | Activation::Softmax
+        | Activation::MeanVariance => {
+            default_weights_initializer()
+        }
+    }
+}
+
+pub fn default_learning_rate_multiplier() -> Scalar {
+    1.0
+}
+
 pub fn default_biases_optimizer() -> Optimizers {
     sgd()
 }
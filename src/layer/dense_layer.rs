@@ -5,10 +5,10 @@ use crate::{
     initializers::Initializers,
     layer::Layer,
     linalg::Matrix,
-    optimizer::{Optimizers},
+    optimizer::{OptimizerState, Optimizers},
 };
 
-use super::LearnableLayer;
+use super::{scale_parameters_update, GradientLayer, LearnableLayer, TrainableLayer};
 
 pub struct DenseLayer {
     // i inputs, j outputs, i x j connections
@@ -19,6 +19,11 @@ pub struct DenseLayer {
     pub biases: Matrix,
     weights_optimizer: Optimizers,
     biases_optimizer: Optimizers,
+    trainable: bool,
+    learning_rate_multiplier: Scalar,
+    // when accumulating, the sums of the weights and biases gradients since they were last taken
+    accumulate_gradients: bool,
+    gradients: Option<(Matrix, Matrix)>,
 }
 
 impl DenseLayer {
@@ -41,6 +46,23 @@ impl DenseLayer {
             input: None,
             weights_optimizer,
             biases_optimizer,
+            trainable: true,
+            learning_rate_multiplier: 1.0,
+            accumulate_gradients: false,
+            gradients: None,
+        }
+    }
+
+    fn update_parameters(&mut self, epoch: usize, weights_gradient: &Matrix, biases_gradient: &Matrix) {
+        if self.trainable {
+            let weights =
+                self.weights_optimizer
+                    .update_parameters(epoch, &self.weights, weights_gradient);
+            let biases =
+                self.biases_optimizer
+                    .update_parameters(epoch, &self.biases, biases_gradient);
+            self.weights = scale_parameters_update(&self.weights, weights, self.learning_rate_multiplier);
+            self.biases = scale_parameters_update(&self.biases, biases, self.learning_rate_multiplier);
         }
     }
 }
@@ -77,12 +99,17 @@ impl Layer for DenseLayer {
 
         let input_gradient = self.weights.transpose().dot(&output_gradient);
 
-        self.weights =
-            self.weights_optimizer
-                .update_parameters(epoch, &self.weights, &weights_gradient);
-        self.biases =
-            self.biases_optimizer
-                .update_parameters(epoch, &self.biases, &biases_gradient);
+        if self.accumulate_gradients {
+            self.gradients = Some(match self.gradients.take() {
+                Some((weights_sum, biases_sum)) => (
+                    weights_sum.component_add(weights_gradient),
+                    biases_sum.component_add(&biases_gradient),
+                ),
+                None => (weights_gradient.clone(), biases_gradient),
+            });
+        } else {
+            self.update_parameters(epoch, weights_gradient, &biases_gradient);
+        }
 
         input_gradient
     }
@@ -103,6 +130,61 @@ impl LearnableLayer for DenseLayer {
         self.weights = Matrix::from_column_leading_vector2(&weights);
         self.biases = Matrix::from_column_vector(&biases);
     }
+
+    // the weights' optimizer state then the biases' one
+    fn get_optimizers_state(&self) -> Vec<OptimizerState> {
+        vec![self.weights_optimizer.get_state(), self.biases_optimizer.get_state()]
+    }
+
+    fn set_optimizers_state(&mut self, state: &[OptimizerState]) {
+        if let [weights_state, biases_state] = state {
+            self.weights_optimizer.set_state(weights_state);
+            self.biases_optimizer.set_state(biases_state);
+        }
+    }
+}
+
+impl GradientLayer for DenseLayer {
+    fn set_accumulate_gradients(&mut self, accumulate: bool) {
+        self.accumulate_gradients = accumulate;
+        self.gradients = None;
+    }
+
+    fn take_gradients(&mut self) -> Option<Vec<Vec<Scalar>>> {
+        self.gradients.take().map(|(weights_gradient, biases_gradient)| {
+            let mut gradients = weights_gradient.get_data_col_leading();
+            gradients.push(biases_gradient.get_column(0));
+            gradients
+        })
+    }
+
+    fn apply_gradients(&mut self, epoch: usize, gradients: &[Vec<Scalar>]) {
+        let mut weights_gradient = gradients.to_vec();
+        let biases_gradient = weights_gradient.pop().unwrap();
+        self.update_parameters(
+            epoch,
+            &Matrix::from_column_leading_vector2(&weights_gradient),
+            &Matrix::from_column_vector(&biases_gradient),
+        );
+    }
+}
+
+impl TrainableLayer for DenseLayer {
+    fn is_trainable(&self) -> bool {
+        self.trainable
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.trainable = trainable;
+    }
+
+    fn get_learning_rate_multiplier(&self) -> Scalar {
+        self.learning_rate_multiplier
+    }
+
+    fn set_learning_rate_multiplier(&mut self, multiplier: Scalar) {
+        self.learning_rate_multiplier = multiplier;
+    }
 }
 
 impl fmt::Debug for DenseLayer {
@@ -3,10 +3,12 @@ use std::cmp::Ordering;
 use rand::Rng;
 
 use crate::linalg::{Matrix, MatrixTrait, Scalar};
+use crate::optimizer::OptimizerState;
+use crate::random::with_rng;
 use crate::network::NetworkLayer;
 use crate::{activation::ActivationLayer, layer::dense_layer::DenseLayer, layer::Layer};
 
-use super::{DropoutLayer, LearnableLayer, ParameterableLayer};
+use super::{DropoutLayer, GradientLayer, LearnableLayer, ParameterableLayer, TrainableLayer};
 
 #[derive(Debug)]
 pub struct FullLayer {
@@ -31,17 +33,18 @@ impl FullLayer {
 
     fn generate_dropout_mask(&mut self, output_shape: (usize, usize)) -> Option<(Matrix, Scalar)> {
         if let Some(dropout_rate) = self.dropout_rate {
-            let mut rng = rand::thread_rng();
-            let dropout_mask = Matrix::from_fn(output_shape.0, output_shape.1, |_, _| {
-                if rng
-                    .gen_range((0.0 as Scalar)..(1.0 as Scalar))
-                    .total_cmp(&self.dropout_rate.unwrap())
-                    == Ordering::Greater
-                {
-                    1.0
-                } else {
-                    0.0
-                }
+            let dropout_mask = with_rng(|rng| {
+                Matrix::from_fn(output_shape.0, output_shape.1, |_, _| {
+                    if rng
+                        .gen_range((0.0 as Scalar)..(1.0 as Scalar))
+                        .total_cmp(&dropout_rate)
+                        == Ordering::Greater
+                    {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                })
             });
             Some((dropout_mask, dropout_rate))
         } else {
@@ -84,7 +87,21 @@ impl Layer for FullLayer {
     }
 }
 
-impl NetworkLayer for FullLayer {}
+impl NetworkLayer for FullLayer {
+    fn ends_with_softmax(&self) -> bool {
+        self.activation.is_softmax()
+    }
+
+    fn backward_skipping_activation(&mut self, epoch: usize, activation_input_gradient: Matrix) -> Matrix {
+        let input_gradient = self.dense.backward(epoch, activation_input_gradient);
+
+        if let Some(mask) = &self.mask {
+            input_gradient.component_mul(mask)
+        } else {
+            input_gradient
+        }
+    }
+}
 
 impl ParameterableLayer for FullLayer {
     fn as_learnable_layer(&self) -> Option<&dyn LearnableLayer> {
@@ -98,6 +115,18 @@ impl ParameterableLayer for FullLayer {
     fn as_dropout_layer(&mut self) -> Option<&mut dyn DropoutLayer> {
         Some(self)
     }
+
+    fn as_trainable_layer(&self) -> Option<&dyn TrainableLayer> {
+        Some(self)
+    }
+
+    fn as_trainable_layer_mut(&mut self) -> Option<&mut dyn TrainableLayer> {
+        Some(self)
+    }
+
+    fn as_gradient_layer_mut(&mut self) -> Option<&mut dyn GradientLayer> {
+        Some(&mut self.dense)
+    }
 }
 
 impl LearnableLayer for FullLayer {
@@ -110,6 +139,32 @@ impl LearnableLayer for FullLayer {
     fn set_learnable_parameters(&mut self, params_matrix: &Vec<Vec<Scalar>>) {
         self.dense.set_learnable_parameters(params_matrix)
     }
+
+    fn get_optimizers_state(&self) -> Vec<OptimizerState> {
+        self.dense.get_optimizers_state()
+    }
+
+    fn set_optimizers_state(&mut self, state: &[OptimizerState]) {
+        self.dense.set_optimizers_state(state)
+    }
+}
+
+impl TrainableLayer for FullLayer {
+    fn is_trainable(&self) -> bool {
+        self.dense.is_trainable()
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.dense.set_trainable(trainable)
+    }
+
+    fn get_learning_rate_multiplier(&self) -> Scalar {
+        self.dense.get_learning_rate_multiplier()
+    }
+
+    fn set_learning_rate_multiplier(&mut self, multiplier: Scalar) {
+        self.dense.set_learning_rate_multiplier(multiplier)
+    }
 }
 
 impl DropoutLayer for FullLayer {
@@ -1,6 +1,7 @@
 use crate::{
     activation::Activation,
-    linalg::{Matrix, Scalar},
+    linalg::{Matrix, MatrixTrait, Scalar},
+    optimizer::OptimizerState,
 };
 
 pub mod defaults;
@@ -28,6 +29,13 @@ pub trait ParameterableLayer {
     fn as_learnable_layer(&self) -> Option<&dyn LearnableLayer>;
     fn as_learnable_layer_mut(&mut self) -> Option<&mut dyn LearnableLayer>;
     fn as_dropout_layer(&mut self) -> Option<&mut dyn DropoutLayer>;
+    fn as_trainable_layer(&self) -> Option<&dyn TrainableLayer>;
+    fn as_trainable_layer_mut(&mut self) -> Option<&mut dyn TrainableLayer>;
+
+    /// Only the layers of full dense networks support it for now.
+    fn as_gradient_layer_mut(&mut self) -> Option<&mut dyn GradientLayer> {
+        None
+    }
 }
 
 pub trait DropoutLayer {
@@ -35,7 +43,54 @@ pub trait DropoutLayer {
     fn disable_dropout(&mut self);
 }
 
+/// A layer whose parameters can be frozen (i.e. not updated by its optimizers)
+/// or trained with a scaled learning rate.
+///
+/// Frozen layers still propagate the error gradient to the previous layers.
+pub trait TrainableLayer {
+    fn is_trainable(&self) -> bool;
+    fn set_trainable(&mut self, trainable: bool);
+    fn get_learning_rate_multiplier(&self) -> Scalar;
+    fn set_learning_rate_multiplier(&mut self, multiplier: Scalar);
+}
+
+/// Scales the step between `parameters` and the `updated` parameters returned by an optimizer.
+///
+/// Since all the optimizers' steps are linear in their learning rate,
+/// this is the same as scaling the learning rate itself.
+pub fn scale_parameters_update(parameters: &Matrix, updated: Matrix, multiplier: Scalar) -> Matrix {
+    if multiplier == 1.0 {
+        updated
+    } else {
+        parameters.component_add(&updated.component_sub(parameters).scalar_mul(multiplier))
+    }
+}
+
+/// A layer whose parameters' gradients can be accumulated by `backward` instead of being applied right away,
+/// so that the gradients of several replicas of a network can be summed before a single optimizer step
+/// (see `Network::train_data_parallel_with_hook`).
+///
+/// The gradients have the layout of `LearnableLayer::get_learnable_parameters`.
+pub trait GradientLayer {
+    fn set_accumulate_gradients(&mut self, accumulate: bool);
+
+    /// Returns the gradients accumulated since the last call, if any.
+    fn take_gradients(&mut self) -> Option<Vec<Vec<Scalar>>>;
+
+    /// Updates the parameters with the optimizers from the given gradients, unless the layer is frozen.
+    fn apply_gradients(&mut self, epoch: usize, gradients: &[Vec<Scalar>]);
+}
+
 pub trait LearnableLayer {
     fn get_learnable_parameters(&self) -> Vec<Vec<Scalar>>;
     fn set_learnable_parameters(&mut self, params_matrix: &Vec<Vec<Scalar>>);
+
+    /// State of each of the layer's optimizers (see `Optimizers::get_state`).
+    ///
+    /// Only the layers of full dense networks save it for now.
+    fn get_optimizers_state(&self) -> Vec<OptimizerState> {
+        vec![]
+    }
+
+    fn set_optimizers_state(&mut self, _state: &[OptimizerState]) {}
 }
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use crate::linalg::Scalar;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExponentialDecay {
+    pub initial_learning_rate: Scalar,
+    pub decay_steps: Scalar,
+    pub decay_rate: Scalar,
+    #[serde(default)]
+    pub staircase: bool,
+}
+
+impl ExponentialDecay {
+    pub fn new(
+        initial_learning_rate: Scalar,
+        decay_steps: Scalar,
+        decay_rate: Scalar,
+        staircase: bool,
+    ) -> Self {
+        Self {
+            initial_learning_rate,
+            decay_steps,
+            decay_rate,
+            staircase,
+        }
+    }
+
+    pub fn get_learning_rate(&self, epoch: usize) -> Scalar {
+        let mut exponent = epoch as Scalar / self.decay_steps;
+        if self.staircase {
+            exponent = exponent.floor();
+        }
+        self.initial_learning_rate * self.decay_rate.powf(exponent)
+    }
+}
@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
 
-use self::{inverse_time_decay::InverseTimeDecay, piecewise_constant::PiecewiseConstant};
+use self::{
+    exponential_decay::ExponentialDecay, inverse_time_decay::InverseTimeDecay,
+    piecewise_constant::PiecewiseConstant,
+};
 use crate::linalg::Scalar;
 
+pub mod exponential_decay;
 pub mod inverse_time_decay;
 pub mod piecewise_constant;
 
@@ -15,6 +19,7 @@ pub fn default_learning_rate() -> LearningRateSchedule {
 pub enum LearningRateSchedule {
     Constant(Scalar),
     InverseTimeDecay(InverseTimeDecay),
+    ExponentialDecay(ExponentialDecay),
     PiecewiseConstant(PiecewiseConstant),
 }
 
@@ -22,6 +27,7 @@ impl LearningRateSchedule {
     pub fn get_learning_rate(&self, epoch: usize) -> Scalar {
         match self {
             LearningRateSchedule::InverseTimeDecay(schedule) => schedule.get_learning_rate(epoch),
+            LearningRateSchedule::ExponentialDecay(schedule) => schedule.get_learning_rate(epoch),
             LearningRateSchedule::PiecewiseConstant(schedule) => schedule.get_learning_rate(epoch),
             LearningRateSchedule::Constant(c) => *c,
         }
@@ -23,8 +23,12 @@ pub mod layer;
 pub mod learning_rate;
 /// Basic linear algebra backends and wrappers (matrix, vector, scalar...)
 pub mod linalg;
+/// Data loaders yielding mini-batches of matrices (vectors, tables, image folders, files...)
+pub mod loader;
 /// Loss functions and abstractions (mse, crossentropy...)
 pub mod loss;
+/// Validation metrics and abstractions (r2, accuracy, roc auc...)
+pub mod metrics;
 /// Model configuration
 pub mod model;
 /// Neural network abstractions
@@ -34,6 +38,11 @@ pub mod optimizer;
 #[cfg(feature = "data")]
 /// Preprocessing and pipelining utilities (normalization, one-hot encoding...)
 pub mod preprocessing;
+/// Seedable random number generation shared by the whole library
+pub mod random;
+#[cfg(feature = "data")]
+/// Out-of-core batches of data read from files (csv, parquet, ipc...)
+pub mod streaming;
 /// Training methodologies (k-fold, split...)
 pub mod trainers;
 /// Utilities for `Vec<Scalar>`, `Vec<Vec<Scalar>>`...
@@ -5,7 +5,7 @@ use arrayfire::{
     random_normal, random_uniform, sign, sqrt, sub, sum_all, transpose, Array, Dim4, MatProp,
     RandomEngine, Seq, log, get_active_backend, Backend, max_all, min_all, join, identity,
 };
-use rand::Rng;
+use crate::random::gen_seed;
 
 use super::{MatrixTrait, Scalar};
 
@@ -34,13 +34,12 @@ impl MatrixTrait for Matrix {
 
     /// Creates a matrix with random values between min and max (excluded).
     fn random_uniform(nrow: usize, ncol: usize, min: Scalar, max: Scalar) -> Self {
-        let mut rng = rand::thread_rng();
         Self(
             random_uniform::<Scalar>(
                 Dim4::new(&[nrow.try_into().unwrap(), ncol.try_into().unwrap(), 1, 1]),
                 &RandomEngine::new(
                     arrayfire::RandomEngineType::MERSENNE_GP11213,
-                    Some(rng.gen()),
+                    Some(gen_seed()),
                 ),
             ) * (max - min)
                 + constant!(min; nrow.try_into().unwrap(), ncol.try_into().unwrap()),
@@ -49,13 +48,12 @@ impl MatrixTrait for Matrix {
 
     /// Creates a matrix with random values following a normal distribution.
     fn random_normal(nrow: usize, ncol: usize, mean: Scalar, std_dev: Scalar) -> Self {
-        let mut rng = rand::thread_rng();
         Self(
             random_normal::<Scalar>(
                 Dim4::new(&[nrow.try_into().unwrap(), ncol.try_into().unwrap(), 1, 1]),
                 &RandomEngine::new(
                     arrayfire::RandomEngineType::MERSENNE_GP11213,
-                    Some(rng.gen()),
+                    Some(gen_seed()),
                 ),
             ) * std_dev
                 + constant!(mean; nrow.try_into().unwrap(), ncol.try_into().unwrap()),
@@ -5,6 +5,7 @@ use rand::Rng;
 use rand_distr::Distribution;
 
 use super::{MatrixTrait, Scalar};
+use crate::random::with_rng;
 
 /// Column leading nalgebra Matrix
 
@@ -31,10 +32,11 @@ impl MatrixTrait for Matrix {
 
     /// Creates a matrix with random values between min and max (excluded).
     fn random_uniform(nrow: usize, ncol: usize, min: Scalar, max: Scalar) -> Self {
-        let mut rng = rand::thread_rng();
-        let data: Vec<Vec<Scalar>> = (0..ncol)
-            .map(|_| (0..nrow).map(|_| rng.gen_range(min..max)).collect())
-            .collect();
+        let data: Vec<Vec<Scalar>> = with_rng(|rng| {
+            (0..ncol)
+                .map(|_| (0..nrow).map(|_| rng.gen_range(min..max)).collect())
+                .collect()
+        });
 
         Self(DMatrix::from_row_slice(nrow, ncol, &data.concat()))
     }
@@ -42,13 +44,11 @@ impl MatrixTrait for Matrix {
     /// Creates a matrix with random values following a normal distribution.
     fn random_normal(nrow: usize, ncol: usize, mean: Scalar, std_dev: Scalar) -> Self {
         let normal = rand_distr::Normal::new(mean, std_dev).unwrap();
-        let data: Vec<Vec<Scalar>> = (0..ncol)
-            .map(|_| {
-                (0..nrow)
-                    .map(|_| normal.sample(&mut rand::thread_rng()))
-                    .collect()
-            })
-            .collect();
+        let data: Vec<Vec<Scalar>> = with_rng(|rng| {
+            (0..ncol)
+                .map(|_| (0..nrow).map(|_| normal.sample(rng)).collect())
+                .collect()
+        });
         Self(DMatrix::from_row_slice(nrow, ncol, &data.concat()))
     }
 
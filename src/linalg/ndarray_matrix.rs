@@ -4,6 +4,7 @@ use ndarray::{Array2};
 use rand::Rng;
 
 use super::{MatrixTrait, Scalar};
+use crate::random::with_rng;
 
 #[derive(Clone, Debug)]
 pub struct Matrix(pub Array2<Scalar>);
@@ -27,15 +28,17 @@ impl MatrixTrait for Matrix {
     }
 
     fn random_uniform(nrow: usize, ncol: usize, min: Scalar, max: Scalar) -> Self {
-        let mat = Array2::from_shape_fn((nrow, ncol), |(_, _)| {
-            rand::thread_rng().gen_range(min..max)
+        let mat = with_rng(|rng| {
+            Array2::from_shape_fn((nrow, ncol), |(_, _)| rng.gen_range(min..max))
         });
         Self(mat)
     }
 
     fn random_normal(nrow: usize, ncol: usize, mean: Scalar, std_dev: Scalar) -> Self {
-        let mat = Array2::from_shape_fn((nrow, ncol), |(_, _)| {
-            rand::thread_rng().gen_range((mean - 3. * std_dev)..(mean + 3. * std_dev))
+        let mat = with_rng(|rng| {
+            Array2::from_shape_fn((nrow, ncol), |(_, _)| {
+                rng.gen_range((mean - 3. * std_dev)..(mean + 3. * std_dev))
+            })
         });
         Self(mat)
     }
@@ -86,7 +89,7 @@ impl MatrixTrait for Matrix {
         let mut mat = Array2::<Scalar>::zeros((columns[0].0.nrows(), columns.len()));
 
         for i in 0..columns.len() {
-            mat.column_mut(i).assign(&columns[i].0);
+            mat.column_mut(i).assign(&columns[i].0.column(0));
         }
 
         Self(mat)
@@ -115,8 +118,8 @@ impl MatrixTrait for Matrix {
         unimplemented!("Incompatible")
     }
 
-    fn map(&self, _f: impl Fn(Scalar) -> Scalar + Sync) -> Self {
-        unimplemented!("Incompatible")
+    fn map(&self, f: impl Fn(Scalar) -> Scalar + Sync) -> Self {
+        Self(self.0.mapv(f))
     }
 
     fn dot(&self, other: &Self) -> Self {
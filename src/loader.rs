@@ -0,0 +1,399 @@
+use std::{
+    sync::{mpsc, Arc},
+    thread,
+};
+
+use rand::seq::SliceRandom;
+
+use crate::{
+    linalg::{Matrix, MatrixTrait, Scalar},
+    random::{gen_seed, set_seed, with_rng},
+};
+
+#[cfg(feature = "data")]
+use crate::{datatable::DataTable, model::Model, streaming::DataStream};
+
+#[cfg(feature = "images")]
+use std::path::{Path, PathBuf};
+
+/// Mini-batches of an epoch, as `(inputs, targets)` matrices holding one sample per column.
+pub type MatrixBatches = Box<dyn Iterator<Item = (Matrix, Matrix)> + Send>;
+
+/// Options shared by the data loaders, set with the `DataLoader` methods.
+#[derive(Clone, Copy, Debug)]
+pub struct LoaderOptions {
+    pub batch_size: usize,
+    pub shuffle: bool,
+    /// Skips the last mini-batch of each epoch if it is smaller than the others
+    pub drop_last: bool,
+    /// Number of mini-batches prepared in advance by a background thread (none by default)
+    pub prefetch: usize,
+}
+
+impl LoaderOptions {
+    pub fn new(batch_size: usize) -> Self {
+        assert!(batch_size > 0, "The batches must not be empty");
+        Self {
+            batch_size,
+            shuffle: false,
+            drop_last: false,
+            prefetch: 0,
+        }
+    }
+
+    /// Splits the indices of the samples into mini-batches, shuffled and without the last smaller one as configured.
+    pub fn batches_indices(&self, samples: usize) -> Vec<Vec<usize>> {
+        let mut indices = (0..samples).collect::<Vec<_>>();
+        if self.shuffle {
+            with_rng(|rng| indices.shuffle(rng));
+        }
+        indices
+            .chunks(self.batch_size)
+            .filter(|batch| !self.drop_last || batch.len() == self.batch_size)
+            .map(|batch| batch.to_vec())
+            .collect()
+    }
+}
+
+/// Source of the mini-batches of a training, so that `Network::train_loader` and the trainers
+/// don't need all the samples as vectors.
+///
+/// The shuffling happens when `batches` is called, from the random generator of the calling thread
+/// (see `random::set_seed`), so that the prefetched epochs are reproducible too.
+pub trait DataLoader {
+    fn options(&self) -> &LoaderOptions;
+
+    fn options_mut(&mut self) -> &mut LoaderOptions;
+
+    /// Number of samples of an epoch, including the dropped ones.
+    fn num_samples(&self) -> usize;
+
+    /// Mini-batches of an epoch, built lazily on the thread consuming them.
+    fn epoch_batches(&mut self) -> MatrixBatches;
+
+    /// Mini-batches of an epoch, built by a background thread if `prefetch` is set and the backend is thread safe.
+    ///
+    /// Called once per epoch, the shuffling loaders yielding the samples in a new order each time.
+    fn batches(&mut self) -> MatrixBatches {
+        let prefetch = self.options().prefetch;
+        let batches = self.epoch_batches();
+        if prefetch > 0 && Matrix::is_backend_thread_safe() {
+            prefetched(batches, prefetch)
+        } else {
+            batches
+        }
+    }
+
+    /// Shuffles the samples at each epoch.
+    fn shuffle(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        self.options_mut().shuffle = true;
+        self
+    }
+
+    /// Skips the last mini-batch of each epoch if it is smaller than the others.
+    fn drop_last(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        self.options_mut().drop_last = true;
+        self
+    }
+
+    /// Prepares up to `batches` mini-batches in advance on a background thread.
+    fn prefetch(mut self, batches: usize) -> Self
+    where
+        Self: Sized,
+    {
+        self.options_mut().prefetch = batches;
+        self
+    }
+}
+
+// The thread stops once the batches are all sent or the receiver is dropped
+fn prefetched(batches: MatrixBatches, prefetch: usize) -> MatrixBatches {
+    let (sender, receiver) = mpsc::sync_channel(prefetch);
+    let seed = gen_seed();
+    thread::spawn(move || {
+        set_seed(seed);
+        for batch in batches {
+            if sender.send(batch).is_err() {
+                break;
+            }
+        }
+    });
+    Box::new(receiver.into_iter())
+}
+
+/// Loads the mini-batches from samples held in memory.
+pub struct VecLoader {
+    x: Arc<Vec<Vec<Scalar>>>,
+    y: Arc<Vec<Vec<Scalar>>>,
+    options: LoaderOptions,
+}
+
+impl VecLoader {
+    /// `x` has shape `(n, i)` and `y` has shape `(n, j)`, where `n` is the number of samples.
+    pub fn new(x: Vec<Vec<Scalar>>, y: Vec<Vec<Scalar>>, batch_size: usize) -> Self {
+        assert_eq!(x.len(), y.len(), "The inputs and the targets must have the same number of samples");
+        Self {
+            x: Arc::new(x),
+            y: Arc::new(y),
+            options: LoaderOptions::new(batch_size),
+        }
+    }
+
+    /// Converts the table to the model's inputs and targets (see `Model::inputs_to_vectors`).
+    #[cfg(feature = "data")]
+    pub fn from_table(model: &Model, table: &DataTable, batch_size: usize) -> Self {
+        let (x, y) = table_to_vectors(model, table);
+        Self::new(x, y, batch_size)
+    }
+}
+
+impl DataLoader for VecLoader {
+    fn options(&self) -> &LoaderOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut LoaderOptions {
+        &mut self.options
+    }
+
+    fn num_samples(&self) -> usize {
+        self.x.len()
+    }
+
+    fn epoch_batches(&mut self) -> MatrixBatches {
+        let (x, y) = (self.x.clone(), self.y.clone());
+        Box::new(
+            self.options
+                .batches_indices(x.len())
+                .into_iter()
+                .map(move |batch| {
+                    let x_batch = batch.iter().map(|&i| x[i].clone()).collect::<Vec<_>>();
+                    let y_batch = batch.iter().map(|&i| y[i].clone()).collect::<Vec<_>>();
+                    (
+                        Matrix::from_column_leading_vector2(&x_batch),
+                        Matrix::from_column_leading_vector2(&y_batch),
+                    )
+                }),
+        )
+    }
+}
+
+/// Loads the mini-batches from a `DataStream`, reading the file as the epoch goes.
+///
+/// The file must hold the data as the network expects it (already preprocessed, see `Pipeline`).
+/// Shuffling uses the stream's shuffle buffer, or a buffer of one chunk if it has none.
+#[cfg(feature = "data")]
+pub struct StreamLoader {
+    model: Arc<Model>,
+    stream: DataStream,
+    options: LoaderOptions,
+}
+
+#[cfg(feature = "data")]
+impl StreamLoader {
+    pub fn new(model: &Model, stream: DataStream, batch_size: usize) -> Self {
+        Self {
+            model: Arc::new(model.clone()),
+            stream,
+            options: LoaderOptions::new(batch_size),
+        }
+    }
+}
+
+#[cfg(feature = "data")]
+impl DataLoader for StreamLoader {
+    fn options(&self) -> &LoaderOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut LoaderOptions {
+        &mut self.options
+    }
+
+    fn num_samples(&self) -> usize {
+        self.stream.num_rows()
+    }
+
+    fn epoch_batches(&mut self) -> MatrixBatches {
+        let stream = match self.stream.shuffle_buffer {
+            None if self.options.shuffle => self.stream.clone().shuffle_buffer(self.stream.chunk_size),
+            _ => self.stream.clone(),
+        };
+        let LoaderOptions { batch_size, drop_last, .. } = self.options;
+        let model = self.model.clone();
+        Box::new(
+            stream
+                .batches(batch_size)
+                .filter(move |batch| !drop_last || batch.num_rows() == batch_size)
+                .map(move |batch| {
+                    let (x, y) = table_to_vectors(&model, &batch);
+                    (
+                        Matrix::from_column_leading_vector2(&x),
+                        Matrix::from_column_leading_vector2(&y),
+                    )
+                }),
+        )
+    }
+}
+
+#[cfg(feature = "data")]
+fn table_to_vectors(model: &Model, table: &DataTable) -> (Vec<Vec<Scalar>>, Vec<Vec<Scalar>>) {
+    let predicted_features = model.dataset_config.predicted_features_names();
+    let id_column = model
+        .dataset_config
+        .get_id_column()
+        .expect("One feature must be configured as an id in the dataset configuration.");
+    let x = model.inputs_to_vectors(&table.drop_columns(&predicted_features), id_column);
+    let y = table.select_columns(&predicted_features).to_vectors_with_missing();
+    (x, y)
+}
+
+/// Loads the mini-batches from a folder holding a subfolder of PNG or JPEG images per class.
+///
+/// The images are resized to squares of `size` pixels with values between 0 and 1,
+/// flattened like `Image::flatten` (channel by channel, each channel column by column), and their targets are one-hot encoded classes.
+///
+/// An image that can't be read during an epoch is skipped with a warning.
+#[cfg(feature = "images")]
+pub struct ImageFolderLoader {
+    /// Names of the classes' subfolders, in the order of the targets
+    pub classes: Vec<String>,
+    samples: Arc<Vec<(PathBuf, usize)>>,
+    size: usize,
+    grayscale: bool,
+    options: LoaderOptions,
+}
+
+#[cfg(feature = "images")]
+impl ImageFolderLoader {
+    /// Fails if the folder can't be read or if one of its images isn't a valid PNG or JPEG.
+    pub fn new<P: AsRef<Path>>(root: P, size: usize, batch_size: usize) -> std::io::Result<Self> {
+        let mut classes = vec![];
+        for entry in std::fs::read_dir(root.as_ref())? {
+            let path = entry?.path();
+            if path.is_dir() {
+                classes.push(path);
+            }
+        }
+        classes.sort();
+
+        let mut samples = vec![];
+        for (class, dir) in classes.iter().enumerate() {
+            let mut images = vec![];
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                if ["png", "jpg", "jpeg"].contains(&extension.to_lowercase().as_str()) {
+                    // Only the header is decoded, the pixels are loaded at each epoch
+                    image::image_dimensions(&path).map_err(|e| {
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+                    })?;
+                    images.push(path);
+                }
+            }
+            images.sort();
+            samples.extend(images.into_iter().map(|path| (path, class)));
+        }
+
+        Ok(Self {
+            classes: classes
+                .iter()
+                .map(|dir| dir.file_name().unwrap().to_string_lossy().to_string())
+                .collect(),
+            samples: Arc::new(samples),
+            size,
+            grayscale: false,
+            options: LoaderOptions::new(batch_size),
+        })
+    }
+
+    /// Loads the images with a single luminance channel instead of the RGB ones.
+    pub fn grayscale(self) -> Self {
+        Self {
+            grayscale: true,
+            ..self
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        if self.grayscale {
+            1
+        } else {
+            3
+        }
+    }
+}
+
+#[cfg(feature = "images")]
+impl DataLoader for ImageFolderLoader {
+    fn options(&self) -> &LoaderOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut LoaderOptions {
+        &mut self.options
+    }
+
+    fn num_samples(&self) -> usize {
+        self.samples.len()
+    }
+
+    fn epoch_batches(&mut self) -> MatrixBatches {
+        let samples = self.samples.clone();
+        let (size, grayscale, n_classes) = (self.size, self.grayscale, self.classes.len());
+        Box::new(
+            self.options
+                .batches_indices(samples.len())
+                .into_iter()
+                .filter_map(move |batch| {
+                    let (x_batch, y_batch): (Vec<_>, Vec<_>) = batch
+                        .iter()
+                        .filter_map(|&i| {
+                            let (path, class) = &samples[i];
+                            let image = load_image(path, size, grayscale)
+                                .map_err(|e| eprintln!("Skipping the image {}: {}", path.display(), e))
+                                .ok()?;
+                            let mut target = vec![0.0; n_classes];
+                            target[*class] = 1.0;
+                            Some((image, target))
+                        })
+                        .unzip();
+                    if x_batch.is_empty() {
+                        return None;
+                    }
+                    Some((
+                        Matrix::from_column_leading_vector2(&x_batch),
+                        Matrix::from_column_leading_vector2(&y_batch),
+                    ))
+                }),
+        )
+    }
+}
+
+#[cfg(feature = "images")]
+fn load_image(path: &Path, size: usize, grayscale: bool) -> image::ImageResult<Vec<Scalar>> {
+    let image = image::open(path)?
+        .resize_exact(size as u32, size as u32, image::imageops::FilterType::Triangle);
+    let (channels, pixels) = if grayscale {
+        (1, image.to_luma8().into_raw())
+    } else {
+        (3, image.to_rgb8().into_raw())
+    };
+
+    // The pixels are stored by rows of interleaved channels, they are flattened by channels of columns
+    let mut values = Vec::with_capacity(pixels.len());
+    for channel in 0..channels {
+        for col in 0..size {
+            for row in 0..size {
+                values.push(pixels[(row * size + col) * channels + channel] as Scalar / 255.);
+            }
+        }
+    }
+    Ok(values)
+}
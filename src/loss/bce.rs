@@ -1,6 +1,6 @@
 use crate::{
     linalg::{Matrix, MatrixTrait, Scalar},
-    loss::Loss,
+    loss::LossFunction,
 };
 
 pub fn bce_vec(y_pred: &Vec<Scalar>, y_true: &Vec<Scalar>) -> Scalar {
@@ -33,6 +33,15 @@ fn bce_prime(y_true: &Matrix, y_pred: &Matrix) -> Matrix {
         .scalar_div(y_pred.dim().0 as Scalar)
 }
 
-pub fn new() -> Loss {
-    Loss::new(bce, bce_prime)
+/// Binary cross entropy
+pub struct BCE;
+
+impl LossFunction for BCE {
+    fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+        bce(y_true, y_pred)
+    }
+
+    fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+        bce_prime(y_true, y_pred)
+    }
 }
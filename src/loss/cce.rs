@@ -0,0 +1,120 @@
+use crate::{
+    linalg::{Matrix, MatrixTrait, Scalar},
+    loss::{mask_gradient, LossFunction},
+};
+
+// Predictions are clamped to avoid taking the log of zero
+const EPSILON: Scalar = 1e-7;
+
+fn clamp(y_pred: &Matrix) -> Matrix {
+    y_pred.map(|p| p.max(EPSILON))
+}
+
+fn cce(y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+    let n_samples = y_pred.dim().1 as Scalar;
+    -y_true.component_mul(&clamp(y_pred).log()).sum() / n_samples
+}
+
+// Derivative of each sample's loss, like the other losses the gradient is not divided by the number of samples
+fn cce_prime(y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+    y_true.component_div(&clamp(y_pred)).scalar_mul(-1.)
+}
+
+// Gradient with respect to the input of a softmax activation:
+// the softmax jacobian simplifies with the derivative of the cross entropy.
+fn cce_softmax_prime(y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+    y_pred.component_sub(y_true)
+}
+
+/// Categorical cross entropy.
+///
+/// If `sparse`, the targets are the indices of the classes, which are one-hot encoded before computing the loss.
+///
+/// With label smoothing, the (one-hot) targets `y` are replaced by `y * (1 - label_smoothing) + label_smoothing / classes`.
+pub struct CategoricalCrossEntropy {
+    pub label_smoothing: Scalar,
+    pub sparse: bool,
+}
+
+// Mask of the samples having at least one known target, repeated for each class
+fn samples_mask(mask: &Matrix, n_classes: usize) -> Matrix {
+    let known = (0..mask.dim().1)
+        .map(|j| mask.get_column(j).iter().any(|m| *m > 0.))
+        .collect::<Vec<_>>();
+    Matrix::from_fn(n_classes, known.len(), |_, j| if known[j] { 1. } else { 0. })
+}
+
+impl CategoricalCrossEntropy {
+    pub fn new(label_smoothing: Scalar, sparse: bool) -> Self {
+        assert!((0. ..1.).contains(&label_smoothing));
+        Self {
+            label_smoothing,
+            sparse,
+        }
+    }
+
+    fn prepare_targets(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+        let n_classes = y_pred.dim().0;
+        let y_true = if self.sparse {
+            let classes = y_true.get_row(0);
+            Matrix::from_fn(n_classes, classes.len(), |i, j| {
+                if classes[j].round() as usize == i {
+                    1.
+                } else {
+                    0.
+                }
+            })
+        } else {
+            y_true.clone()
+        };
+
+        if self.label_smoothing > 0. {
+            y_true
+                .scalar_mul(1. - self.label_smoothing)
+                .scalar_add(self.label_smoothing / n_classes as Scalar)
+        } else {
+            y_true
+        }
+    }
+}
+
+impl LossFunction for CategoricalCrossEntropy {
+    fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+        cce(&self.prepare_targets(y_true, y_pred), y_pred)
+    }
+
+    fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+        cce_prime(&self.prepare_targets(y_true, y_pred), y_pred)
+    }
+
+    /// Samples are only left out if all their targets are missing, since the classes cannot be treated separately.
+    fn masked_loss(&self, y_true: &Matrix, y_pred: &Matrix, mask: &Matrix) -> Scalar {
+        let known = (0..mask.dim().1)
+            .filter(|&j| mask.get_column(j).iter().any(|m| *m > 0.))
+            .collect::<Vec<_>>();
+        if known.is_empty() {
+            return 0.;
+        }
+        let y_true = Matrix::from_fn(y_true.dim().0, known.len(), |i, k| y_true.index(i, known[k]));
+        let y_pred = Matrix::from_fn(y_pred.dim().0, known.len(), |i, k| y_pred.index(i, known[k]));
+        self.loss(&y_true, &y_pred)
+    }
+
+    fn masked_loss_prime(&self, y_true: &Matrix, y_pred: &Matrix, mask: &Matrix) -> Matrix {
+        mask_gradient(
+            &self.loss_prime(y_true, y_pred),
+            &samples_mask(mask, y_pred.dim().0),
+        )
+    }
+
+    fn masked_softmax_loss_prime(&self, y_true: &Matrix, y_pred: &Matrix, mask: &Matrix) -> Option<Matrix> {
+        Some(mask_gradient(
+            &cce_softmax_prime(&self.prepare_targets(y_true, y_pred), y_pred),
+            &samples_mask(mask, y_pred.dim().0),
+        ))
+    }
+
+    fn softmax_loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Option<Matrix> {
+        Some(cce_softmax_prime(&self.prepare_targets(y_true, y_pred), y_pred))
+    }
+}
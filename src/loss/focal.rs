@@ -0,0 +1,68 @@
+use crate::{
+    linalg::{Matrix, MatrixTrait, Scalar},
+    loss::LossFunction,
+};
+
+// Predictions are clamped to avoid taking the log of zero
+const EPSILON: Scalar = 1e-7;
+
+// -alpha * (1 - p)^gamma * log(p) for positives, -(1 - alpha) * p^gamma * log(1 - p) for negatives
+fn focal_element(gamma: Scalar, alpha: Scalar, y: Scalar, p: Scalar) -> Scalar {
+    let p = p.clamp(EPSILON, 1. - EPSILON);
+    -y * alpha * (1. - p).powf(gamma) * p.ln()
+        - (1. - y) * (1. - alpha) * p.powf(gamma) * (1. - p).ln()
+}
+
+fn focal_element_prime(gamma: Scalar, alpha: Scalar, y: Scalar, p: Scalar) -> Scalar {
+    let p = p.clamp(EPSILON, 1. - EPSILON);
+    let positive = gamma * (1. - p).powf(gamma - 1.) * p.ln() - (1. - p).powf(gamma) / p;
+    let negative = -gamma * p.powf(gamma - 1.) * (1. - p).ln() + p.powf(gamma) / (1. - p);
+    y * alpha * positive + (1. - y) * (1. - alpha) * negative
+}
+
+fn focal(gamma: Scalar, alpha: Scalar, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+    let (nrow, ncol) = y_pred.dim();
+    Matrix::from_fn(nrow, ncol, |i, j| {
+        focal_element(gamma, alpha, y_true.index(i, j), y_pred.index(i, j))
+    })
+    .mean()
+}
+
+fn focal_prime(gamma: Scalar, alpha: Scalar, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+    let (nrow, ncol) = y_pred.dim();
+    Matrix::from_fn(nrow, ncol, |i, j| {
+        focal_element_prime(gamma, alpha, y_true.index(i, j), y_pred.index(i, j))
+    })
+}
+
+/// Binary focal loss, a cross entropy down-weighting the well classified samples
+/// so that the training focuses on the hard (often rare) ones.
+///
+/// `gamma` sets how much easy samples are down-weighted (0 gives a cross entropy),
+/// and `alpha` (between 0 and 1) weights the positive class, `1 - alpha` weighting the negative one.
+///
+/// The predictions are probabilities, usually the output of a sigmoid.
+///
+/// Resources: https://arxiv.org/abs/1708.02002
+pub struct Focal {
+    pub gamma: Scalar,
+    pub alpha: Scalar,
+}
+
+impl Focal {
+    pub fn new(gamma: Scalar, alpha: Scalar) -> Self {
+        assert!(gamma >= 0.);
+        assert!((0. ..=1.).contains(&alpha));
+        Self { gamma, alpha }
+    }
+}
+
+impl LossFunction for Focal {
+    fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+        focal(self.gamma, self.alpha, y_true, y_pred)
+    }
+
+    fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+        focal_prime(self.gamma, self.alpha, y_true, y_pred)
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{
+    linalg::{Matrix, MatrixTrait, Scalar},
+    loss::{mask_gradient, LossFunction},
+};
+
+// Variances are clamped to avoid dividing by zero
+const EPSILON: Scalar = 1e-6;
+
+fn mean_of(y_pred: &Matrix, i: usize, j: usize) -> Scalar {
+    y_pred.index(2 * i, j)
+}
+
+fn variance_of(y_pred: &Matrix, i: usize, j: usize) -> Scalar {
+    y_pred.index(2 * i + 1, j).max(EPSILON)
+}
+
+// 0.5 * (ln(var) + (y - mean)^2 / var) for each target of each sample, the constant term being left out
+fn gaussian_nll_elements(y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+    let (nrow, ncol) = y_true.dim();
+    Matrix::from_fn(nrow, ncol, |i, j| {
+        let variance = variance_of(y_pred, i, j);
+        let error = y_true.index(i, j) - mean_of(y_pred, i, j);
+        0.5 * (variance.ln() + error * error / variance)
+    })
+}
+
+fn gaussian_nll_prime(y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+    let (nrow, ncol) = y_pred.dim();
+    Matrix::from_fn(nrow, ncol, |k, j| {
+        let i = k / 2;
+        let variance = variance_of(y_pred, i, j);
+        let error = y_true.index(i, j) - mean_of(y_pred, i, j);
+        if k % 2 == 0 {
+            -error / variance
+        } else {
+            0.5 * (1. / variance - error * error / (variance * variance))
+        }
+    })
+}
+
+// Repeats each target row for its mean and variance outputs
+fn expand_targets(y_true: &Matrix) -> Matrix {
+    let (nrow, ncol) = y_true.dim();
+    Matrix::from_fn(nrow * 2, ncol, |i, j| y_true.index(i / 2, j))
+}
+
+/// Gaussian negative log-likelihood, for networks predicting the mean and the variance of each target.
+///
+/// The network must have `targets * 2` outputs: the mean and then the variance of each target,
+/// the variances being kept positive by the output layer (see `NetworkModelBuilder::gaussian_output`).
+pub struct GaussianNLL;
+
+impl LossFunction for GaussianNLL {
+    fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+        gaussian_nll_elements(y_true, y_pred).mean()
+    }
+
+    fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+        gaussian_nll_prime(y_true, y_pred)
+    }
+
+    fn masked_loss(&self, y_true: &Matrix, y_pred: &Matrix, mask: &Matrix) -> Scalar {
+        let known_count = mask.sum();
+        if known_count == 0. {
+            return 0.;
+        }
+        gaussian_nll_elements(y_true, y_pred)
+            .component_mul(mask)
+            .sum()
+            / known_count
+    }
+
+    fn masked_loss_prime(&self, y_true: &Matrix, y_pred: &Matrix, mask: &Matrix) -> Matrix {
+        mask_gradient(&gaussian_nll_prime(y_true, y_pred), &expand_targets(mask))
+    }
+
+    /// The predicted means.
+    fn point_predictions(&self, y_pred: &Matrix) -> Matrix {
+        let (nrow, ncol) = y_pred.dim();
+        Matrix::from_fn(nrow / 2, ncol, |i, j| mean_of(y_pred, i, j))
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{
+    linalg::{Matrix, MatrixTrait, Scalar},
+    loss::LossFunction,
+};
+
+fn huber(delta: Scalar, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+    y_pred
+        .component_sub(y_true)
+        .map(|r| {
+            if r.abs() <= delta {
+                0.5 * r * r
+            } else {
+                delta * (r.abs() - 0.5 * delta)
+            }
+        })
+        .mean()
+}
+
+fn huber_prime(delta: Scalar, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+    y_pred
+        .component_sub(y_true)
+        .map(|r| r.clamp(-delta, delta))
+}
+
+/// Huber loss: quadratic for errors smaller than `delta` and linear beyond,
+/// making it less sensitive to outliers than the MSE.
+pub struct Huber {
+    pub delta: Scalar,
+}
+
+impl Huber {
+    pub fn new(delta: Scalar) -> Self {
+        assert!(delta > 0.);
+        Self { delta }
+    }
+}
+
+impl LossFunction for Huber {
+    fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+        huber(self.delta, y_true, y_pred)
+    }
+
+    fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+        huber_prime(self.delta, y_true, y_pred)
+    }
+}
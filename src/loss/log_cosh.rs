@@ -0,0 +1,32 @@
+use crate::{
+    linalg::{Matrix, MatrixTrait, Scalar},
+    loss::LossFunction,
+};
+
+// log(cosh(r)) = |r| + log(1 + exp(-2|r|)) - log(2), which doesn't overflow for large errors
+fn log_cosh_scalar(r: Scalar) -> Scalar {
+    r.abs() + (-2. * r.abs()).exp().ln_1p() - (2.0 as Scalar).ln()
+}
+
+fn log_cosh(y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+    y_pred.component_sub(y_true).map(log_cosh_scalar).mean()
+}
+
+fn log_cosh_prime(y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+    y_pred.component_sub(y_true).map(Scalar::tanh)
+}
+
+/// Logarithm of the hyperbolic cosine of the error.
+///
+/// Behaves like the MSE for small errors and like the MAE for large ones, while being smooth everywhere.
+pub struct LogCosh;
+
+impl LossFunction for LogCosh {
+    fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+        log_cosh(y_true, y_pred)
+    }
+
+    fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+        log_cosh_prime(y_true, y_pred)
+    }
+}
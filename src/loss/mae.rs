@@ -0,0 +1,33 @@
+use crate::{
+    linalg::{Matrix, MatrixTrait, Scalar},
+    loss::LossFunction,
+};
+
+fn mae(y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+    y_pred.component_sub(y_true).map(Scalar::abs).mean()
+}
+
+fn mae_prime(y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+    y_pred.component_sub(y_true).map(|r| {
+        if r > 0. {
+            1.
+        } else if r < 0. {
+            -1.
+        } else {
+            0.
+        }
+    })
+}
+
+/// Mean absolute error
+pub struct MAE;
+
+impl LossFunction for MAE {
+    fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+        mae(y_true, y_pred)
+    }
+
+    fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+        mae_prime(y_true, y_pred)
+    }
+}
@@ -1,3 +1,9 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
 use crate::linalg::Matrix;
@@ -6,48 +12,367 @@ use crate::linalg::Scalar;
 
 pub mod mse;
 pub mod bce;
+pub mod cce;
+pub mod mae;
+pub mod huber;
+pub mod log_cosh;
+pub mod quantile;
+pub mod focal;
+pub mod gaussian_nll;
+
+/// A loss function and its derivative.
+///
+/// `y_true` and `y_pred` have shape `(j, n)` where `j` is the number of outputs and `n` the number of samples.
+pub trait LossFunction: Send + Sync {
+    fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar;
+
+    /// Derivative of the loss with respect to `y_pred`.
+    ///
+    /// It is the derivative of each sample's loss (of each element's for the losses averaging the elements),
+    /// not divided by the number of samples, the layers summing the gradients of the batch.
+    fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix;
+
+    /// Derivative of the loss with respect to the input of a softmax activation producing `y_pred`,
+    /// if the loss can be fused with a softmax.
+    ///
+    /// It is used instead of backpropagating through the softmax when the network ends with one.
+    ///
+    /// None by default, in which case the gradient goes through the softmax's jacobian.
+    fn softmax_loss_prime(&self, _y_true: &Matrix, _y_pred: &Matrix) -> Option<Matrix> {
+        None
+    }
+
+    /// Loss ignoring the missing targets.
+    ///
+    /// `mask` has the shape of `y_true`, holding 1 for the known targets and 0 for the missing ones,
+    /// which have been replaced by zeros in `y_true`.
+    ///
+    /// By default, the loss of each target (row) is computed on its known values and weighted by their number,
+    /// which is exact for the losses averaging the errors of every output of every sample.
+    fn masked_loss(&self, y_true: &Matrix, y_pred: &Matrix, mask: &Matrix) -> Scalar {
+        let (nrow, ncol) = mask.dim();
+        let mut total_loss = 0.;
+        let mut known_count = 0;
+        for i in 0..nrow {
+            let known = (0..ncol).filter(|&j| mask.index(i, j) > 0.).collect::<Vec<_>>();
+            if known.is_empty() {
+                continue;
+            }
+            let y_true_row = Matrix::from_fn(1, known.len(), |_, k| y_true.index(i, known[k]));
+            let y_pred_row = Matrix::from_fn(1, known.len(), |_, k| y_pred.index(i, known[k]));
+            total_loss += self.loss(&y_true_row, &y_pred_row) * known.len() as Scalar;
+            known_count += known.len();
+        }
+        if known_count == 0 {
+            0.
+        } else {
+            total_loss / known_count as Scalar
+        }
+    }
+
+    /// Derivative of `masked_loss` with respect to `y_pred`.
+    ///
+    /// By default, `loss_prime` with the derivatives of the missing targets zeroed (see `mask_gradient`).
+    fn masked_loss_prime(&self, y_true: &Matrix, y_pred: &Matrix, mask: &Matrix) -> Matrix {
+        mask_gradient(&self.loss_prime(y_true, y_pred), mask)
+    }
+
+    /// Same as `softmax_loss_prime` for `masked_loss`.
+    fn masked_softmax_loss_prime(&self, y_true: &Matrix, y_pred: &Matrix, mask: &Matrix) -> Option<Matrix> {
+        self.softmax_loss_prime(y_true, y_pred)
+            .map(|gradient| mask_gradient(&gradient, mask))
+    }
+
+    /// Point estimates of the targets from the predictions, for losses whose predictions
+    /// are not directly the targets (distributions, several quantiles...).
+    ///
+    /// The predictions themselves by default.
+    fn point_predictions(&self, y_pred: &Matrix) -> Matrix {
+        y_pred.clone()
+    }
+
+    /// Loss of each sample (column), whose mean is the loss of the whole batch.
+    ///
+    /// Computes the loss column by column by default, losses may override it with a faster version.
+    fn samples_losses(&self, y_true: &Matrix, y_pred: &Matrix) -> Vec<Scalar> {
+        (0..y_pred.dim().1)
+            .map(|j| {
+                self.loss(
+                    &y_true.get_column_as_matrix(j),
+                    &y_pred.get_column_as_matrix(j),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Zeroes the derivatives of the missing targets (where `mask` is 0) and scales up the others
+/// so that the gradient keeps the magnitude it would have without missing targets.
+///
+/// `mask` must have the shape of `gradient`.
+pub fn mask_gradient(gradient: &Matrix, mask: &Matrix) -> Matrix {
+    let (nrow, ncol) = mask.dim();
+    let known_count = mask.sum();
+    if known_count == 0. {
+        return Matrix::zeros(nrow, ncol);
+    }
+    gradient
+        .component_mul(mask)
+        .scalar_mul((nrow * ncol) as Scalar / known_count)
+}
+
+pub type LossFactory = dyn Fn(&serde_json::Value) -> Box<dyn LossFunction> + Send + Sync;
+
+lazy_static! {
+    static ref CUSTOM_LOSSES: RwLock<HashMap<String, Arc<LossFactory>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Registers a custom loss under `name`, so that `Losses::Custom` can refer to it.
+///
+/// The factory builds the loss from its parameters, as they are stored in the model's JSON.
+///
+/// Losses must be registered before any model using them is trained, in every program using them.
+pub fn register_loss<F>(name: &str, factory: F)
+where
+    F: Fn(&serde_json::Value) -> Box<dyn LossFunction> + Send + Sync + 'static,
+{
+    CUSTOM_LOSSES
+        .write()
+        .unwrap()
+        .insert(name.to_string(), Arc::new(factory));
+}
 
 #[derive(Serialize, Debug, Deserialize, Clone)]
 pub enum Losses {
     MSE,
     BCE,
+    /// Cross entropy between one-hot encoded targets and predicted probabilities (usually the output of a softmax).
+    ///
+    /// `label_smoothing` (between 0 and 1) spreads that much of the targets' probability uniformly across all classes.
+    CategoricalCrossEntropy {
+        #[serde(default)]
+        label_smoothing: Scalar,
+    },
+    /// Same as `CategoricalCrossEntropy` but the targets are a single feature holding the index of the class.
+    SparseCategoricalCrossEntropy {
+        #[serde(default)]
+        label_smoothing: Scalar,
+    },
+    /// Mean absolute error
+    MAE,
+    /// Quadratic for errors smaller than `delta`, linear beyond
+    Huber { delta: Scalar },
+    LogCosh,
+    /// Quantile (pinball) loss, predicting each target at each of the given quantiles (see `quantile::Quantile`)
+    Quantile { quantiles: Vec<Scalar> },
+    /// Binary cross entropy focusing on the hard samples (see `focal::Focal`)
+    Focal { gamma: Scalar, alpha: Scalar },
+    /// Gaussian negative log-likelihood of targets given their predicted mean and variance (see `gaussian_nll::GaussianNLL`)
+    GaussianNLL,
+    /// Loss registered with `register_loss`
+    Custom {
+        name: String,
+        #[serde(default)]
+        params: serde_json::Value,
+    },
 }
 
 impl Losses {
+    /// Whether the loss is meant for classifiers, whose predictions are probabilities.
+    pub fn is_classification(&self) -> bool {
+        matches!(
+            self,
+            Losses::BCE
+                | Losses::CategoricalCrossEntropy { .. }
+                | Losses::SparseCategoricalCrossEntropy { .. }
+                | Losses::Focal { .. }
+        )
+    }
+
+    /// Refers to a loss registered with `register_loss`, built with the given parameters.
+    pub fn custom<P: Serialize>(name: &str, params: P) -> Self {
+        Losses::Custom {
+            name: name.to_string(),
+            params: serde_json::to_value(params).unwrap(),
+        }
+    }
+
     pub fn to_loss(&self) -> Loss {
         match self {
-            Losses::MSE => mse::new(),
-            Losses::BCE => bce::new(),
+            Losses::MSE => Loss::new(mse::MSE),
+            Losses::BCE => Loss::new(bce::BCE),
+            Losses::CategoricalCrossEntropy { label_smoothing } => {
+                Loss::new(cce::CategoricalCrossEntropy::new(*label_smoothing, false))
+            }
+            Losses::SparseCategoricalCrossEntropy { label_smoothing } => {
+                Loss::new(cce::CategoricalCrossEntropy::new(*label_smoothing, true))
+            }
+            Losses::MAE => Loss::new(mae::MAE),
+            Losses::Huber { delta } => Loss::new(huber::Huber::new(*delta)),
+            Losses::LogCosh => Loss::new(log_cosh::LogCosh),
+            Losses::Quantile { quantiles } => Loss::new(quantile::Quantile::new(quantiles.clone())),
+            Losses::Focal { gamma, alpha } => Loss::new(focal::Focal::new(*gamma, *alpha)),
+            Losses::GaussianNLL => Loss::new(gaussian_nll::GaussianNLL),
+            Losses::Custom { name, params } => {
+                let factory = CUSTOM_LOSSES
+                    .read()
+                    .unwrap()
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| panic!("The loss {} must be registered with `register_loss`", name));
+                Loss(factory(params))
+            }
         }
     }
 }
 
-pub type LossFn = fn(&Matrix, &Matrix) -> Scalar;
-pub type LossPrimeFn = fn(&Matrix, &Matrix) -> Matrix;
-
-pub struct Loss {
-    loss: LossFn,
-    derivative: LossPrimeFn,
-}
+pub struct Loss(Box<dyn LossFunction>);
 
 impl Loss {
-    pub fn new(loss: LossFn, derivative: LossPrimeFn) -> Self {
-        Self { loss, derivative }
+    pub fn new<L: LossFunction + 'static>(loss: L) -> Self {
+        Self(Box::new(loss))
     }
 }
 
+/// The targets (`y_true`) may be partially missing, as NaN values which are masked out of the loss and its derivatives.
 impl Loss {
     pub fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
-        (self.loss)(y_true, y_pred)
+        match missing_targets(y_true) {
+            Some((y_true, mask)) => self.0.masked_loss(&y_true, y_pred, &mask),
+            None => self.0.loss(y_true, y_pred),
+        }
     }
 
     pub fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
-        (self.derivative)(y_true, y_pred)
+        match missing_targets(y_true) {
+            Some((y_true, mask)) => self.0.masked_loss_prime(&y_true, y_pred, &mask),
+            None => self.0.loss_prime(y_true, y_pred),
+        }
+    }
+
+    /// See `LossFunction::softmax_loss_prime`.
+    pub fn softmax_loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Option<Matrix> {
+        match missing_targets(y_true) {
+            Some((y_true, mask)) => self.0.masked_softmax_loss_prime(&y_true, y_pred, &mask),
+            None => self.0.softmax_loss_prime(y_true, y_pred),
+        }
+    }
+
+    /// Weighted average of the losses of the samples, `weights` holding one weight per sample (column).
+    ///
+    /// Samples whose targets are all missing are left out.
+    pub fn weighted_loss(&self, y_true: &Matrix, y_pred: &Matrix, weights: &[Scalar]) -> Scalar {
+        let (losses, weights) = match missing_targets(y_true) {
+            Some((y_true, mask)) => {
+                let losses = (0..y_pred.dim().1)
+                    .map(|j| {
+                        self.0.masked_loss(
+                            &y_true.get_column_as_matrix(j),
+                            &y_pred.get_column_as_matrix(j),
+                            &mask.get_column_as_matrix(j),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let weights = weights
+                    .iter()
+                    .enumerate()
+                    .map(|(j, w)| {
+                        if mask.get_column(j).iter().any(|m| *m > 0.) {
+                            *w
+                        } else {
+                            0.
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                (losses, weights)
+            }
+            None => (self.0.samples_losses(y_true, y_pred), weights.to_vec()),
+        };
+        let total_weight = weights.iter().sum::<Scalar>();
+        if total_weight == 0. {
+            return 0.;
+        }
+        losses
+            .iter()
+            .zip(weights.iter())
+            .map(|(loss, weight)| loss * weight)
+            .sum::<Scalar>()
+            / total_weight
+    }
+
+    /// Derivative of `weighted_loss` with respect to `y_pred`.
+    pub fn weighted_loss_prime(&self, y_true: &Matrix, y_pred: &Matrix, weights: &[Scalar]) -> Matrix {
+        scale_samples(&self.loss_prime(y_true, y_pred), weights)
     }
 
+    /// Same as `softmax_loss_prime` for `weighted_loss`.
+    pub fn weighted_softmax_loss_prime(
+        &self,
+        y_true: &Matrix,
+        y_pred: &Matrix,
+        weights: &[Scalar],
+    ) -> Option<Matrix> {
+        self.softmax_loss_prime(y_true, y_pred)
+            .map(|gradient| scale_samples(&gradient, weights))
+    }
+
+    /// Loss of each target (row of `y_true`), computed on its known values only.
+    pub fn targets_losses(&self, y_true: &Matrix, y_pred: &Matrix) -> Vec<Scalar> {
+        let (nrow, ncol) = y_true.dim();
+        let (y_true, mask) = missing_targets(y_true)
+            .unwrap_or_else(|| (y_true.clone(), Matrix::constant(nrow, ncol, 1.)));
+        (0..nrow)
+            .map(|i| {
+                let target_mask =
+                    Matrix::from_fn(nrow, ncol, |k, j| if k == i { mask.index(k, j) } else { 0. });
+                self.0.masked_loss(&y_true, y_pred, &target_mask)
+            })
+            .collect()
+    }
+
+    /// Same as `targets_losses` with the shapes of `loss_vec`.
+    pub fn targets_losses_vec(&self, y_true: &Vec<Vec<Scalar>>, y_pred: &Vec<Vec<Scalar>>) -> Vec<Scalar> {
+        let y_true = Matrix::from_column_leading_vector2(y_true);
+        let y_pred = Matrix::from_column_leading_vector2(y_pred);
+        self.targets_losses(&y_true, &y_pred)
+    }
+
+    /// See `LossFunction::point_predictions`, `y_pred` having the shape of `loss_vec`.
+    pub fn point_predictions_vec(&self, y_pred: &Vec<Vec<Scalar>>) -> Vec<Vec<Scalar>> {
+        self.0
+            .point_predictions(&Matrix::from_column_leading_vector2(y_pred))
+            .get_data_col_leading()
+    }
+
+    /// `y_true` and `y_pred` have shape `(n, j)` where `n` is the number of samples and `j` is the number of outputs.
     pub fn loss_vec(&self, y_true: &Vec<Vec<Scalar>>, y_pred: &Vec<Vec<Scalar>>) -> Scalar {
-        let y_true = Matrix::from_row_leading_vector2(&y_true);
-        let y_pred = Matrix::from_row_leading_vector2(&y_pred);
+        let y_true = Matrix::from_column_leading_vector2(&y_true);
+        let y_pred = Matrix::from_column_leading_vector2(&y_pred);
         self.loss(&y_true, &y_pred)
     }
 }
+
+// Returns the targets with their missing (NaN) values replaced by zeros and the mask of the known ones,
+// if any target is missing
+fn missing_targets(y_true: &Matrix) -> Option<(Matrix, Matrix)> {
+    let mask = y_true.map(|v| if v.is_nan() { 0. } else { 1. });
+    let (nrow, ncol) = mask.dim();
+    if mask.sum() == (nrow * ncol) as Scalar {
+        return None;
+    }
+    Some((y_true.map(|v| if v.is_nan() { 0. } else { v }), mask))
+}
+
+// Scales the gradient of each sample by its weight, normalized so that the weights average to 1
+//
+// The gradient is zero if all the weights are
+fn scale_samples(gradient: &Matrix, weights: &[Scalar]) -> Matrix {
+    let (nrow, ncol) = gradient.dim();
+    let total_weight = weights.iter().sum::<Scalar>();
+    if total_weight == 0. {
+        return Matrix::zeros(nrow, ncol);
+    }
+    let scale = ncol as Scalar / total_weight;
+    gradient.component_mul(&Matrix::from_fn(nrow, ncol, |_, j| weights[j] * scale))
+}
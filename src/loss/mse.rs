@@ -1,6 +1,6 @@
 use crate::{
     linalg::{Matrix, MatrixTrait, Scalar},
-    loss::Loss,
+    loss::LossFunction,
 };
 
 pub fn mse_vec(y_true: &Vec<Scalar>, y_pred: &Vec<Scalar>) -> Scalar {
@@ -21,6 +21,15 @@ fn mse_prime(y_pred: &Matrix, y_true: &Matrix) -> Matrix {
     (y_pred.component_sub(&y_true)).scalar_mul(-2.0)
 }
 
-pub fn new() -> Loss {
-    Loss::new(mse, mse_prime)
+/// Mean squared error
+pub struct MSE;
+
+impl LossFunction for MSE {
+    fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+        mse(y_true, y_pred)
+    }
+
+    fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+        mse_prime(y_true, y_pred)
+    }
 }
@@ -0,0 +1,107 @@
+use crate::{
+    linalg::{Matrix, MatrixTrait, Scalar},
+    loss::{mask_gradient, LossFunction},
+};
+
+// Repeats each target row once per quantile so that it lines up with the predictions
+fn expand_targets(y_true: &Matrix, n_quantiles: usize) -> Matrix {
+    let (nrow, ncol) = y_true.dim();
+    Matrix::from_fn(nrow * n_quantiles, ncol, |i, j| {
+        y_true.index(i / n_quantiles, j)
+    })
+}
+
+fn quantiles_matrix(quantiles: &[Scalar], nrow: usize, ncol: usize) -> Matrix {
+    Matrix::from_fn(nrow, ncol, |i, _| quantiles[i % quantiles.len()])
+}
+
+fn quantile_elements(quantiles: &[Scalar], y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+    let (nrow, ncol) = y_pred.dim();
+    let taus = quantiles_matrix(quantiles, nrow, ncol);
+    let errors = expand_targets(y_true, quantiles.len()).component_sub(y_pred);
+
+    // max(tau * e, (tau - 1) * e)
+    errors
+        .component_mul(&taus)
+        .maxof(&errors.component_mul(&taus.scalar_sub(1.)))
+}
+
+fn quantile(quantiles: &[Scalar], y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+    quantile_elements(quantiles, y_true, y_pred).mean()
+}
+
+fn quantile_prime(quantiles: &[Scalar], y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+    let (nrow, ncol) = y_pred.dim();
+    let errors = expand_targets(y_true, quantiles.len()).component_sub(y_pred);
+
+    Matrix::from_fn(nrow, ncol, |i, j| {
+        let tau = quantiles[i % quantiles.len()];
+        if errors.index(i, j) > 0. {
+            -tau
+        } else {
+            1. - tau
+        }
+    })
+}
+
+/// Quantile (aka. pinball) loss.
+///
+/// With several quantiles, the network must have `targets * quantiles` outputs:
+/// the outputs of each target are consecutive, one per quantile in the given order.
+/// Predicting for instance the quantiles `0.05` and `0.95` gives a 90% prediction interval.
+pub struct Quantile {
+    pub quantiles: Vec<Scalar>,
+}
+
+impl Quantile {
+    pub fn new(quantiles: Vec<Scalar>) -> Self {
+        assert!(!quantiles.is_empty());
+        assert!(quantiles.iter().all(|tau| *tau > 0. && *tau < 1.));
+        Self { quantiles }
+    }
+}
+
+impl LossFunction for Quantile {
+    fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+        quantile(&self.quantiles, y_true, y_pred)
+    }
+
+    fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+        quantile_prime(&self.quantiles, y_true, y_pred)
+    }
+
+    fn masked_loss(&self, y_true: &Matrix, y_pred: &Matrix, mask: &Matrix) -> Scalar {
+        let mask = expand_targets(mask, self.quantiles.len());
+        let known_count = mask.sum();
+        if known_count == 0. {
+            return 0.;
+        }
+        quantile_elements(&self.quantiles, y_true, y_pred)
+            .component_mul(&mask)
+            .sum()
+            / known_count
+    }
+
+    fn masked_loss_prime(&self, y_true: &Matrix, y_pred: &Matrix, mask: &Matrix) -> Matrix {
+        mask_gradient(
+            &quantile_prime(&self.quantiles, y_true, y_pred),
+            &expand_targets(mask, self.quantiles.len()),
+        )
+    }
+
+    /// The predictions of the quantile closest to the median.
+    fn point_predictions(&self, y_pred: &Matrix) -> Matrix {
+        let n_quantiles = self.quantiles.len();
+        let median = (0..n_quantiles)
+            .min_by(|&a, &b| {
+                (self.quantiles[a] - 0.5)
+                    .abs()
+                    .total_cmp(&(self.quantiles[b] - 0.5).abs())
+            })
+            .unwrap();
+        let (nrow, ncol) = y_pred.dim();
+        Matrix::from_fn(nrow / n_quantiles, ncol, |i, j| {
+            y_pred.index(i * n_quantiles + median, j)
+        })
+    }
+}
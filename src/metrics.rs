@@ -0,0 +1,271 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    benchmarking::{
+        accuracy, average_precision, classes_and_probabilities, log_loss, macro_precision_recall_f1,
+        one_vs_rest, roc_auc, top_k_accuracy, weighted_confusion_matrix,
+    },
+    linalg::Scalar,
+    loss::Losses,
+    vec_utils::{
+        class_of, explained_variance_score_vector2, max_error_vector2, mean_absolute_error_vector2,
+        mean_absolute_percentage_error_vector2, median_absolute_error_vector2, r2_score_vector2,
+        root_mean_squared_error_vector2, weighted_r2_score_vector2,
+    },
+};
+
+/// A validation metric computed by the trainers.
+///
+/// `y_true` and `y_pred` have shape `(n, j)` where `n` is the number of samples and `j` is the number of outputs,
+/// `y_pred` holding the point predictions of the model (see `LossFunction::point_predictions`).
+///
+/// `weights` holds the weight of each sample when the dataset or the model weights them,
+/// which the metrics take into account except the areas under the curves and the regression errors.
+pub trait Metric: Send + Sync {
+    /// Name under which the metric is stored in `EpochEvaluation::metrics`.
+    fn name(&self) -> String;
+
+    /// Value of the metric, NaN if it is undefined on these samples (see `compute_metrics`).
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], weights: Option<&[Scalar]>) -> Scalar;
+
+    /// Whether greater values are better, used to pick the best fold or checkpoint.
+    fn higher_is_better(&self) -> bool {
+        true
+    }
+}
+
+/// Computes each metric and stores its value under its name.
+///
+/// The undefined (NaN) values are left out, such as the ROC AUC of a single class.
+pub fn compute_metrics(
+    metrics: &[Arc<dyn Metric>],
+    y_true: &[Vec<Scalar>],
+    y_pred: &[Vec<Scalar>],
+    weights: Option<&[Scalar]>,
+) -> BTreeMap<String, Scalar> {
+    metrics
+        .iter()
+        .map(|metric| (metric.name(), metric.compute(y_true, y_pred, weights)))
+        .filter(|(_, value)| !value.is_nan())
+        .collect()
+}
+
+/// Metrics computed by the trainers when none is configured:
+/// the accuracy for classification losses and the R2 score otherwise.
+pub fn default_metrics(loss: &Losses) -> Vec<Arc<dyn Metric>> {
+    if loss.is_classification() {
+        vec![Arc::new(Accuracy)]
+    } else {
+        vec![Arc::new(R2)]
+    }
+}
+
+/// Coefficient of determination of all the outputs, weighted if the samples are.
+pub struct R2;
+
+impl Metric for R2 {
+    fn name(&self) -> String {
+        "r2".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], weights: Option<&[Scalar]>) -> Scalar {
+        match weights {
+            Some(weights) => weighted_r2_score_vector2(y_true, y_pred, weights),
+            None => r2_score_vector2(y_true, y_pred),
+        }
+    }
+}
+
+/// Weighted proportion of correctly classified samples (see `ClassificationMetrics` for the classes).
+pub struct Accuracy;
+
+impl Metric for Accuracy {
+    fn name(&self) -> String {
+        "accuracy".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], weights: Option<&[Scalar]>) -> Scalar {
+        let (classes, probabilities, weights) = classes_and_probabilities(y_true, y_pred, weights);
+        let predicted = probabilities.iter().map(|p| class_of(p)).collect::<Vec<_>>();
+        accuracy(&classes, &predicted, &weights)
+    }
+}
+
+/// Weighted proportion of samples whose class is among the `k` most probable predicted ones.
+pub struct TopKAccuracy {
+    pub k: usize,
+}
+
+impl Metric for TopKAccuracy {
+    fn name(&self) -> String {
+        format!("top_{}_accuracy", self.k)
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], weights: Option<&[Scalar]>) -> Scalar {
+        let (classes, probabilities, weights) = classes_and_probabilities(y_true, y_pred, weights);
+        top_k_accuracy(&classes, &probabilities, self.k, &weights)
+    }
+}
+
+/// F1 score of each class, from the weighted counts of the samples, averaged over the classes.
+pub struct F1Macro;
+
+impl Metric for F1Macro {
+    fn name(&self) -> String {
+        "f1_macro".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], weights: Option<&[Scalar]>) -> Scalar {
+        let (classes, probabilities, weights) = classes_and_probabilities(y_true, y_pred, weights);
+        let n_classes = probabilities.first().map_or(0, |p| p.len());
+        let predicted = probabilities.iter().map(|p| class_of(p)).collect::<Vec<_>>();
+        macro_precision_recall_f1(&weighted_confusion_matrix(&classes, &predicted, n_classes, &weights)).2
+    }
+}
+
+/// Area under the ROC curve (one-vs-rest macro average if there are more than two classes), unweighted.
+pub struct RocAuc;
+
+impl Metric for RocAuc {
+    fn name(&self) -> String {
+        "roc_auc".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], _weights: Option<&[Scalar]>) -> Scalar {
+        let (classes, probabilities, _) = classes_and_probabilities(y_true, y_pred, None);
+        one_vs_rest(&classes, &probabilities, roc_auc).unwrap_or(Scalar::NAN)
+    }
+}
+
+/// Area under the precision-recall curve (one-vs-rest macro average if there are more than two classes), unweighted.
+pub struct PrAuc;
+
+impl Metric for PrAuc {
+    fn name(&self) -> String {
+        "pr_auc".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], _weights: Option<&[Scalar]>) -> Scalar {
+        let (classes, probabilities, _) = classes_and_probabilities(y_true, y_pred, None);
+        one_vs_rest(&classes, &probabilities, average_precision).unwrap_or(Scalar::NAN)
+    }
+}
+
+/// Weighted mean negative log of the probability predicted for the true class.
+pub struct LogLoss;
+
+impl Metric for LogLoss {
+    fn name(&self) -> String {
+        "log_loss".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], weights: Option<&[Scalar]>) -> Scalar {
+        let (classes, probabilities, weights) = classes_and_probabilities(y_true, y_pred, weights);
+        log_loss(&classes, &probabilities, &weights)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        false
+    }
+}
+
+/// Mean absolute error of all the outputs.
+///
+/// Like the other regression metrics, it is in the original units of the targets
+/// when the trainer reverts the predictions (see `SplitTraining::metrics_in_original_units`).
+pub struct Mae;
+
+impl Metric for Mae {
+    fn name(&self) -> String {
+        "mae".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], _weights: Option<&[Scalar]>) -> Scalar {
+        mean_absolute_error_vector2(y_true, y_pred)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        false
+    }
+}
+
+/// Root mean squared error of all the outputs.
+pub struct Rmse;
+
+impl Metric for Rmse {
+    fn name(&self) -> String {
+        "rmse".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], _weights: Option<&[Scalar]>) -> Scalar {
+        root_mean_squared_error_vector2(y_true, y_pred)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        false
+    }
+}
+
+/// Mean absolute percentage error of all the outputs, as a fraction. Zero targets are ignored.
+pub struct Mape;
+
+impl Metric for Mape {
+    fn name(&self) -> String {
+        "mape".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], _weights: Option<&[Scalar]>) -> Scalar {
+        mean_absolute_percentage_error_vector2(y_true, y_pred)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        false
+    }
+}
+
+/// Median absolute error of all the outputs, robust to outliers.
+pub struct MedianAbsoluteError;
+
+impl Metric for MedianAbsoluteError {
+    fn name(&self) -> String {
+        "median_ae".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], _weights: Option<&[Scalar]>) -> Scalar {
+        median_absolute_error_vector2(y_true, y_pred)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        false
+    }
+}
+
+/// Largest absolute error over all the outputs.
+pub struct MaxError;
+
+impl Metric for MaxError {
+    fn name(&self) -> String {
+        "max_error".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], _weights: Option<&[Scalar]>) -> Scalar {
+        max_error_vector2(y_true, y_pred)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        false
+    }
+}
+
+/// Explained variance score of all the outputs.
+pub struct ExplainedVariance;
+
+impl Metric for ExplainedVariance {
+    fn name(&self) -> String {
+        "explained_variance".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], _weights: Option<&[Scalar]>) -> Scalar {
+        explained_variance_score_vector2(y_true, y_pred)
+    }
+}
@@ -1,7 +1,8 @@
 
 use serde::{Serialize, Deserialize};
+use serde_aux::field_attributes::bool_true;
 
-use crate::{network::NetworkLayer, vision::{conv_network::{ConvNetwork, ConvNetworkLayer}, conv_layer::avg_pooling_layer::AvgPoolingLayer}};
+use crate::{layer::defaults::default_learning_rate_multiplier, learning_rate::LearningRateSchedule, linalg::Scalar, network::NetworkLayer, vision::{conv_network::{ConvNetwork, ConvNetworkLayer}, conv_layer::avg_pooling_layer::AvgPoolingLayer}};
 
 use super::{full_dense_conv_layer_model::{FullDenseConvLayerModel, FullDenseConvLayerModelBuilder}, network_model::NetworkModelBuilder, full_direct_conv_layer_model::{FullDirectConvLayerModel, FullDirectConvLayerModelBuilder}};
 
@@ -13,7 +14,12 @@ pub struct ConvNetworkModelBuilder {
 impl ConvNetworkModelBuilder {
     pub fn new(parent: NetworkModelBuilder, in_channels: usize) -> Self {
         Self { 
-            model: ConvNetworkModel { layers: vec![], in_channels },
+            model: ConvNetworkModel {
+                layers: vec![],
+                in_channels,
+                trainable: true,
+                learning_rate_multiplier: default_learning_rate_multiplier(),
+            },
             parent,
         }
     }
@@ -30,6 +36,20 @@ impl ConvNetworkModelBuilder {
         FullDirectConvLayerModelBuilder::new(self, kernels_size)
     }
 
+    /// Disables the updates of all the convolutional layers' parameters during training.
+    pub fn frozen(mut self) -> Self {
+        self.model.trainable = false;
+        self
+    }
+
+    /// Scales the learning rate of all the convolutional layers' optimizers.
+    ///
+    /// It is combined with the multipliers set on the layers themselves.
+    pub fn learning_rate_multiplier(mut self, multiplier: Scalar) -> Self {
+        self.model.learning_rate_multiplier = multiplier;
+        self
+    }
+
     pub fn avg_pooling(mut self, kernel_size: usize) -> Self {
         self.model.layers.push(ConvNetworkLayerModels::AvgPooling { kernel_size });
         self
@@ -50,6 +70,10 @@ impl ConvNetworkModelBuilder {
 pub struct ConvNetworkModel {
     pub in_channels: usize,
     pub layers: Vec<ConvNetworkLayerModels>,
+    #[serde(default = "bool_true")]
+    pub trainable: bool,
+    #[serde(default = "default_learning_rate_multiplier")]
+    pub learning_rate_multiplier: Scalar,
 }
 
 impl ConvNetworkModel {
@@ -59,9 +83,16 @@ impl ConvNetworkModel {
         let mut in_img_dims = (in_dims as f64 / in_channels as f64).sqrt() as usize;
 
         for layer_config in self.layers.into_iter() {
-            let (out_img_dims, out_channels, conv_layer) = layer_config
+            let (out_img_dims, out_channels, mut conv_layer) = layer_config
                 .to_conv_layer(in_img_dims, in_channels);
 
+            if let Some(layer) = conv_layer.as_trainable_layer_mut() {
+                layer.set_trainable(layer.is_trainable() && self.trainable);
+                layer.set_learning_rate_multiplier(
+                    layer.get_learning_rate_multiplier() * self.learning_rate_multiplier,
+                );
+            }
+
             in_img_dims = out_img_dims;
             in_channels = out_channels;
             layers.push(conv_layer);
@@ -70,6 +101,12 @@ impl ConvNetworkModel {
         let network_layer = ConvNetwork::new(layers, self.in_channels);
         (in_img_dims * in_img_dims * in_channels, Box::new(network_layer))
     }
+
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        for layer in self.layers.iter_mut() {
+            layer.set_learning_rate(learning_rate.clone());
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -94,4 +131,12 @@ impl ConvNetworkLayerModels {
             }
         }
     }
+
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        match self {
+            Self::FullDenseConv(model) => model.set_learning_rate(learning_rate),
+            Self::FullDirectConv(model) => model.set_learning_rate(learning_rate),
+            Self::AvgPooling { .. } => {}
+        }
+    }
 }
\ No newline at end of file
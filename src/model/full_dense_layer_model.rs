@@ -1,6 +1,7 @@
 use serde::{Serialize, Deserialize};
+use serde_aux::field_attributes::bool_true;
 
-use crate::{activation::Activation, initializers::Initializers, optimizer::{Optimizers, sgd, momentum, adam}, layer::{dense_layer::DenseLayer, full_layer::FullLayer}, network::NetworkLayer};
+use crate::{activation::Activation, initializers::Initializers, learning_rate::LearningRateSchedule, optimizer::{Optimizers, sgd, momentum, adam}, layer::{dense_layer::DenseLayer, full_layer::FullLayer, defaults::{default_learning_rate_multiplier, default_weights_initializer_for}, TrainableLayer}, network::NetworkLayer, linalg::Scalar};
 
 use super::network_model::NetworkModelBuilder;
 
@@ -13,7 +14,11 @@ pub struct FullDenseLayerModel {
     pub weights_initializer: Initializers,
     pub biases_optimizer: Optimizers,
     pub weights_optimizer: Optimizers,
-    pub dropout: Option<f32>
+    pub dropout: Option<f32>,
+    #[serde(default = "bool_true")]
+    pub trainable: bool,
+    #[serde(default = "default_learning_rate_multiplier")]
+    pub learning_rate_multiplier: Scalar,
 }
 
 impl FullDenseLayerModel {
@@ -27,19 +32,28 @@ impl FullDenseLayerModel {
             self.biases_initializer,
         );
 
-        let layer = FullLayer::new(
+        let mut layer = FullLayer::new(
             inner,
             self.activation.to_layer(),
             self.dropout
         );
+        layer.set_trainable(self.trainable);
+        layer.set_learning_rate_multiplier(self.learning_rate_multiplier);
 
         (self.size, Box::new(layer))
     }
+
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        self.biases_optimizer.set_learning_rate(learning_rate.clone());
+        self.weights_optimizer.set_learning_rate(learning_rate);
+    }
 }
 
 pub struct FullDenseLayerModelBuilder {
     pub model: FullDenseLayerModel,
-    parent: NetworkModelBuilder
+    parent: NetworkModelBuilder,
+    // once set explicitly, the weights initializer doesn't follow the activation anymore
+    weights_initializer_set: bool,
 }
 
 impl FullDenseLayerModelBuilder {
@@ -49,12 +63,15 @@ impl FullDenseLayerModelBuilder {
                 size,
                 activation: Activation::ReLU,
                 biases_initializer: Initializers::Zeros,
-                weights_initializer: Initializers::GlorotUniform,
+                weights_initializer: default_weights_initializer_for(Activation::ReLU),
                 biases_optimizer: sgd(),
                 weights_optimizer: sgd(),
-                dropout: None
+                dropout: None,
+                trainable: true,
+                learning_rate_multiplier: default_learning_rate_multiplier(),
             },
             parent,
+            weights_initializer_set: false,
         }
     }
 
@@ -72,10 +89,46 @@ impl FullDenseLayerModelBuilder {
         }
     }
 
+    /// Disables the updates of the layer's parameters during training.
+    pub fn frozen(self) -> Self {
+        self.trainable(false)
+    }
+
+    pub fn trainable(self, trainable: bool) -> Self {
+        Self {
+            model: FullDenseLayerModel {
+                trainable,
+                ..self.model
+            },
+            ..self
+        }
+    }
+
+    /// Scales the learning rate of the layer's optimizers.
+    pub fn learning_rate_multiplier(self, multiplier: Scalar) -> Self {
+        Self {
+            model: FullDenseLayerModel {
+                learning_rate_multiplier: multiplier,
+                ..self.model
+            },
+            ..self
+        }
+    }
+
+    /// Sets the activation function of the layer.
+    ///
+    /// Unless set explicitly, the weights initializer is changed to the one that fits the activation.
     pub fn activation(self, activation: Activation) -> Self {
+        let weights_initializer = if self.weights_initializer_set {
+            self.model.weights_initializer.clone()
+        } else {
+            default_weights_initializer_for(activation)
+        };
+
         Self {
             model: FullDenseLayerModel {
                 activation,
+                weights_initializer,
                 ..self.model
             },
             ..self
@@ -102,6 +155,11 @@ impl FullDenseLayerModelBuilder {
         self.activation(Activation::Softmax)
     }
 
+    /// Outputs means and softplus-activated variances, for a Gaussian NLL loss.
+    pub fn mean_variance(self) -> Self {
+        self.activation(Activation::MeanVariance)
+    }
+
     pub fn init_zeros(self) -> Self {
         self.init(Initializers::Zeros)
     }
@@ -139,15 +197,47 @@ impl FullDenseLayerModelBuilder {
     }
 
     pub fn weights_init_uniform(self) -> Self {
-        self.biases_init(Initializers::Uniform)
+        self.weights_init(Initializers::Uniform)
     }
 
     pub fn weights_init_uniform_signed(self) -> Self {
-        self.biases_init(Initializers::UniformSigned)
+        self.weights_init(Initializers::UniformSigned)
     }
 
     pub fn weights_init_glorot_uniform(self) -> Self {
-        self.biases_init(Initializers::GlorotUniform)
+        self.weights_init(Initializers::GlorotUniform)
+    }
+
+    pub fn weights_init_glorot_normal(self) -> Self {
+        self.weights_init(Initializers::GlorotNormal)
+    }
+
+    pub fn weights_init_he_uniform(self) -> Self {
+        self.weights_init(Initializers::HeUniform)
+    }
+
+    pub fn weights_init_he_normal(self) -> Self {
+        self.weights_init(Initializers::HeNormal)
+    }
+
+    pub fn weights_init_lecun_uniform(self) -> Self {
+        self.weights_init(Initializers::LeCunUniform)
+    }
+
+    pub fn weights_init_lecun_normal(self) -> Self {
+        self.weights_init(Initializers::LeCunNormal)
+    }
+
+    pub fn weights_init_truncated_normal(self, mean: Scalar, std_dev: Scalar) -> Self {
+        self.weights_init(Initializers::TruncatedNormal { mean, std_dev })
+    }
+
+    pub fn weights_init_orthogonal(self) -> Self {
+        self.weights_init(Initializers::Orthogonal)
+    }
+
+    pub fn biases_init_constant(self, value: Scalar) -> Self {
+        self.biases_init(Initializers::Constant(value))
     }
 
     pub fn sgd(self) -> Self {
@@ -232,6 +322,7 @@ impl FullDenseLayerModelBuilder {
                 weights_initializer: initializer,
                 ..self.model
             },
+            weights_initializer_set: true,
             ..self
         }
     }
@@ -1,6 +1,8 @@
 use serde::{Serialize, Deserialize};
+use serde_aux::field_attributes::bool_true;
 
-use crate::vision::{conv_initializers::ConvInitializers, conv_activation::ConvActivation, conv_optimizer::{ConvOptimizers, conv_sgd, conv_momentum, conv_adam}, conv_network::ConvNetworkLayer, conv_layer::{direct_conv_layer::DirectConvLayer, full_conv_layer::FullConvLayer}};
+use crate::{layer::{defaults::default_learning_rate_multiplier, TrainableLayer}, learning_rate::LearningRateSchedule, linalg::Scalar};
+use crate::vision::{conv_layer::defaults::default_kernels_initializer_for, conv_initializers::ConvInitializers, conv_activation::ConvActivation, conv_optimizer::{ConvOptimizers, conv_sgd, conv_momentum, conv_adam}, conv_network::ConvNetworkLayer, conv_layer::{direct_conv_layer::DirectConvLayer, full_conv_layer::FullConvLayer}};
 
 use super::conv_network_model::ConvNetworkModelBuilder;
 
@@ -13,7 +15,11 @@ pub struct FullDirectConvLayerModel {
     pub kernels_initializer: ConvInitializers,
     pub biases_optimizer: ConvOptimizers,
     pub kernels_optimizer: ConvOptimizers,
-    pub dropout: Option<f32>
+    pub dropout: Option<f32>,
+    #[serde(default = "bool_true")]
+    pub trainable: bool,
+    #[serde(default = "default_learning_rate_multiplier")]
+    pub learning_rate_multiplier: Scalar,
 }
 
 impl FullDirectConvLayerModel {
@@ -36,19 +42,28 @@ impl FullDirectConvLayerModel {
             self.kernels_size,
         );
         
-        let layer = FullConvLayer::new(
+        let mut layer = FullConvLayer::new(
             Box::new(inner_layer),
             self.activation.to_layer(),
             self.dropout
         );
+        layer.set_trainable(self.trainable);
+        layer.set_learning_rate_multiplier(self.learning_rate_multiplier);
 
         (out_img_dims, out_channels, Box::new(layer))
     }
+
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        self.biases_optimizer.set_learning_rate(learning_rate.clone());
+        self.kernels_optimizer.set_learning_rate(learning_rate);
+    }
 }
 
 pub struct FullDirectConvLayerModelBuilder {
     pub model: FullDirectConvLayerModel,
-    parent: ConvNetworkModelBuilder
+    parent: ConvNetworkModelBuilder,
+    // once set explicitly, the kernels initializer doesn't follow the activation anymore
+    kernels_initializer_set: bool,
 }
 
 impl FullDirectConvLayerModelBuilder {
@@ -58,12 +73,15 @@ impl FullDirectConvLayerModelBuilder {
                 kernels_size,
                 activation: ConvActivation::ConvReLU,
                 biases_initializer: ConvInitializers::Zeros,
-                kernels_initializer: ConvInitializers::GlorotUniform,
+                kernels_initializer: default_kernels_initializer_for(ConvActivation::ConvReLU),
                 biases_optimizer: conv_sgd(),
                 kernels_optimizer: conv_sgd(),
-                dropout: None
+                dropout: None,
+                trainable: true,
+                learning_rate_multiplier: default_learning_rate_multiplier(),
             },
             parent,
+            kernels_initializer_set: false,
         }
     }
 
@@ -81,10 +99,46 @@ impl FullDirectConvLayerModelBuilder {
         }
     }
 
+    /// Disables the updates of the layer's parameters during training.
+    pub fn frozen(self) -> Self {
+        self.trainable(false)
+    }
+
+    pub fn trainable(self, trainable: bool) -> Self {
+        Self {
+            model: FullDirectConvLayerModel {
+                trainable,
+                ..self.model
+            },
+            ..self
+        }
+    }
+
+    /// Scales the learning rate of the layer's optimizers.
+    pub fn learning_rate_multiplier(self, multiplier: Scalar) -> Self {
+        Self {
+            model: FullDirectConvLayerModel {
+                learning_rate_multiplier: multiplier,
+                ..self.model
+            },
+            ..self
+        }
+    }
+
+    /// Sets the activation function of the layer.
+    ///
+    /// Unless set explicitly, the kernels initializer is changed to the one that fits the activation.
     pub fn activation(self, activation: ConvActivation) -> Self {
+        let kernels_initializer = if self.kernels_initializer_set {
+            self.model.kernels_initializer.clone()
+        } else {
+            default_kernels_initializer_for(activation)
+        };
+
         Self {
             model: FullDirectConvLayerModel {
                 activation,
+                kernels_initializer,
                 ..self.model
             },
             ..self
@@ -144,15 +198,47 @@ impl FullDirectConvLayerModelBuilder {
     }
 
     pub fn kernels_init_uniform(self) -> Self {
-        self.biases_init(ConvInitializers::Uniform)
+        self.kernels_init(ConvInitializers::Uniform)
     }
 
     pub fn kernels_init_uniform_signed(self) -> Self {
-        self.biases_init(ConvInitializers::UniformSigned)
+        self.kernels_init(ConvInitializers::UniformSigned)
     }
 
     pub fn kernels_init_glorot_uniform(self) -> Self {
-        self.biases_init(ConvInitializers::GlorotUniform)
+        self.kernels_init(ConvInitializers::GlorotUniform)
+    }
+
+    pub fn kernels_init_glorot_normal(self) -> Self {
+        self.kernels_init(ConvInitializers::GlorotNormal)
+    }
+
+    pub fn kernels_init_he_uniform(self) -> Self {
+        self.kernels_init(ConvInitializers::HeUniform)
+    }
+
+    pub fn kernels_init_he_normal(self) -> Self {
+        self.kernels_init(ConvInitializers::HeNormal)
+    }
+
+    pub fn kernels_init_lecun_uniform(self) -> Self {
+        self.kernels_init(ConvInitializers::LeCunUniform)
+    }
+
+    pub fn kernels_init_lecun_normal(self) -> Self {
+        self.kernels_init(ConvInitializers::LeCunNormal)
+    }
+
+    pub fn kernels_init_truncated_normal(self, mean: Scalar, std_dev: Scalar) -> Self {
+        self.kernels_init(ConvInitializers::TruncatedNormal { mean, std_dev })
+    }
+
+    pub fn kernels_init_orthogonal(self) -> Self {
+        self.kernels_init(ConvInitializers::Orthogonal)
+    }
+
+    pub fn biases_init_constant(self, value: Scalar) -> Self {
+        self.biases_init(ConvInitializers::Constant(value))
     }
 
     pub fn sgd(self) -> Self {
@@ -237,6 +323,7 @@ impl FullDirectConvLayerModelBuilder {
                 kernels_initializer: initializer,
                 ..self.model
             },
+            kernels_initializer_set: true,
             ..self
         }
     }
@@ -13,10 +13,16 @@ use sha2::{Digest, Sha256};
 use crate::dataset::Dataset;
 #[cfg(feature = "data")]
 use crate::datatable::DataTable;
+#[cfg(feature = "data")]
+use crate::preprocessing::sample::SamplingStrategy;
+#[cfg(feature = "data")]
+use crate::{network::WeightedBatch, streaming::DataStream};
 
-use crate::linalg::Scalar;
+use crate::linalg::{Matrix, MatrixTrait, Scalar};
+use crate::loader::DataLoader;
 use crate::loss::Losses;
 use crate::network::{Network};
+use crate::vec_utils::class_of;
 
 use self::network_model::{NetworkModel, NetworkModelBuilder};
 
@@ -25,6 +31,8 @@ pub mod conv_network_model;
 pub mod full_dense_layer_model;
 pub mod full_direct_conv_layer_model;
 pub mod full_dense_conv_layer_model;
+#[cfg(feature = "data")]
+pub mod online;
 
 pub struct ModelBuilder {
     pub model: Model
@@ -39,7 +47,11 @@ impl ModelBuilder {
                 loss: Losses::MSE,
                 epochs: 100,
                 batch_size: Some(32),
-                network: None
+                network: None,
+                seed: None,
+                class_weights: None,
+                sampling: None,
+                data_parallel: None,
             }
         }
     }
@@ -51,7 +63,10 @@ impl ModelBuilder {
                 loss: Losses::MSE,
                 epochs: 100,
                 batch_size: Some(32),
-                network: None
+                network: None,
+                seed: None,
+                class_weights: None,
+                data_parallel: None,
             }
         }
     }
@@ -86,6 +101,59 @@ impl ModelBuilder {
         }
     }
 
+    /// Seeds every random operation (initialization, dropout, shuffling...) of the trainers using this model.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            model: Model {
+                seed: Some(seed),
+                ..self.model
+            },
+        }
+    }
+
+    /// Weights the samples of each class in the loss and the validation metrics.
+    ///
+    /// The class of a sample is the index of its largest predicted feature (one-hot encoded classes),
+    /// or the value of its predicted feature if there is only one (class indices).
+    pub fn class_weights(self, class_weights: Vec<Scalar>) -> Self {
+        Self {
+            model: Model {
+                class_weights: Some(class_weights),
+                ..self.model
+            },
+        }
+    }
+
+    #[cfg(feature = "data")]
+    /// Resamples the training data at each epoch to balance its classes (see `SamplingStrategy`).
+    pub fn sampling(self, sampling: SamplingStrategy) -> Self {
+        Self {
+            model: Model {
+                sampling: Some(sampling),
+                ..self.model
+            },
+        }
+    }
+
+    /// Splits each mini-batch between `threads` replicas of the network, whose gradients are summed
+    /// before a single optimizer step (see `Network::train_data_parallel_with_hook`).
+    ///
+    /// Only available with a thread safe backend (see `MatrixTrait::is_backend_thread_safe`),
+    /// and only used by networks whose layers all support it (full dense networks).
+    pub fn data_parallel(self, threads: usize) -> Self {
+        assert!(
+            Matrix::is_backend_thread_safe(),
+            "The data-parallel training requires a thread safe backend"
+        );
+        assert!(threads > 0);
+        Self {
+            model: Model {
+                data_parallel: Some(threads),
+                ..self.model
+            },
+        }
+    }
+
     pub fn neural_network(self) -> NetworkModelBuilder {
         NetworkModelBuilder::new().set_parent(self)
     }
@@ -107,7 +175,16 @@ pub struct Model {
     pub loss: Losses,
     pub batch_size: Option<usize>,
     pub dataset_config: Dataset,
-    pub network: Option<NetworkModel>
+    pub network: Option<NetworkModel>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub class_weights: Option<Vec<Scalar>>,
+    #[serde(default)]
+    pub sampling: Option<SamplingStrategy>,
+    /// Number of threads training each mini-batch (see `ModelBuilder::data_parallel`)
+    #[serde(default)]
+    pub data_parallel: Option<usize>,
 }
 
 #[cfg(not(feature = "data"))]
@@ -116,7 +193,14 @@ pub struct Model {
     pub epochs: usize,
     pub loss: Losses,
     pub batch_size: Option<usize>,
-    pub network: Option<NetworkModel>
+    pub network: Option<NetworkModel>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub class_weights: Option<Vec<Scalar>>,
+    /// Number of threads training each mini-batch (see `ModelBuilder::data_parallel`)
+    #[serde(default)]
+    pub data_parallel: Option<usize>,
 }
 
 impl Model {
@@ -173,21 +257,258 @@ impl Model {
         train_data: &DataTable,
         id_column: &str,
     ) -> Scalar {
-        let (train_x_table, train_y_table) =
-            train_data.random_order_in_out(&self.dataset_config.predicted_features_names());
+        self.train_epoch_with_hook(epoch, network, train_data, id_column, &mut |_, _, _| false)
+    }
 
-        let train_x = train_x_table.drop_column(id_column).to_vectors();
-        let train_y = train_y_table.to_vectors();
+    #[cfg(feature = "data")]
+    /// Same as `train_epoch` but calls `on_batch_end` after each mini-batch (see `Network::train_weighted_with_hook`).
+    pub fn train_epoch_with_hook(
+        &self,
+        epoch: usize,
+        network: &mut Network,
+        train_data: &DataTable,
+        id_column: &str,
+        on_batch_end: &mut dyn FnMut(usize, Scalar, &Network) -> bool,
+    ) -> Scalar {
+        let (train_x, train_y, train_weights) = self.epoch_vectors(train_data, id_column);
 
-        let train_loss = network.train(
+        self.train_network(
+            network,
+            || self.to_network(),
             epoch,
             &train_x,
             &train_y,
-            &self.loss.to_loss(),
-            self.batch_size.unwrap_or(train_x.len()),
+            train_weights.as_deref(),
+            on_batch_end,
+        )
+    }
+
+    #[cfg(feature = "data")]
+    /// Trains an already trained network on new rows for the model's epochs (online learning, see `OnlineModel`),
+    /// numbering them from `first_epoch` so that the learning rate schedules go on where they stopped.
+    ///
+    /// The rows must be preprocessed with the fitted pipeline of the first training (see `Pipeline::apply`),
+    /// and the network should have its optimizers' state restored (see `Network::load_optimizers_state`).
+    ///
+    /// Returns the training loss of the last epoch.
+    pub fn partial_fit(&self, network: &mut Network, rows: &DataTable, first_epoch: usize) -> Scalar {
+        let id_column = self
+            .dataset_config
+            .get_id_column()
+            .expect("One feature must be configured as an id in the dataset configuration.");
+        let mut loss = Scalar::NAN;
+        for epoch in first_epoch..first_epoch + self.epochs {
+            loss = self.train_epoch(epoch, network, rows, id_column);
+        }
+        loss
+    }
+
+    #[cfg(feature = "data")]
+    /// Same as `train_epoch_with_hook` but reads the training data by mini-batches from a `DataStream`,
+    /// so that it never needs to fit in memory.
+    ///
+    /// The stream's file must hold the data as the network expects it (already preprocessed, see `Pipeline`).
+    /// Without a batch size, each mini-batch is a chunk of the stream.
+    ///
+    /// The sampling strategies and the data-parallel training are not applied when streaming.
+    pub fn train_epoch_streaming(
+        &self,
+        epoch: usize,
+        network: &mut Network,
+        stream: &DataStream,
+        id_column: &str,
+        on_batch_end: &mut dyn FnMut(usize, Scalar, &Network) -> bool,
+    ) -> Scalar {
+        let predicted_features = self.dataset_config.predicted_features_names();
+        let batches = stream
+            .batches(self.batch_size.unwrap_or(stream.chunk_size))
+            .map(|batch| {
+                let x_table = batch.drop_columns(&predicted_features);
+                let y = batch.select_columns(&predicted_features).to_vectors_with_missing();
+                let x = self.inputs_to_vectors(&x_table, id_column);
+                let weights = self.samples_weights(&x_table, &y);
+                (x, y, weights)
+            });
+
+        network.train_batches_with_hook(epoch, batches, &self.loss.to_loss(), on_batch_end)
+    }
+
+    #[cfg(feature = "data")]
+    /// Shuffles (or resamples, see `SamplingStrategy`) the training data for an epoch,
+    /// and converts it to the network's inputs, targets and samples' weights.
+    pub fn epoch_vectors(
+        &self,
+        train_data: &DataTable,
+        id_column: &str,
+    ) -> WeightedBatch {
+        let predicted_features = self.dataset_config.predicted_features_names();
+        let (train_x_table, train_y_table) = match self.sampling {
+            // The order of the balanced batches must be kept
+            Some(SamplingStrategy::BalancedBatches) => {
+                let train_data =
+                    SamplingStrategy::BalancedBatches.apply(train_data, &predicted_features);
+                (
+                    train_data.drop_columns(&predicted_features),
+                    train_data.select_columns(&predicted_features),
+                )
+            }
+            Some(sampling) => sampling
+                .apply(train_data, &predicted_features)
+                .random_order_in_out(&predicted_features),
+            None => train_data.random_order_in_out(&predicted_features),
+        };
+
+        let train_x = self.inputs_to_vectors(&train_x_table, id_column);
+        let train_y = train_y_table.to_vectors_with_missing();
+        let train_weights = self.samples_weights(&train_x_table, &train_y);
+        (train_x, train_y, train_weights)
+    }
+
+    /// Same as `train_epoch_with_hook` but the mini-batches come from a `DataLoader` (see `Network::train_loader_with_hook`).
+    ///
+    /// The loader is in charge of the mini-batches, so the model must not set the samples' weights,
+    /// the class weights, a sampling strategy or data-parallel training, and its batch size, if any,
+    /// must be the loader's. Panics otherwise rather than ignoring them.
+    pub fn train_epoch_loader(
+        &self,
+        epoch: usize,
+        network: &mut Network,
+        loader: &mut dyn DataLoader,
+        on_batch_end: &mut dyn FnMut(usize, Scalar, &Network) -> bool,
+    ) -> Scalar {
+        self.assert_loader_compatible(loader);
+        network.train_loader_with_hook(epoch, loader, &self.loss.to_loss(), on_batch_end)
+    }
+
+    // Panics if the model sets something the loaders don't apply
+    fn assert_loader_compatible(&self, loader: &dyn DataLoader) {
+        if let Some(batch_size) = self.batch_size {
+            assert_eq!(
+                batch_size,
+                loader.options().batch_size,
+                "The model's batch size must be the loader's"
+            );
+        }
+        assert!(self.class_weights.is_none(), "The loaders don't apply the class weights");
+        assert!(self.data_parallel.is_none(), "The loaders don't train data-parallel");
+        #[cfg(feature = "data")]
+        {
+            assert!(self.sampling.is_none(), "The loaders don't apply the sampling strategies");
+            assert!(
+                self.dataset_config.get_sample_weight_column().is_none(),
+                "The loaders don't apply the samples' weights"
+            );
+        }
+    }
+
+    // Trains the network for an epoch, splitting the mini-batches between replicas if the model is data-parallel
+    #[allow(clippy::too_many_arguments)]
+    fn train_network<F: Fn() -> Network>(
+        &self,
+        network: &mut Network,
+        make_replica: F,
+        epoch: usize,
+        train_x: &Vec<Vec<Scalar>>,
+        train_y: &Vec<Vec<Scalar>>,
+        train_weights: Option<&[Scalar]>,
+        on_batch_end: &mut dyn FnMut(usize, Scalar, &Network) -> bool,
+    ) -> Scalar {
+        let loss = self.loss.to_loss();
+        let batch_size = self.batch_size.unwrap_or(train_x.len());
+        match self.data_parallel {
+            Some(threads) if Matrix::is_backend_thread_safe() && network.supports_data_parallel() => {
+                let mut replicas = network.take_replicas(threads, make_replica);
+                let error = network.train_data_parallel_with_hook(
+                    &mut replicas,
+                    epoch,
+                    train_x,
+                    train_y,
+                    train_weights,
+                    &loss,
+                    batch_size,
+                    on_batch_end,
+                );
+                network.keep_replicas(replicas);
+                error
+            }
+            _ => network.train_weighted_with_hook(
+                epoch,
+                train_x,
+                train_y,
+                train_weights,
+                &loss,
+                batch_size,
+                on_batch_end,
+            ),
+        }
+    }
+
+    #[cfg(feature = "data")]
+    /// Converts the inputs table to vectors, without its id, sample weight and group columns.
+    ///
+    /// Panics if an input is missing (null or NaN), only the predicted features may be.
+    pub fn inputs_to_vectors(&self, x_table: &DataTable, id_column: &str) -> Vec<Vec<Scalar>> {
+        let mut x_table = x_table.drop_column(id_column);
+        if let Some(weight_column) = self.dataset_config.get_sample_weight_column() {
+            x_table = x_table.drop_column(weight_column);
+        }
+        if let Some(group_column) = self.dataset_config.get_group_column() {
+            x_table = x_table.drop_column(group_column);
+        }
+        let x = x_table.to_vectors();
+        assert!(
+            x.iter().flatten().all(|v| !v.is_nan()),
+            "The inputs must not have missing values, only the predicted features may"
         );
+        x
+    }
 
-        train_loss
+    #[cfg(feature = "data")]
+    /// Returns the weight of each sample, if the dataset has a `SampleWeight` feature and/or the model has class weights.
+    ///
+    /// `x_table` must hold the sample weight column, and `y` the predicted features of the same samples.
+    pub fn samples_weights(&self, x_table: &DataTable, y: &[Vec<Scalar>]) -> Option<Vec<Scalar>> {
+        let samples_weights = self
+            .dataset_config
+            .get_sample_weight_column()
+            .map(|weight_column| x_table.column_to_vector(weight_column));
+
+        match (samples_weights, self.classes_weights(y)) {
+            (Some(samples_weights), Some(classes_weights)) => Some(
+                samples_weights
+                    .iter()
+                    .zip(classes_weights.iter())
+                    .map(|(s, c)| s * c)
+                    .collect(),
+            ),
+            (samples_weights, classes_weights) => samples_weights.or(classes_weights),
+        }
+    }
+
+    /// Returns the weight of the class of each sample if the model has class weights.
+    ///
+    /// The samples with a missing target have no known class and get a weight of 1.
+    ///
+    /// Panics if a class has no weight.
+    pub fn classes_weights(&self, y: &[Vec<Scalar>]) -> Option<Vec<Scalar>> {
+        let class_weights = self.class_weights.as_ref()?;
+        let weights = y
+            .iter()
+            .map(|sample| {
+                if sample.iter().any(|v| v.is_nan()) {
+                    return 1.;
+                }
+                let class = class_of(sample);
+                *class_weights.get(class).unwrap_or_else(|| {
+                    panic!(
+                        "The class {} has no weight, the model has {} class weights",
+                        class,
+                        class_weights.len()
+                    )
+                })
+            })
+            .collect();
+        Some(weights)
     }
 
     #[cfg(not(feature = "data"))]
@@ -198,22 +519,72 @@ impl Model {
         train_x: &Vec<Vec<Scalar>>,
         train_y: &Vec<Vec<Scalar>>,
     ) -> Scalar {
-        let train_loss = network.train(
+        self.train_epoch_with_hook(epoch, network, train_x, train_y, &mut |_, _, _| false)
+    }
+
+    #[cfg(not(feature = "data"))]
+    /// Same as `train_epoch` but calls `on_batch_end` after each mini-batch (see `Network::train_weighted_with_hook`).
+    pub fn train_epoch_with_hook(
+        &self,
+        epoch: usize,
+        network: &mut Network,
+        train_x: &Vec<Vec<Scalar>>,
+        train_y: &Vec<Vec<Scalar>>,
+        on_batch_end: &mut dyn FnMut(usize, Scalar, &Network) -> bool,
+    ) -> Scalar {
+        let train_weights = self.classes_weights(train_y);
+
+        self.train_network(
+            network,
+            || self.to_network(train_x[0].len()),
             epoch,
-            &train_x,
-            &train_y,
-            &self.loss.to_loss(),
-            self.batch_size.unwrap_or(train_x.len()),
-        );
+            train_x,
+            train_y,
+            train_weights.as_deref(),
+            on_batch_end,
+        )
+    }
 
-        train_loss
+    #[cfg(feature = "data")]
+    /// Names of the columns of the predictions: the predicted features,
+    /// or `<name>_mean` and `<name>_std` for each of them with a Gaussian NLL loss.
+    pub fn preds_columns_names(&self) -> Vec<String> {
+        let names = self.dataset_config.predicted_features_names();
+        match self.loss {
+            Losses::GaussianNLL => names
+                .iter()
+                .flat_map(|name| vec![format!("{}_mean", name), format!("{}_std", name)])
+                .collect(),
+            _ => names.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    /// Converts the outputs of the network to predictions, turning the variances
+    /// predicted with a Gaussian NLL loss into standard deviations.
+    pub fn outputs_to_preds(&self, outputs: &[Vec<Scalar>]) -> Vec<Vec<Scalar>> {
+        match self.loss {
+            Losses::GaussianNLL => outputs
+                .iter()
+                .map(|sample| {
+                    sample
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| if i % 2 == 0 { *v } else { v.sqrt() })
+                        .collect()
+                })
+                .collect(),
+            _ => outputs.to_vec(),
+        }
     }
 
     #[cfg(feature = "data")]
     /// Uses the model's dataset configuration to label the prediction's columns and convert it all to a `DataTable` spreadsheet.
+    ///
+    /// With a Gaussian NLL loss, the columns are `pred_<name>_mean` and `pred_<name>_std`.
     pub fn preds_to_table(&self, preds: Vec<Vec<Scalar>>) -> DataTable {
         let mut table = DataTable::new_empty();
-        let names = self.dataset_config.predicted_features_names();
+        let names = self.preds_columns_names();
+        let preds = self.outputs_to_preds(&preds);
         let mut preds_columns: Vec<Vec<Scalar>> = Vec::new();
 
         // inverse the transpose
@@ -225,7 +596,7 @@ impl Model {
             preds_columns.push(column);
         }
 
-        for (i, pred) in preds.iter().enumerate() {
+        for (i, pred) in preds_columns.iter().enumerate() {
             table = table.append_column(Series::new(&format!("pred_{}", names[i]), pred.clone()));
         }
         table
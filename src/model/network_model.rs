@@ -1,6 +1,6 @@
 use serde::{Serialize, Deserialize};
 
-use crate::network::{Network, NetworkLayer};
+use crate::{learning_rate::LearningRateSchedule, network::{Network, NetworkLayer}};
 
 use super::{ModelBuilder, conv_network_model::{ConvNetworkModelBuilder, ConvNetworkModel}, full_dense_layer_model::{FullDenseLayerModel, FullDenseLayerModelBuilder}};
 
@@ -40,6 +40,14 @@ impl NetworkModelBuilder {
         self
     }
 
+    /// Adds an output layer predicting the mean and the variance of each of the `targets`,
+    /// the variances being kept positive through a softplus.
+    ///
+    /// To be trained with `Losses::GaussianNLL`.
+    pub fn gaussian_output(self, targets: usize) -> Self {
+        self.full_dense(targets * 2).mean_variance().end()
+    }
+
     pub fn end(self) -> ModelBuilder {
         match self.parent {
             Some(parent) => parent.accept_neural_network(self.model),
@@ -67,6 +75,13 @@ impl NetworkModel {
         }
         Network::new(layers)
     }
+
+    /// Overrides the learning rate schedule of every optimizer in the network.
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        for layer in self.layers.iter_mut() {
+            layer.set_learning_rate(learning_rate.clone());
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -82,4 +97,11 @@ impl NetworkLayerModels {
             Self::FullDense(layer) => layer.to_layer(in_dims)
         }
     }
+
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        match self {
+            Self::Convolution(network) => network.set_learning_rate(learning_rate),
+            Self::FullDense(layer) => layer.set_learning_rate(learning_rate)
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,204 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::PathBuf,
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use rand::{seq::index::sample, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    datatable::DataTable,
+    linalg::Scalar,
+    network::{
+        params::{NetworkOptimizersState, NetworkParams},
+        Network,
+    },
+    random::{set_seed, with_rng},
+};
+
+use super::Model;
+
+/// Keeps a uniform random sample of the rows seen so far (reservoir sampling),
+/// replayed along the new rows of each `OnlineModel` update to limit the forgetting of the older ones.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayBuffer {
+    pub capacity: usize,
+    /// Number of replayed rows per new row (1 by default)
+    pub ratio: Scalar,
+    // Names of the columns, in the order of the dataframes
+    columns: Vec<String>,
+    rows: Vec<Vec<Scalar>>,
+    seen: usize,
+}
+
+impl ReplayBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ratio: 1.0,
+            columns: vec![],
+            rows: vec![],
+            seen: 0,
+        }
+    }
+
+    pub fn ratio(self, ratio: Scalar) -> Self {
+        Self { ratio, ..self }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Adds the rows to the sample, every row seen so far having the same probability to be kept.
+    pub fn add(&mut self, rows: &DataTable) {
+        if self.columns.is_empty() {
+            self.columns = rows.get_columns_names().iter().map(|c| c.to_string()).collect();
+        }
+        for row in self.to_vectors(rows) {
+            if self.rows.len() < self.capacity {
+                self.rows.push(row);
+            } else {
+                let i = with_rng(|rng| rng.gen_range(0..=self.seen));
+                if i < self.capacity {
+                    self.rows[i] = row;
+                }
+            }
+            self.seen += 1;
+        }
+    }
+
+    /// Appends a random sample of the replayed rows to the new rows.
+    ///
+    /// The columns are converted to scalars, as the replayed ones are.
+    pub fn mix(&self, rows: &DataTable) -> DataTable {
+        if self.is_empty() {
+            return rows.clone();
+        }
+        let n = ((self.ratio * rows.num_rows() as Scalar).round() as usize).min(self.len());
+        let replayed = with_rng(|rng| sample(rng, self.len(), n));
+
+        let mut vectors = self.to_vectors(rows);
+        vectors.extend(replayed.iter().map(|i| self.rows[i].clone()));
+        // The vectors' values are in the reverse order of the dataframes' columns (see `DataTable::to_vectors`)
+        let names = self.columns.iter().rev().collect::<Vec<_>>();
+        DataTable::from_vectors(&names, &vectors)
+    }
+
+    fn to_vectors(&self, rows: &DataTable) -> Vec<Vec<Scalar>> {
+        rows.select_columns(&self.columns).to_vectors_with_missing()
+    }
+}
+
+/// A model updated with new rows as they come (online learning) instead of being retrained from scratch.
+///
+/// Along the network's parameters, it keeps the state of its optimizers and the number of epochs trained so far,
+/// so that each update goes on where the previous one stopped, even after saving and loading it.
+///
+/// The new rows must be preprocessed like the first training data, with the same fitted pipeline
+/// (see `Pipeline::apply`).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OnlineModel {
+    pub model: Model,
+    pub params: NetworkParams,
+    #[serde(default)]
+    pub optimizers_state: NetworkOptimizersState,
+    /// Number of epochs trained so far
+    #[serde(default)]
+    pub epochs: usize,
+    #[serde(default)]
+    pub replay: Option<ReplayBuffer>,
+}
+
+impl OnlineModel {
+    /// Starts from a new network, the first update being a regular training.
+    pub fn new(model: Model) -> Self {
+        if let Some(seed) = model.seed {
+            set_seed(seed);
+        }
+        let network = model.to_network();
+        Self::from_network(model, &network, 0)
+    }
+
+    /// Starts from a network already trained for `epochs` epochs, keeping its optimizers' state.
+    pub fn from_network(model: Model, network: &Network, epochs: usize) -> Self {
+        Self {
+            model,
+            params: network.get_params(),
+            optimizers_state: network.get_optimizers_state(),
+            epochs,
+            replay: None,
+        }
+    }
+
+    /// Replays older rows along the new ones of each update.
+    pub fn replay_buffer(self, replay: ReplayBuffer) -> Self {
+        Self {
+            replay: Some(replay),
+            ..self
+        }
+    }
+
+    /// Instantiates the network with its parameters and its optimizers' state.
+    pub fn to_network(&self) -> Network {
+        let mut network = self.model.to_network();
+        network.load_params(&self.params);
+        network.load_optimizers_state(&self.optimizers_state);
+        network
+    }
+
+    /// Trains the network on the new rows (see `Model::partial_fit`), mixed with replayed older rows if any.
+    ///
+    /// Returns the training loss of the last epoch.
+    pub fn partial_fit(&mut self, rows: &DataTable) -> Scalar {
+        let mut network = self.to_network();
+        let train_data = match &self.replay {
+            Some(replay) => replay.mix(rows),
+            None => rows.clone(),
+        };
+
+        let loss = self.model.partial_fit(&mut network, &train_data, self.epochs);
+
+        self.epochs += self.model.epochs;
+        if let Some(replay) = self.replay.as_mut() {
+            replay.add(rows);
+        }
+        self.params = network.get_params();
+        self.optimizers_state = network.get_optimizers_state();
+        loss
+    }
+
+    pub fn to_json<P: Into<PathBuf>>(&self, path: P) {
+        let json = serde_json::to_value(self).unwrap();
+        let mut file = File::create(path.into()).unwrap();
+        file.write_all(json.to_string().as_bytes()).unwrap();
+    }
+
+    pub fn from_json<P: Into<PathBuf>>(path: P) -> Self {
+        let file = File::open(path.into()).unwrap();
+        serde_json::from_reader(file).unwrap()
+    }
+
+    pub fn to_binary_compressed<P: Into<PathBuf>>(&self, path: P) {
+        let result = bincode::serialize(self).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(result.as_slice()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut file = File::create(path.into()).unwrap();
+        file.write_all(&compressed).unwrap();
+    }
+
+    pub fn from_binary_compressed<P: Into<PathBuf>>(path: P) -> Self {
+        let file = File::open(path.into()).unwrap();
+        let mut decoder = GzDecoder::new(file);
+        let mut buffer = Vec::new();
+        decoder.read_to_end(&mut buffer).unwrap();
+        bincode::deserialize(buffer.as_slice()).unwrap()
+    }
+}
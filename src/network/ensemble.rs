@@ -0,0 +1,245 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::PathBuf,
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    linalg::Scalar,
+    loss::Losses,
+    model::network_model::NetworkModel,
+    vec_utils::class_of,
+};
+
+use super::{params::NetworkParams, Network};
+
+/// How the predictions of the members of an `Ensemble` are combined.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnsembleMethod {
+    /// Weighted average of the members' outputs.
+    Average,
+    /// Weighted proportion of the members predicting each class (see `vec_utils::class_of`),
+    /// or predicting the class 1 if there is only one output.
+    Vote,
+    /// Outputs of a meta network taking the concatenated members' outputs as inputs (see `Ensemble::fit_stacking`).
+    Stacking,
+}
+
+/// Meta network of a stacking ensemble.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MetaNetwork {
+    pub network: NetworkModel,
+    pub params: NetworkParams,
+}
+
+/// Ensemble of networks sharing the same architecture, such as the networks of each fold of a `KFolds` training
+/// (see `KFolds::compute_ensemble`).
+///
+/// Unlike averaging their parameters, combining the predictions of independently trained networks
+/// makes sense whatever their parameters.
+///
+/// The ensemble is saved and loaded as a single file holding the architecture, the members' parameters,
+/// their weights and the meta network if any.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Ensemble {
+    pub network: NetworkModel,
+    pub in_dims: usize,
+    pub members: Vec<NetworkParams>,
+    /// Weight of each member, summing to 1
+    pub weights: Vec<Scalar>,
+    pub method: EnsembleMethod,
+    #[serde(default)]
+    pub meta: Option<MetaNetwork>,
+}
+
+impl Ensemble {
+    /// Creates an ensemble averaging the outputs of the members with equal weights.
+    pub fn new(network: NetworkModel, in_dims: usize, members: Vec<NetworkParams>) -> Self {
+        assert!(!members.is_empty(), "An ensemble requires members");
+        let weights = vec![1.0 / members.len() as Scalar; members.len()];
+        Self {
+            network,
+            in_dims,
+            members,
+            weights,
+            method: EnsembleMethod::Average,
+            meta: None,
+        }
+    }
+
+    pub fn method(self, method: EnsembleMethod) -> Self {
+        Self { method, ..self }
+    }
+
+    /// Sets the weights of the members, normalized to sum to 1.
+    pub fn weights(self, weights: Vec<Scalar>) -> Self {
+        assert_eq!(weights.len(), self.members.len());
+        let sum = weights.iter().sum::<Scalar>();
+        assert!(sum > 0.0, "The ensemble's weights must not all be zero");
+        Self {
+            weights: weights.iter().map(|w| w / sum).collect(),
+            ..self
+        }
+    }
+
+    /// Weights proportional to the members' validation scores, or to their inverse if lower is better.
+    ///
+    /// Members whose score is missing (NaN) get no weight, nor do those whose higher is better score is not positive.
+    /// Lower is better scores are offset by a small epsilon, so that a perfect (zero or negative) one gets
+    /// a much larger weight than the others instead of an infinite one.
+    /// The weights are equal if no member gets a weight.
+    pub fn weights_from_scores(scores: &[Scalar], higher_is_better: bool) -> Vec<Scalar> {
+        const EPSILON: Scalar = 1e-6;
+        let weights = scores
+            .iter()
+            .map(|score| match *score {
+                score if score.is_nan() => 0.0,
+                score if higher_is_better => score.max(0.0),
+                score => 1.0 / (score.max(0.0) + EPSILON),
+            })
+            .collect::<Vec<_>>();
+        if weights.iter().all(|w| *w == 0.0) {
+            vec![1.0; scores.len()]
+        } else {
+            weights
+        }
+    }
+
+    /// Instantiates the network of each member.
+    pub fn to_networks(&self) -> Vec<Network> {
+        self.members
+            .iter()
+            .map(|params| {
+                let mut network = self.network.clone().to_network(self.in_dims);
+                network.load_params(params);
+                network
+            })
+            .collect()
+    }
+
+    /// `inputs` has shape `(n, i)` where `n` is the number of samples and `i` is the number of inputs.
+    ///
+    /// Returns the combined predictions which have shape `(n, j)` where `j` is the number of outputs,
+    /// or the number of outputs of the meta network with stacking.
+    pub fn predict_many(&self, inputs: &Vec<Vec<Scalar>>, batch_size: usize) -> Vec<Vec<Scalar>> {
+        let members_preds = self.members_predictions(inputs, batch_size);
+
+        match self.method {
+            EnsembleMethod::Average => (0..inputs.len())
+                .map(|s| {
+                    let mut pred = vec![0.0; members_preds[0][s].len()];
+                    for (preds, weight) in members_preds.iter().zip(self.weights.iter()) {
+                        for (p, member_p) in pred.iter_mut().zip(preds[s].iter()) {
+                            *p += weight * member_p;
+                        }
+                    }
+                    pred
+                })
+                .collect(),
+            EnsembleMethod::Vote => (0..inputs.len())
+                .map(|s| {
+                    let outputs = members_preds[0][s].len();
+                    let mut votes = vec![0.0; outputs];
+                    for (preds, weight) in members_preds.iter().zip(self.weights.iter()) {
+                        let class = class_of(&preds[s]);
+                        if outputs == 1 {
+                            votes[0] += weight * class.min(1) as Scalar;
+                        } else {
+                            votes[class] += weight;
+                        }
+                    }
+                    votes
+                })
+                .collect(),
+            EnsembleMethod::Stacking => {
+                let meta = self
+                    .meta
+                    .as_ref()
+                    .expect("A stacking ensemble must be fitted with `fit_stacking` before predicting");
+                let mut network = meta.network.clone().to_network(self.meta_in_dims(&members_preds));
+                network.load_params(&meta.params);
+                network.predict_many(&stack(&members_preds), batch_size)
+            }
+        }
+    }
+
+    /// Trains a meta network predicting `y` from the concatenated members' outputs on `x`,
+    /// and combines the members' predictions with it from now on.
+    ///
+    /// `x` should not have been used to train the members (a holdout set for instance),
+    /// otherwise the meta network learns to trust their overfitted predictions.
+    pub fn fit_stacking(
+        &mut self,
+        meta_network: NetworkModel,
+        x: &Vec<Vec<Scalar>>,
+        y: &Vec<Vec<Scalar>>,
+        loss: Losses,
+        epochs: usize,
+        batch_size: usize,
+    ) {
+        let members_preds = self.members_predictions(x, batch_size);
+        let meta_x = stack(&members_preds);
+
+        let mut network = meta_network.clone().to_network(self.meta_in_dims(&members_preds));
+        let loss = loss.to_loss();
+        for epoch in 0..epochs {
+            network.train(epoch, &meta_x, y, &loss, batch_size);
+        }
+
+        self.meta = Some(MetaNetwork {
+            network: meta_network,
+            params: network.get_params(),
+        });
+        self.method = EnsembleMethod::Stacking;
+    }
+
+    // predictions[m][s] is the output of the m-th member for the s-th sample
+    fn members_predictions(&self, inputs: &Vec<Vec<Scalar>>, batch_size: usize) -> Vec<Vec<Vec<Scalar>>> {
+        self.to_networks()
+            .iter_mut()
+            .map(|network| network.predict_many(inputs, batch_size))
+            .collect()
+    }
+
+    fn meta_in_dims(&self, members_preds: &[Vec<Vec<Scalar>>]) -> usize {
+        members_preds.iter().map(|preds| preds.first().map_or(0, |p| p.len())).sum()
+    }
+
+    pub fn to_json<P: Into<PathBuf>>(&self, path: P) {
+        let json = serde_json::to_value(self).unwrap();
+        let mut file = File::create(path.into()).unwrap();
+        file.write_all(json.to_string().as_bytes()).unwrap();
+    }
+
+    pub fn from_json<P: Into<PathBuf>>(path: P) -> Self {
+        let file = File::open(path.into()).unwrap();
+        serde_json::from_reader(file).unwrap()
+    }
+
+    pub fn to_binary_compressed<P: Into<PathBuf>>(&self, path: P) {
+        let result = bincode::serialize(self).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(result.as_slice()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut file = File::create(path.into()).unwrap();
+        file.write_all(&compressed).unwrap();
+    }
+
+    pub fn from_binary_compressed<P: Into<PathBuf>>(path: P) -> Self {
+        let file = File::open(path.into()).unwrap();
+        let mut decoder = GzDecoder::new(file);
+        let mut buffer = Vec::new();
+        decoder.read_to_end(&mut buffer).unwrap();
+        bincode::deserialize(buffer.as_slice()).unwrap()
+    }
+}
+
+// Concatenates the outputs of the members for each sample
+fn stack(members_preds: &[Vec<Vec<Scalar>>]) -> Vec<Vec<Scalar>> {
+    (0..members_preds[0].len())
+        .map(|s| members_preds.iter().flat_map(|preds| preds[s].iter().copied()).collect())
+        .collect()
+}
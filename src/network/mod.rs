@@ -1,25 +1,82 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, ops::Range, thread};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
     layer::{Layer, ParameterableLayer},
     linalg::{Matrix, MatrixTrait, Scalar},
+    loader::DataLoader,
     loss::Loss, monitor::TM,
+    random::{derive_seed, gen_seed, set_seed},
 };
 
-use self::params::NetworkParams;
+use self::params::{NetworkOptimizersState, NetworkParams};
 
+pub mod ensemble;
 pub mod params;
+pub mod params_averaging;
+
+/// Inputs, targets and optional weights of the samples of a mini-batch.
+pub type WeightedBatch = (Vec<Vec<Scalar>>, Vec<Vec<Scalar>>, Option<Vec<Scalar>>);
+
+/// Gradients of each layer of a network, `None` for the layers without parameters (see `Network::compute_gradients`).
+pub type NetworkGradients = Vec<Option<Vec<Vec<Scalar>>>>;
+
+/// Derivative of the loss of a mini-batch with respect to the network's predictions, one column per sample
+/// (see `Network::output_gradient`).
+///
+/// With `through_softmax`, it is the derivative with respect to the input of the final softmax activation
+/// (see `LossFunction::softmax_loss_prime`).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OutputGradient {
+    pub columns: Vec<Vec<Scalar>>,
+    pub through_softmax: bool,
+}
+
+impl OutputGradient {
+    /// The derivative for the samples in `range`, to be back-propagated by the network which predicted them.
+    pub fn samples(&self, range: Range<usize>) -> Self {
+        Self {
+            columns: self.columns[range].to_vec(),
+            through_softmax: self.through_softmax,
+        }
+    }
+}
+
+/// Sums the gradients computed by networks of the same architecture.
+pub fn sum_gradients<I: IntoIterator<Item = NetworkGradients>>(gradients: I) -> NetworkGradients {
+    let mut gradients = gradients.into_iter();
+    let mut sum = gradients.next().unwrap_or_default();
+    for other in gradients {
+        for (sum, other) in sum.iter_mut().zip(other) {
+            match (sum.as_mut(), other) {
+                (Some(sum), Some(other)) => {
+                    for (sum, other) in sum.iter_mut().zip(other.iter()) {
+                        for (a, b) in sum.iter_mut().zip(other.iter()) {
+                            *a += b;
+                        }
+                    }
+                }
+                (None, other) => *sum = other,
+                (Some(_), None) => {}
+            }
+        }
+    }
+    sum
+}
 
 #[derive(Debug)]
 pub struct Network {
     // May be one or more layers inside
     // A layer is a layer as long as it implements the Layer trait
     layers: Vec<Box<dyn NetworkLayer>>,
+    // Replicas of the data-parallel training, kept from one epoch to the next (see `take_replicas`)
+    replicas: Vec<Network>,
 }
 
 impl Network {
     pub fn new(layers: Vec<Box<dyn NetworkLayer>>) -> Self {
-        Self { layers }
+        Self { layers, replicas: vec![] }
     }
 
     pub fn get_params(&self) -> NetworkParams {
@@ -40,6 +97,73 @@ impl Network {
         }
     }
 
+    /// State of the optimizers of each learnable layer (moments...), saved along the parameters
+    /// to resume the training later on as if it never stopped (see `OnlineModel`).
+    pub fn get_optimizers_state(&self) -> NetworkOptimizersState {
+        NetworkOptimizersState(
+            self.layers
+                .iter()
+                .filter_map(|l| l.as_learnable_layer())
+                .map(|l| l.get_optimizers_state())
+                .collect(),
+        )
+    }
+
+    pub fn load_optimizers_state(&mut self, state: &NetworkOptimizersState) {
+        let layers = self.layers.iter_mut().filter_map(|l| l.as_learnable_layer_mut());
+        for (layer, state) in layers.zip(state.0.iter()) {
+            layer.set_optimizers_state(state);
+        }
+    }
+
+    /// Returns the number of top-level layers, which are the ones addressed by index in the methods below.
+    pub fn layers_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Stops updating the parameters of the `index`-th layer during training.
+    ///
+    /// The layer still propagates the error gradient to the previous layers.
+    pub fn freeze_layer(&mut self, index: usize) {
+        if let Some(l) = self.layers[index].as_trainable_layer_mut() {
+            l.set_trainable(false);
+        }
+    }
+
+    /// Resumes updating the parameters of the `index`-th layer during training.
+    pub fn unfreeze_layer(&mut self, index: usize) {
+        if let Some(l) = self.layers[index].as_trainable_layer_mut() {
+            l.set_trainable(true);
+        }
+    }
+
+    /// Freezes all the layers before the `index`-th one and unfreezes the others.
+    ///
+    /// Typically used to only train the head of a pre-trained network.
+    pub fn freeze_layers_until(&mut self, index: usize) {
+        for i in 0..self.layers.len() {
+            if i < index {
+                self.freeze_layer(i);
+            } else {
+                self.unfreeze_layer(i);
+            }
+        }
+    }
+
+    pub fn is_layer_trainable(&self, index: usize) -> bool {
+        self.layers[index]
+            .as_trainable_layer()
+            .map(|l| l.is_trainable())
+            .unwrap_or(false)
+    }
+
+    /// Scales the learning rate of all the optimizers of the `index`-th layer by `multiplier`.
+    pub fn set_layer_learning_rate_multiplier(&mut self, index: usize, multiplier: Scalar) {
+        if let Some(l) = self.layers[index].as_trainable_layer_mut() {
+            l.set_learning_rate_multiplier(multiplier);
+        }
+    }
+
     /// `input` has shape `(i,)` where `i` is the number of inputs.
     pub fn predict(&mut self, input: &Vec<Scalar>) -> Vec<Scalar> {
         self.layers.iter_mut().for_each(|l| {
@@ -114,6 +238,18 @@ impl Network {
         ys: &Vec<Vec<Scalar>>,
         loss: &Loss,
         batch_size: usize
+    ) -> (Vec<Vec<Scalar>>, Scalar, Scalar) {
+        self.predict_evaluate_many_weighted(inputs, ys, None, loss, batch_size)
+    }
+
+    /// Same as `predict_evaluate_many` but the loss of each sample is weighted by `weights` which has shape `(n,)`.
+    pub fn predict_evaluate_many_weighted(
+        &mut self,
+        inputs: &Vec<Vec<Scalar>>,
+        ys: &Vec<Vec<Scalar>>,
+        weights: Option<&[Scalar]>,
+        loss: &Loss,
+        batch_size: usize
     ) -> (Vec<Vec<Scalar>>, Scalar, Scalar) {
         TM::start("predevmany");
         TM::start("init");
@@ -126,6 +262,7 @@ impl Network {
         let mut i = 0;
         let x_batches: Vec<_> = inputs.chunks(batch_size).map(|c| c.to_vec()).collect();
         let y_batches: Vec<_> = ys.chunks(batch_size).map(|c| c.to_vec()).collect();
+        let weights_batches: Option<Vec<_>> = weights.map(|w| w.chunks(batch_size).collect());
         let n_batches = x_batches.len();
         TM::end();
         
@@ -137,7 +274,12 @@ impl Network {
             let input_batch_matrix = Matrix::from_column_leading_vector2(&input_batch);
             let pred = self.layers.forward(input_batch_matrix);
             let y_true_batch_matrix = Matrix::from_column_leading_vector2(&y_true_batch);
-            let e = loss.loss(&y_true_batch_matrix, &pred);
+            let e = match &weights_batches {
+                Some(weights_batches) => {
+                    loss.weighted_loss(&y_true_batch_matrix, &pred, weights_batches[i])
+                }
+                None => loss.loss(&y_true_batch_matrix, &pred),
+            };
 
             losses.push(e);
             preds.extend(pred.get_data_col_leading());
@@ -170,6 +312,36 @@ impl Network {
         y_train: &Vec<Vec<Scalar>>,
         loss: &Loss,
         batch_size: usize,
+    ) -> Scalar {
+        self.train_weighted(epoch, x_train, y_train, None, loss, batch_size)
+    }
+
+    /// Same as `train` but the loss of each sample is weighted by `weights` which has shape `(n,)`.
+    pub fn train_weighted(
+        &mut self,
+        epoch: usize,
+        x_train: &Vec<Vec<Scalar>>,
+        y_train: &Vec<Vec<Scalar>>,
+        weights: Option<&[Scalar]>,
+        loss: &Loss,
+        batch_size: usize,
+    ) -> Scalar {
+        self.train_weighted_with_hook(epoch, x_train, y_train, weights, loss, batch_size, &mut |_, _, _| false)
+    }
+
+    /// Same as `train_weighted` but calls `on_batch_end` with the index and the loss of each mini-batch.
+    ///
+    /// The remaining mini-batches are skipped if `on_batch_end` returns `true`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn train_weighted_with_hook(
+        &mut self,
+        epoch: usize,
+        x_train: &Vec<Vec<Scalar>>,
+        y_train: &Vec<Vec<Scalar>>,
+        weights: Option<&[Scalar]>,
+        loss: &Loss,
+        batch_size: usize,
+        on_batch_end: &mut dyn FnMut(usize, Scalar, &Network) -> bool,
     ) -> Scalar {
         TM::start("train");
         TM::start("init");
@@ -181,7 +353,10 @@ impl Network {
         let mut i = 0;
         let x_train_batches: Vec<_> = x_train.chunks(batch_size).map(|c| c.to_vec()).collect();
         let y_train_batches: Vec<_> = y_train.chunks(batch_size).map(|c| c.to_vec()).collect();
+        let weights_batches: Option<Vec<_>> = weights.map(|w| w.chunks(batch_size).collect());
         let n_batches = x_train_batches.len();
+        // The softmax-loss gradient is fused when possible, which is both faster and more stable
+        let ends_with_softmax = self.layers.last().is_some_and(|l| l.ends_with_softmax());
         TM::end();
         
         TM::start("batches");
@@ -189,25 +364,397 @@ impl Network {
             x_train_batches.into_iter().zip(y_train_batches.into_iter())
         {
             TM::start(format!("{}/{}", i, n_batches));
-            let input_batch_matrix = Matrix::from_column_leading_vector2(&input_batch);
+            let batch_weights = weights_batches.as_ref().map(|w| w[i]);
+            let e = self.train_batch(
+                epoch,
+                Matrix::from_column_leading_vector2(&input_batch),
+                &Matrix::from_column_leading_vector2(&y_true_batch),
+                batch_weights,
+                loss,
+                ends_with_softmax,
+            );
 
-            let pred = self.layers.forward(input_batch_matrix);
-            
-            let y_true_batch_matrix = Matrix::from_column_leading_vector2(&y_true_batch);
-            let e = loss.loss(&y_true_batch_matrix, &pred);
+            error += e;
+
+            let stop = on_batch_end(i, e, self);
+            i += 1;
+            TM::end_with_message(format!("error: {:.4} total_error: {:.4}", e, error));
+            if stop {
+                break;
+            }
+        }
+        // No mini-batch (empty data) leaves the network unchanged, with a loss of 0
+        if i > 0 {
+            error /= i as Scalar;
+        }
+        TM::end();
+        TM::end_with_message(format!("avg_error: {:.4}", error));
+        error
+    }
+
+    /// Same as `train_weighted_with_hook` but the mini-batches come from an iterator,
+    /// so that the samples of the epoch don't need to be in memory all at once (see `streaming::DataStream`).
+    ///
+    /// Without any mini-batch, the network is left unchanged and the loss is 0.
+    pub fn train_batches_with_hook<I: Iterator<Item = WeightedBatch>>(
+        &mut self,
+        epoch: usize,
+        batches: I,
+        loss: &Loss,
+        on_batch_end: &mut dyn FnMut(usize, Scalar, &Network) -> bool,
+    ) -> Scalar {
+        let batches = batches.map(|(x, y, weights)| {
+            (
+                Matrix::from_column_leading_vector2(&x),
+                Matrix::from_column_leading_vector2(&y),
+                weights,
+            )
+        });
+        self.train_matrices_with_hook(epoch, batches, loss, on_batch_end)
+    }
+
+    /// Same as `train` but the mini-batches come from a `DataLoader`, which shuffles and prefetches them as configured.
+    pub fn train_loader(&mut self, epoch: usize, loader: &mut dyn DataLoader, loss: &Loss) -> Scalar {
+        self.train_loader_with_hook(epoch, loader, loss, &mut |_, _, _| false)
+    }
+
+    /// Same as `train_loader` but calls `on_batch_end` with the index and the loss of each mini-batch.
+    ///
+    /// The remaining mini-batches are skipped if `on_batch_end` returns `true`.
+    pub fn train_loader_with_hook(
+        &mut self,
+        epoch: usize,
+        loader: &mut dyn DataLoader,
+        loss: &Loss,
+        on_batch_end: &mut dyn FnMut(usize, Scalar, &Network) -> bool,
+    ) -> Scalar {
+        let batches = loader.batches().map(|(x, y)| (x, y, None));
+        self.train_matrices_with_hook(epoch, batches, loss, on_batch_end)
+    }
+
+    fn train_matrices_with_hook<I: Iterator<Item = (Matrix, Matrix, Option<Vec<Scalar>>)>>(
+        &mut self,
+        epoch: usize,
+        batches: I,
+        loss: &Loss,
+        on_batch_end: &mut dyn FnMut(usize, Scalar, &Network) -> bool,
+    ) -> Scalar {
+        TM::start("train");
+        for layer in self.layers.iter_mut() {
+            if let Some(layer) = layer.as_dropout_layer() {
+                layer.enable_dropout();
+            }
+        }
+
+        let mut error = 0.;
+        let mut i = 0;
+        let ends_with_softmax = self.layers.last().is_some_and(|l| l.ends_with_softmax());
+
+        TM::start("batches");
+        for (input_batch, y_true_batch, batch_weights) in batches {
+            TM::start(format!("{}", i));
+            let e = self.train_batch(epoch, input_batch, &y_true_batch, batch_weights.as_deref(), loss, ends_with_softmax);
 
             error += e;
 
-            let error_gradient = loss.loss_prime(&y_true_batch_matrix, &pred);
-            self.layers.backward(epoch, error_gradient.clone());
+            let stop = on_batch_end(i, e, self);
             i += 1;
             TM::end_with_message(format!("error: {:.4} total_error: {:.4}", e, error));
+            if stop {
+                break;
+            }
+        }
+        // No mini-batch (empty data) leaves the network unchanged, with a loss of 0
+        if i > 0 {
+            error /= i as Scalar;
         }
-        error /= i as Scalar;
         TM::end();
         TM::end_with_message(format!("avg_error: {:.4}", error));
         error
     }
+
+    /// Takes `count` replicas of the network for `train_data_parallel_with_hook`.
+    ///
+    /// The replicas given back with `keep_replicas` are reused, the missing ones are created with `make_replica`.
+    pub fn take_replicas<F: FnMut() -> Network>(&mut self, count: usize, make_replica: F) -> Vec<Network> {
+        let mut replicas = std::mem::take(&mut self.replicas);
+        replicas.resize_with(count, make_replica);
+        replicas
+    }
+
+    /// Keeps the replicas taken with `take_replicas` until the next epoch.
+    pub fn keep_replicas(&mut self, replicas: Vec<Network>) {
+        self.replicas = replicas;
+    }
+
+    /// Whether every learnable layer supports the data-parallel training (see `GradientLayer`).
+    pub fn supports_data_parallel(&mut self) -> bool {
+        self.layers
+            .iter_mut()
+            .all(|l| l.as_learnable_layer().is_none() || l.as_gradient_layer_mut().is_some())
+    }
+
+    /// Same as `train_weighted_with_hook` but each mini-batch is split between the `replicas` of the network,
+    /// each one computing the gradients of its share of the samples on its own thread.
+    ///
+    /// Before each mini-batch, the replicas are loaded with the network's parameters.
+    /// The loss is derived on the predictions of the whole mini-batch, so that the samples' weights
+    /// and the missing targets are normalized as with a single thread. The gradients of the replicas
+    /// are then summed, like the gradients of the samples of a mini-batch are,
+    /// and applied by a single step of the network's optimizers.
+    ///
+    /// The replicas must have the same architecture as the network (see `Model::data_parallel`),
+    /// and the backend must be thread safe (see `MatrixTrait::is_backend_thread_safe`).
+    #[allow(clippy::too_many_arguments)]
+    pub fn train_data_parallel_with_hook(
+        &mut self,
+        replicas: &mut [Network],
+        epoch: usize,
+        x_train: &[Vec<Scalar>],
+        y_train: &[Vec<Scalar>],
+        weights: Option<&[Scalar]>,
+        loss: &Loss,
+        batch_size: usize,
+        on_batch_end: &mut dyn FnMut(usize, Scalar, &Network) -> bool,
+    ) -> Scalar {
+        assert!(Matrix::is_backend_thread_safe(), "The data-parallel training requires a thread safe backend");
+        assert!(!replicas.is_empty(), "The data-parallel training requires replicas");
+        assert!(self.supports_data_parallel(), "Only full dense networks support the data-parallel training");
+
+        TM::start("train");
+        let mut error = 0.;
+        let mut i = 0;
+        let n_batches = x_train.len().div_ceil(batch_size);
+
+        TM::start("batches");
+        for (input_batch, y_true_batch) in x_train.chunks(batch_size).zip(y_train.chunks(batch_size)) {
+            TM::start(format!("{}/{}", i, n_batches));
+            let params = self.get_params();
+            let batch_weights = weights.map(|w| &w[i * batch_size..i * batch_size + input_batch.len()]);
+            let shard_size = input_batch.len().div_ceil(replicas.len());
+            // Each thread has its own random generator, seeded from the current one for reproducibility
+            let batch_seed = gen_seed();
+
+            let preds = thread::scope(|scope| {
+                let handles = replicas
+                    .iter_mut()
+                    .zip(input_batch.chunks(shard_size))
+                    .enumerate()
+                    .map(|(s, (replica, x_shard))| {
+                        let params = &params;
+                        scope.spawn(move || {
+                            set_seed(derive_seed(batch_seed, s as u64));
+                            replica.load_params(params);
+                            replica.forward_gradients(x_shard)
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+            });
+
+            let (e, output_gradient) = self.output_gradient(y_true_batch, &preds.concat(), batch_weights, loss);
+            error += e;
+
+            let shards_gradients = thread::scope(|scope| {
+                let handles = replicas
+                    .iter_mut()
+                    .zip(preds.iter())
+                    .enumerate()
+                    .map(|(s, (replica, shard_preds))| {
+                        let shard_gradient = output_gradient.samples(s * shard_size..s * shard_size + shard_preds.len());
+                        scope.spawn(move || replica.backward_gradients(epoch, &shard_gradient))
+                    })
+                    .collect::<Vec<_>>();
+                handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+            });
+            self.apply_gradients(epoch, &sum_gradients(shards_gradients));
+
+            let stop = on_batch_end(i, e, self);
+            i += 1;
+            TM::end_with_message(format!("error: {:.4} total_error: {:.4}", e, error));
+            if stop {
+                break;
+            }
+        }
+        // No mini-batch (empty data) leaves the network unchanged, with a loss of 0
+        if i > 0 {
+            error /= i as Scalar;
+        }
+        TM::end();
+        TM::end_with_message(format!("avg_error: {:.4}", error));
+        error
+    }
+
+    /// Forward and backward passes of a mini-batch in training mode, without updating the parameters.
+    ///
+    /// Returns the loss of the mini-batch and the gradients of each layer (see `GradientLayer`),
+    /// which are sums over the samples like the gradients applied by `train`.
+    ///
+    /// Requires every learnable layer to support it (see `supports_data_parallel`).
+    pub fn compute_gradients(
+        &mut self,
+        epoch: usize,
+        x: &[Vec<Scalar>],
+        y: &[Vec<Scalar>],
+        weights: Option<&[Scalar]>,
+        loss: &Loss,
+    ) -> (Scalar, NetworkGradients) {
+        let preds = self.forward_gradients(x);
+        let (e, output_gradient) = self.output_gradient(y, &preds, weights, loss);
+        (e, self.backward_gradients(epoch, &output_gradient))
+    }
+
+    /// Forward pass of a mini-batch in training mode, the first step of `compute_gradients`.
+    ///
+    /// Returns the predictions, which can be derived along with the ones of other replicas of the network
+    /// (see `output_gradient`) before calling `backward_gradients`.
+    pub fn forward_gradients(&mut self, x: &[Vec<Scalar>]) -> Vec<Vec<Scalar>> {
+        for layer in self.layers.iter_mut() {
+            if let Some(layer) = layer.as_dropout_layer() {
+                layer.enable_dropout();
+            }
+            if let Some(layer) = layer.as_gradient_layer_mut() {
+                layer.set_accumulate_gradients(true);
+            }
+        }
+
+        self.layers
+            .forward(Matrix::from_column_leading_vector2(&x.to_vec()))
+            .get_data_col_leading()
+    }
+
+    /// Loss of a mini-batch and its derivative with respect to the predictions, the second step of `compute_gradients`.
+    pub fn output_gradient(
+        &self,
+        y_true: &[Vec<Scalar>],
+        y_pred: &[Vec<Scalar>],
+        weights: Option<&[Scalar]>,
+        loss: &Loss,
+    ) -> (Scalar, OutputGradient) {
+        let ends_with_softmax = self.layers.last().is_some_and(|l| l.ends_with_softmax());
+        let (e, gradient, through_softmax) = loss_and_gradient(
+            &Matrix::from_column_leading_vector2(&y_true.to_vec()),
+            &Matrix::from_column_leading_vector2(&y_pred.to_vec()),
+            weights,
+            loss,
+            ends_with_softmax,
+        );
+        let output_gradient = OutputGradient {
+            columns: gradient.get_data_col_leading(),
+            through_softmax,
+        };
+        (e, output_gradient)
+    }
+
+    /// Backward pass of the predictions of the last `forward_gradients`, the last step of `compute_gradients`.
+    ///
+    /// Returns the gradients of each layer, without updating the parameters.
+    pub fn backward_gradients(&mut self, epoch: usize, output_gradient: &OutputGradient) -> NetworkGradients {
+        self.backward_output(
+            epoch,
+            Matrix::from_column_leading_vector2(&output_gradient.columns),
+            output_gradient.through_softmax,
+        );
+
+        let mut gradients = Vec::with_capacity(self.layers.len());
+        for layer in self.layers.iter_mut() {
+            match layer.as_gradient_layer_mut() {
+                Some(layer) => {
+                    gradients.push(layer.take_gradients());
+                    layer.set_accumulate_gradients(false);
+                }
+                None => gradients.push(None),
+            }
+        }
+        gradients
+    }
+
+    /// Applies the gradients computed by `compute_gradients` (or their sum, see `sum_gradients`)
+    /// with a single step of each layer's optimizer.
+    pub fn apply_gradients(&mut self, epoch: usize, gradients: &NetworkGradients) {
+        for (layer, gradients) in self.layers.iter_mut().zip(gradients.iter()) {
+            if let (Some(layer), Some(gradients)) = (layer.as_gradient_layer_mut(), gradients) {
+                layer.apply_gradients(epoch, gradients);
+            }
+        }
+    }
+
+    // Forward and backward passes of a mini-batch, returns its loss
+    fn train_batch(
+        &mut self,
+        epoch: usize,
+        input_batch_matrix: Matrix,
+        y_true_batch_matrix: &Matrix,
+        batch_weights: Option<&[Scalar]>,
+        loss: &Loss,
+        ends_with_softmax: bool,
+    ) -> Scalar {
+        let pred = self.layers.forward(input_batch_matrix);
+        let (e, error_gradient, through_softmax) =
+            loss_and_gradient(y_true_batch_matrix, &pred, batch_weights, loss, ends_with_softmax);
+        self.backward_output(epoch, error_gradient, through_softmax);
+        e
+    }
+
+    // Backward pass of the derivative of the loss, see `OutputGradient`
+    fn backward_output(&mut self, epoch: usize, error_gradient: Matrix, through_softmax: bool) {
+        if through_softmax {
+            self.backward_from_softmax_input(epoch, error_gradient);
+        } else {
+            self.layers.backward(epoch, error_gradient);
+        }
+    }
+
+    fn backward_from_softmax_input(&mut self, epoch: usize, error_gradient: Matrix) {
+        TM::start("net.back");
+        let n_layers = self.layers.len();
+        let mut error_gradient = error_gradient;
+        for (i, layer) in self.layers.iter_mut().enumerate().rev() {
+            TM::start(format!("layer[{}]", i+1));
+            error_gradient = if i == n_layers - 1 {
+                layer.backward_skipping_activation(epoch, error_gradient)
+            } else {
+                layer.backward(epoch, error_gradient)
+            };
+            TM::end();
+        }
+        TM::end();
+    }
+}
+
+// Loss of a mini-batch and its derivative, with respect to the input of the final softmax
+// if the loss has a fused derivative (the returned boolean)
+fn loss_and_gradient(
+    y_true: &Matrix,
+    y_pred: &Matrix,
+    weights: Option<&[Scalar]>,
+    loss: &Loss,
+    ends_with_softmax: bool,
+) -> (Scalar, Matrix, bool) {
+    let e = match weights {
+        Some(w) => loss.weighted_loss(y_true, y_pred, w),
+        None => loss.loss(y_true, y_pred),
+    };
+
+    let fused_gradient = if ends_with_softmax {
+        match weights {
+            Some(w) => loss.weighted_softmax_loss_prime(y_true, y_pred, w),
+            None => loss.softmax_loss_prime(y_true, y_pred),
+        }
+    } else {
+        None
+    };
+
+    match fused_gradient {
+        Some(error_gradient) => (e, error_gradient, true),
+        None => {
+            let error_gradient = match weights {
+                Some(w) => loss.weighted_loss_prime(y_true, y_pred, w),
+                None => loss.loss_prime(y_true, y_pred),
+            };
+            (e, error_gradient, false)
+        }
+    }
 }
 
 impl Layer for Vec<Box<dyn NetworkLayer>> {
@@ -237,4 +784,16 @@ impl Layer for Vec<Box<dyn NetworkLayer>> {
     }
 }
 
-pub trait NetworkLayer: Layer + ParameterableLayer + Debug + Send {}
+pub trait NetworkLayer: Layer + ParameterableLayer + Debug + Send {
+    /// Whether the output of the layer is produced by a softmax activation.
+    fn ends_with_softmax(&self) -> bool {
+        false
+    }
+
+    /// Same as `backward` but `output_gradient` is taken with respect to the input of the layer's final activation.
+    ///
+    /// Only called on layers for which `ends_with_softmax` returns true.
+    fn backward_skipping_activation(&mut self, epoch: usize, output_gradient: Matrix) -> Matrix {
+        self.backward(epoch, output_gradient)
+    }
+}
@@ -3,12 +3,20 @@ use std::{fs::File, io::{Write, Read}, path::PathBuf};
 use flate2::{write::GzEncoder, Compression, read::GzDecoder};
 use serde::{Deserialize, Serialize};
 
-use crate::linalg::{Matrix, MatrixTrait, Scalar};
+use crate::{
+    linalg::{Matrix, MatrixTrait, Scalar},
+    optimizer::OptimizerState,
+};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct NetworkParams(pub Vec<Vec<Vec<Scalar>>>);
 
+/// State of the optimizers of each learnable layer of a network (see `Network::get_optimizers_state`).
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct NetworkOptimizersState(pub Vec<Vec<OptimizerState>>);
+
 impl NetworkParams {
+    /// Averages the parameters of the networks, each one having the same weight.
     pub fn average(networks: &Vec<Self>) -> Self {
         let mut params = Vec::new();
 
@@ -19,8 +27,9 @@ impl NetworkParams {
 
             for network in networks.iter().skip(1) {
                 let other_params = Matrix::from_column_leading_vector2(&network.0[layer_index]);
-                layer_params = layer_params.component_add(&other_params).scalar_div(2.0);
+                layer_params = layer_params.component_add(&other_params);
             }
+            layer_params = layer_params.scalar_div(networks.len() as Scalar);
 
             params.push(layer_params.get_data_col_leading());
         }
@@ -28,6 +37,32 @@ impl NetworkParams {
         NetworkParams(params)
     }
 
+    /// Moves each parameter towards the corresponding one in `other` by a fraction `t` of their difference.
+    ///
+    /// `t = 0` returns `self` and `t = 1` returns `other`.
+    pub fn interpolate(&self, other: &Self, t: Scalar) -> Self {
+        let params = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(layer, other_layer)| {
+                layer
+                    .iter()
+                    .zip(other_layer.iter())
+                    .map(|(column, other_column)| {
+                        column
+                            .iter()
+                            .zip(other_column.iter())
+                            .map(|(a, b)| a + (b - a) * t)
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        NetworkParams(params)
+    }
+
     pub fn to_json<P: Into<PathBuf>>(&self, path: P) {
         let json = serde_json::to_value(self).unwrap();
         let mut file = File::create(path.into()).unwrap();
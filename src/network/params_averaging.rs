@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::linalg::Scalar;
+
+use super::params::NetworkParams;
+
+/// Ways of averaging the parameters of a network across the epochs of its training.
+///
+/// The averaged parameters usually generalize better than the final ones.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum ParamsAveraging {
+    /// Exponential moving average of the parameters, updated at the end of each epoch:
+    /// `avg = decay * avg + (1 - decay) * params`
+    ExponentialMovingAverage { decay: Scalar },
+    /// Stochastic Weight Averaging: uniform average of the parameters at the end of each of the last `epochs` epochs.
+    ///
+    /// Resources: https://arxiv.org/pdf/1803.05407.pdf
+    StochasticWeightAveraging { epochs: usize },
+}
+
+/// Keeps track of the averaged parameters of a network during its training.
+pub struct ParamsAverager {
+    pub averaging: ParamsAveraging,
+    averaged: Option<NetworkParams>,
+    count: usize,
+}
+
+impl ParamsAverager {
+    pub fn new(averaging: ParamsAveraging) -> Self {
+        Self {
+            averaging,
+            averaged: None,
+            count: 0,
+        }
+    }
+
+    /// Accumulates the parameters of the network at the end of the epoch `epoch` (starting at 0) out of `epochs`.
+    pub fn update(&mut self, epoch: usize, epochs: usize, params: NetworkParams) {
+        let t = match self.averaging {
+            ParamsAveraging::ExponentialMovingAverage { decay } => 1. - decay,
+            ParamsAveraging::StochasticWeightAveraging { epochs: last_epochs } => {
+                if epoch + last_epochs < epochs {
+                    return;
+                }
+                1. / (self.count + 1) as Scalar
+            }
+        };
+
+        self.averaged = Some(match self.averaged.take() {
+            Some(averaged) => averaged.interpolate(&params, t),
+            None => params,
+        });
+        self.count += 1;
+    }
+
+    /// Returns the averaged parameters, if at least one epoch has been accumulated.
+    pub fn get_params(&self) -> Option<&NetworkParams> {
+        self.averaged.as_ref()
+    }
+
+    pub fn take_params(&mut self) -> Option<NetworkParams> {
+        self.count = 0;
+        self.averaged.take()
+    }
+}
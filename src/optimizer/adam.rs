@@ -29,9 +29,9 @@ pub struct Adam {
     #[serde(default = "default_learning_rate")]
     learning_rate: LearningRateSchedule,
     #[serde(skip)]
-    m: Option<Matrix>, // first moment vector
+    pub(crate) m: Option<Matrix>, // first moment vector
     #[serde(skip)]
-    v: Option<Matrix>, // second moment vector
+    pub(crate) v: Option<Matrix>, // second moment vector
 }
 
 impl Adam {
@@ -62,6 +62,10 @@ impl Adam {
         }
     }
 
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        self.learning_rate = learning_rate;
+    }
+
     pub fn update_parameters(
         &mut self,
         epoch: usize,
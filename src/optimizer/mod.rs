@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-use crate::linalg::Matrix;
+use crate::{
+    learning_rate::LearningRateSchedule,
+    linalg::{Matrix, MatrixTrait, Scalar},
+};
 
 use self::{adam::Adam, momentum::Momentum, sgd::SGD};
 
@@ -8,6 +11,12 @@ pub mod adam;
 pub mod momentum;
 pub mod sgd;
 
+/// Moments of an optimizer as column-leading matrices, `None` before its first update.
+///
+/// Unlike the optimizer's hyperparameters, they are not part of the model's configuration,
+/// so they are saved separately to resume a training (see `Network::get_optimizers_state`).
+pub type OptimizerState = Vec<Option<Vec<Vec<Scalar>>>>;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Optimizers {
     SGD(SGD),
@@ -32,6 +41,40 @@ impl Optimizers {
             }
         }
     }
+
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        match self {
+            Optimizers::SGD(sgd) => sgd.set_learning_rate(learning_rate),
+            Optimizers::Momentum(momentum) => momentum.set_learning_rate(learning_rate),
+            Optimizers::Adam(adam) => adam.set_learning_rate(learning_rate),
+        }
+    }
+
+    pub fn get_state(&self) -> OptimizerState {
+        match self {
+            Optimizers::SGD(_) => vec![],
+            Optimizers::Momentum(momentum) => vec![momentum.v.as_ref().map(|v| v.get_data_col_leading())],
+            Optimizers::Adam(adam) => vec![
+                adam.m.as_ref().map(|m| m.get_data_col_leading()),
+                adam.v.as_ref().map(|v| v.get_data_col_leading()),
+            ],
+        }
+    }
+
+    /// Restores the moments returned by `get_state` on an optimizer of the same kind.
+    pub fn set_state(&mut self, state: &OptimizerState) {
+        let matrix = |i: usize| -> Option<Matrix> {
+            state.get(i)?.as_ref().map(Matrix::from_column_leading_vector2)
+        };
+        match self {
+            Optimizers::SGD(_) => {}
+            Optimizers::Momentum(momentum) => momentum.v = matrix(0),
+            Optimizers::Adam(adam) => {
+                adam.m = matrix(0);
+                adam.v = matrix(1);
+            }
+        }
+    }
 }
 
 pub fn adam() -> Optimizers {
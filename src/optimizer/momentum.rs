@@ -17,7 +17,7 @@ pub struct Momentum {
     #[serde(default = "default_learning_rate")]
     learning_rate: LearningRateSchedule,
     #[serde(skip)]
-    v: Option<Matrix>,
+    pub(crate) v: Option<Matrix>,
 }
 
 impl Momentum {
@@ -37,6 +37,10 @@ impl Momentum {
         }
     }
 
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        self.learning_rate = learning_rate;
+    }
+
     pub fn update_parameters(
         &mut self,
         epoch: usize,
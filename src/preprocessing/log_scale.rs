@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
     dataset::{Dataset, Feature},
@@ -7,7 +7,7 @@ use crate::{
     vec_utils::min_vector,
 };
 
-use super::{feature_cached::FeatureExtractorCached, DataTransformation, CachedConfig};
+use super::{feature_cached::FeatureExtractorCached, CachedConfig, DataTransformation, Reverter};
 
 pub struct LogScale10 {
     logged_features: HashMap<String, Scalar>,
@@ -19,26 +19,15 @@ impl LogScale10 {
             logged_features: HashMap::new(),
         }
     }
-}
 
-impl DataTransformation for LogScale10 {
-    fn transform(
-        &mut self,
+    // Scales the features with the fitted minimums
+    fn log_scale(
+        &self,
         cached_config: &CachedConfig,
         dataset_config: &Dataset,
         data: &DataTable,
     ) -> (Dataset, DataTable) {
-        let mut logged_features = HashMap::new();
-
-        for feature in dataset_config.features.iter() {
-            if feature.log10 {
-                let values = data.column_to_vector(&feature.name);
-                let min = min_vector(&values);
-                logged_features.insert(feature.name.clone(), min);
-            }
-        }
-
-        self.logged_features = logged_features.clone();
+        let logged_features = self.logged_features.clone();
 
         let mut extractor = FeatureExtractorCached::new(
             Box::new(move |feature: &Feature| match &feature.with_log10 {
@@ -69,23 +58,78 @@ impl DataTransformation for LogScale10 {
 
         extractor.transform(cached_config, dataset_config, data)
     }
+}
 
-    fn reverse_columnswise(&mut self, data: &DataTable) -> DataTable {
-        let mut reversed_data = data.clone();
-
-        for (feature, min) in self.logged_features.iter() {
-            if reversed_data.has_column(feature) {
-                reversed_data = reversed_data.map_scalar_column(feature, |x| {
-                    if min <= &1.0 {
-                        (10 as Scalar).powf(x) - min.abs() - 0.001
-                    } else {
-                        (10 as Scalar).powf(x)
-                    }
-                });
+fn unlog_data(logged_features: &HashMap<String, Scalar>, data: &DataTable) -> DataTable {
+    let mut reversed_data = data.clone();
+
+    for (feature, min) in logged_features.iter() {
+        let unlog = |x: Scalar| {
+            if min <= &1.0 {
+                (10 as Scalar).powf(x) - min.abs() - 0.001
+            } else {
+                (10 as Scalar).powf(x)
+            }
+        };
+
+        if reversed_data.has_column(feature) {
+            reversed_data = reversed_data.map_scalar_column(feature, unlog);
+        }
+
+        // Predicted mean and standard deviation (see `Losses::GaussianNLL`):
+        // the standard deviation is propagated through the exponential with the delta method
+        let mean_column = format!("{}_mean", feature);
+        let std_column = format!("{}_std", feature);
+        if reversed_data.has_column(&mean_column) {
+            if reversed_data.has_column(&std_column) {
+                let means = reversed_data.column_to_vector(&mean_column);
+                let stds = reversed_data
+                    .column_to_vector(&std_column)
+                    .iter()
+                    .zip(means.iter())
+                    .map(|(std, mean)| (10 as Scalar).ln() * (10 as Scalar).powf(*mean) * std)
+                    .collect::<Vec<_>>();
+                reversed_data = reversed_data.with_column_scalar(&std_column, &stds);
             }
+            reversed_data = reversed_data.map_scalar_column(&mean_column, unlog);
         }
+    }
+
+    reversed_data
+}
+
+impl DataTransformation for LogScale10 {
+    fn transform(
+        &mut self,
+        cached_config: &CachedConfig,
+        dataset_config: &Dataset,
+        data: &DataTable,
+    ) -> (Dataset, DataTable) {
+        let mut logged_features = HashMap::new();
+
+        for feature in dataset_config.features.iter() {
+            if feature.log10 {
+                let values = data.column_to_vector(&feature.name);
+                let min = min_vector(&values);
+                logged_features.insert(feature.name.clone(), min);
+            }
+        }
+
+        self.logged_features = logged_features;
+        self.log_scale(cached_config, dataset_config, data)
+    }
+
+    fn apply(&mut self, dataset_config: &Dataset, data: &DataTable) -> (Dataset, DataTable) {
+        self.log_scale(&CachedConfig::NotCached, dataset_config, data)
+    }
+
+    fn reverse_columnswise(&mut self, data: &DataTable) -> DataTable {
+        unlog_data(&self.logged_features, data)
+    }
 
-        reversed_data
+    fn reverter(&self) -> Option<Arc<Reverter>> {
+        let logged_features = self.logged_features.clone();
+        Some(Arc::new(move |data: &DataTable| unlog_data(&logged_features, data)))
     }
 
     fn get_name(&self) -> String {
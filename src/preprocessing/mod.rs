@@ -3,7 +3,8 @@ use std::{
     collections::hash_map::DefaultHasher,
     hash::{Hash, Hasher},
     path::PathBuf,
-    rc::Rc
+    rc::Rc,
+    sync::Arc,
 };
 
 use crate::{dataset::Dataset, datatable::DataTable, monitor::TM};
@@ -31,6 +32,8 @@ pub struct Pipeline {
     cached_config: CachedConfig,
     dataset_config: Option<Dataset>,
     data: Option<DataTable>,
+    // Configuration of the transformed data of the last run
+    fitted_dataset_config: Option<Dataset>,
 }
 
 pub enum CachedConfig {
@@ -45,6 +48,7 @@ impl Pipeline {
             cached_config: CachedConfig::NotCached,
             dataset_config: None,
             data: None,
+            fitted_dataset_config: None,
         }
     }
 
@@ -168,12 +172,47 @@ impl Pipeline {
         };
 
         let data = data.select_columns(dataset_config.feature_names().as_slice());
+        self.fitted_dataset_config = Some(dataset_config.clone());
 
         TM::end_with_message(format!("{:?}", data.describe()));
 
         (dataset_config, data)
     }
 
+    /// Transforms new rows of the loaded dataset with the transformations as they were fitted
+    /// by the last `run` (see `DataTransformation::apply`), without refitting them to the new rows.
+    ///
+    /// The columns are the same as the ones of `run`: the one-hot encoded categories missing
+    /// from the new rows are zeros, and the ones that were not seen by `run` are dropped.
+    pub fn apply(&mut self, data: &DataTable) -> DataTable {
+        let fitted_dataset_config = self
+            .fitted_dataset_config
+            .clone()
+            .expect("The pipeline must be run before applying it to new data");
+        let dataset_config = self.dataset_config.clone().unwrap();
+
+        TM::start("pipeline.apply");
+
+        let data = data.select_columns(dataset_config.feature_names().as_slice());
+        let mut res = (dataset_config, data);
+        for transformation in &mut self.transformations {
+            let mut transformation = transformation.borrow_mut();
+            res = transformation.apply(&res.0, &res.1);
+        }
+
+        let mut data = res.1;
+        for name in fitted_dataset_config.feature_names() {
+            if !data.has_column(name) {
+                data = data.with_column_scalar(name, &vec![0.0; data.num_rows()]);
+            }
+        }
+        let data = data.select_columns(fitted_dataset_config.feature_names().as_slice());
+
+        TM::end_with_message(format!("Transformed {} new rows", data.num_rows()));
+
+        data
+    }
+
     pub fn revert(&mut self, data: &DataTable) -> DataTable {
         let mut res = data.clone();
 
@@ -183,8 +222,30 @@ impl Pipeline {
         }
         res
     }
+
+    /// Thread-safe equivalent of `Pipeline::revert` for the transformations as they are
+    /// after the last `Pipeline::run`, usable by the trainers to compute metrics in original units.
+    ///
+    /// Only the transformations providing a `DataTransformation::reverter` are reverted.
+    pub fn reverter(&self) -> Arc<Reverter> {
+        let reverters = self
+            .transformations
+            .iter()
+            .rev()
+            .filter_map(|transformation| transformation.borrow().reverter())
+            .collect::<Vec<_>>();
+
+        Arc::new(move |data: &DataTable| {
+            reverters
+                .iter()
+                .fold(data.clone(), |data, reverter| reverter(&data))
+        })
+    }
 }
 
+/// Reverts transformed data into its original units (see `Pipeline::reverter`).
+pub type Reverter = dyn Fn(&DataTable) -> DataTable + Send + Sync;
+
 pub trait DataTransformation {
     fn get_name(&self) -> String;
     fn transform(
@@ -194,4 +255,19 @@ pub trait DataTransformation {
         data: &DataTable,
     ) -> (Dataset, DataTable);
     fn reverse_columnswise(&mut self, data: &DataTable) -> DataTable;
+
+    /// Transforms new data with the state fitted by the last `transform`, such as the normalization bounds
+    /// (see `Pipeline::apply`).
+    ///
+    /// Defaults to `transform` without caching, which suits the transformations that are not fitted to the data.
+    fn apply(&mut self, dataset_config: &Dataset, data: &DataTable) -> (Dataset, DataTable) {
+        self.transform(&CachedConfig::NotCached, dataset_config, data)
+    }
+
+    /// Snapshot of `reverse_columnswise` that can be shared between threads.
+    ///
+    /// `None` if the transformation is not reverted.
+    fn reverter(&self) -> Option<Arc<Reverter>> {
+        None
+    }
 }
@@ -1,11 +1,11 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
     dataset::{Dataset, Feature},
     datatable::DataTable,
 };
 
-use super::{feature_cached::FeatureExtractorCached, DataTransformation, CachedConfig};
+use super::{feature_cached::FeatureExtractorCached, CachedConfig, DataTransformation, Reverter};
 use crate::linalg::Scalar;
 
 pub struct Normalize {
@@ -26,6 +26,8 @@ impl Normalize {
         self
     }
 
+    /// Denormalizes the normalized features' columns, as well as their predicted
+    /// `<name>_mean` and `<name>_std` columns (see `Losses::GaussianNLL`).
     pub fn denormalize_data(&self, data: &DataTable) -> DataTable {
         let mut denormalized_data = data.clone();
 
@@ -33,29 +35,32 @@ impl Normalize {
             if denormalized_data.has_column(feature_name) {
                 denormalized_data = denormalized_data.denormalize_column(feature_name, *min_max);
             }
+
+            let mean_column = format!("{}_mean", feature_name);
+            if denormalized_data.has_column(&mean_column) {
+                denormalized_data = denormalized_data.denormalize_column(&mean_column, *min_max);
+            }
+
+            // A standard deviation is only scaled, not shifted
+            let std_column = format!("{}_std", feature_name);
+            if denormalized_data.has_column(&std_column) {
+                let (min, max) = *min_max;
+                denormalized_data =
+                    denormalized_data.map_scalar_column(&std_column, |x| x * (max - min));
+            }
         }
 
         denormalized_data
     }
-}
 
-impl DataTransformation for Normalize {
-    fn transform(
-        &mut self,
+    // Normalizes the features with the fitted bounds
+    fn normalize(
+        &self,
         cached_config: &CachedConfig,
         dataset_config: &Dataset,
         data: &DataTable,
     ) -> (Dataset, DataTable) {
-        let mut features_min_max: HashMap<String, (Scalar, Scalar)> = HashMap::new();
-
-        for feature in dataset_config.features.iter() {
-            if feature.normalized {
-                let min_max = data.min_max_column(&feature.name);
-                features_min_max.insert(feature.name.clone(), min_max);
-            }
-        }
-
-        self.features_min_max = features_min_max.clone();
+        let features_min_max = self.features_min_max.clone();
 
         let mut extractor = FeatureExtractorCached::new(
             Box::new(move |feature: &Feature| match &feature.with_normalized {
@@ -82,11 +87,43 @@ impl DataTransformation for Normalize {
 
         extractor.transform(cached_config, dataset_config, data)
     }
+}
+
+impl DataTransformation for Normalize {
+    fn transform(
+        &mut self,
+        cached_config: &CachedConfig,
+        dataset_config: &Dataset,
+        data: &DataTable,
+    ) -> (Dataset, DataTable) {
+        let mut features_min_max: HashMap<String, (Scalar, Scalar)> = HashMap::new();
+
+        for feature in dataset_config.features.iter() {
+            if feature.normalized {
+                let min_max = data.min_max_column(&feature.name);
+                features_min_max.insert(feature.name.clone(), min_max);
+            }
+        }
+
+        self.features_min_max = features_min_max;
+        self.normalize(cached_config, dataset_config, data)
+    }
+
+    fn apply(&mut self, dataset_config: &Dataset, data: &DataTable) -> (Dataset, DataTable) {
+        self.normalize(&CachedConfig::NotCached, dataset_config, data)
+    }
 
     fn reverse_columnswise(&mut self, data: &DataTable) -> DataTable {
         self.denormalize_data(data)
     }
 
+    fn reverter(&self) -> Option<Arc<Reverter>> {
+        let normalize = Normalize {
+            features_min_max: self.features_min_max.clone(),
+        };
+        Some(Arc::new(move |data: &DataTable| normalize.denormalize_data(data)))
+    }
+
     fn get_name(&self) -> String {
         "norm".to_string()
     }
@@ -1,4 +1,6 @@
-use crate::{dataset::Dataset, datatable::DataTable};
+use serde::{Deserialize, Serialize};
+
+use crate::{dataset::Dataset, datatable::DataTable, linalg::Scalar, vec_utils::class_of};
 
 use super::{DataTransformation, CachedConfig};
 
@@ -38,3 +40,93 @@ impl DataTransformation for Sample {
         format!("sample({},{})", self.count, seed)
     }
 }
+
+/// Class-imbalance aware sampling of the training data, applied anew at each epoch
+/// by `Model::train_epoch` (hence to the training fold only).
+///
+/// The class of a row is computed from its predicted features (see `vec_utils::class_of`).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SamplingStrategy {
+    /// Samples the rows of each class with replacement, as many times as there are rows in the largest class
+    Oversample,
+    /// Samples the rows of each class without replacement, as many times as there are rows in the smallest class
+    Undersample,
+    /// Keeps the number of rows but interleaves the classes so that every mini-batch is balanced,
+    /// sampling the rows of the smaller classes with replacement
+    BalancedBatches,
+}
+
+impl SamplingStrategy {
+    /// Samples the rows of `data` according to the strategy.
+    ///
+    /// The rows are shuffled by `Oversample` and `Undersample`, but they are ordered
+    /// by `BalancedBatches` and must not be shuffled afterwards.
+    pub fn apply<S: AsRef<str>>(&self, data: &DataTable, predicted_features: &[S]) -> DataTable {
+        if data.num_rows() == 0 {
+            return data.clone();
+        }
+
+        let classes = data
+            .select_columns(predicted_features)
+            .to_vectors_with_missing()
+            .iter()
+            .map(|y| class_of(y))
+            .collect::<Vec<_>>();
+        let n_classes = classes.iter().max().map_or(0, |c| c + 1);
+        let classes_column = classes.iter().map(|c| *c as Scalar).collect::<Vec<_>>();
+        let data = data.with_column_scalar(CLASS_COLUMN, &classes_column);
+
+        let classes_tables = (0..n_classes)
+            .map(|c| {
+                data.filter_by_scalar_column(CLASS_COLUMN, |v| v as usize == c)
+                    .drop_column(CLASS_COLUMN)
+            })
+            .filter(|table| table.num_rows() > 0)
+            .collect::<Vec<_>>();
+        let counts = classes_tables.iter().map(|t| t.num_rows());
+
+        match self {
+            SamplingStrategy::Oversample => {
+                let n = counts.max().unwrap_or(0);
+                let mut sampled = DataTable::new_empty();
+                for table in &classes_tables {
+                    sampled = sampled.apppend(&table.resample(n));
+                }
+                sampled.shuffle()
+            }
+            SamplingStrategy::Undersample => {
+                let n = counts.min().unwrap_or(0);
+                let mut sampled = DataTable::new_empty();
+                for table in &classes_tables {
+                    sampled = sampled.apppend(&table.sample(Some(n), true));
+                }
+                sampled.shuffle()
+            }
+            SamplingStrategy::BalancedBatches => {
+                let n_tables = classes_tables.len();
+                let n = (data.num_rows() + n_tables - 1) / n_tables.max(1);
+                let mut sampled = DataTable::new_empty();
+                for (c, table) in classes_tables.iter().enumerate() {
+                    let table = if table.num_rows() >= n {
+                        table.sample(Some(n), true)
+                    } else {
+                        table.resample(n)
+                    };
+                    // The i-th row of the c-th class goes at position i * classes + c
+                    let order = (0..n)
+                        .map(|i| (i * n_tables + c) as Scalar)
+                        .collect::<Vec<_>>();
+                    sampled = sampled.apppend(&table.with_column_scalar(ORDER_COLUMN, &order));
+                }
+                sampled
+                    .sort_by_column(ORDER_COLUMN)
+                    .drop_column(ORDER_COLUMN)
+                    .split(data.num_rows(), 0)
+                    .0
+            }
+        }
+    }
+}
+
+const CLASS_COLUMN: &str = "__sampling_class";
+const ORDER_COLUMN: &str = "__sampling_order";
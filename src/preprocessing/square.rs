@@ -1,11 +1,11 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, sync::Arc};
 
 use crate::{
     dataset::{Dataset, Feature},
     datatable::DataTable,
 };
 
-use super::{feature_cached::FeatureExtractorCached, DataTransformation, CachedConfig};
+use super::{feature_cached::FeatureExtractorCached, CachedConfig, DataTransformation, Reverter};
 
 pub struct Square {
     squared_features: HashSet<String>,
@@ -19,6 +19,18 @@ impl Square {
     }
 }
 
+fn unsquare_data(squared_features: &HashSet<String>, data: &DataTable) -> DataTable {
+    let mut reversed_data = data.clone();
+
+    for feature in squared_features.iter() {
+        if reversed_data.has_column(feature) {
+            reversed_data = reversed_data.map_scalar_column(feature, |x| x.sqrt());
+        }
+    }
+
+    reversed_data
+}
+
 impl DataTransformation for Square {
     fn transform(
         &mut self,
@@ -60,15 +72,12 @@ impl DataTransformation for Square {
     }
 
     fn reverse_columnswise(&mut self, data: &DataTable) -> DataTable {
-        let mut reversed_data = data.clone();
-
-        for feature in self.squared_features.iter() {
-            if reversed_data.has_column(feature) {
-                reversed_data = reversed_data.map_scalar_column(feature, |x| x.sqrt());
-            }
-        }
+        unsquare_data(&self.squared_features, data)
+    }
 
-        reversed_data
+    fn reverter(&self) -> Option<Arc<Reverter>> {
+        let squared_features = self.squared_features.clone();
+        Some(Arc::new(move |data: &DataTable| unsquare_data(&squared_features, data)))
     }
 
     fn get_name(&self) -> String {
@@ -0,0 +1,39 @@
+use std::cell::RefCell;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Reseeds the random number generator of the current thread.
+///
+/// Every random operation of the library (initialization, dropout, shuffling, sampling...)
+/// draws from this generator, so seeding it makes them reproducible.
+///
+/// Each thread has its own generator, seeded from entropy until this function is called on it.
+pub fn set_seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Calls `f` with the random number generator of the current thread.
+pub fn with_rng<T, F: FnOnce(&mut StdRng) -> T>(f: F) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+/// Draws a new seed from the random number generator of the current thread.
+///
+/// Useful to seed external generators (dataframes sampling, GPU backends...).
+pub fn gen_seed() -> u64 {
+    with_rng(|rng| rng.gen())
+}
+
+/// Derives the seed of the `index`-th sub task (a k-fold thread for instance) from a base seed.
+///
+/// Uses the SplitMix64 finalizer so that consecutive indices yield unrelated seeds.
+pub fn derive_seed(seed: u64, index: u64) -> u64 {
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,178 @@
+use std::{fs::File, path::PathBuf, sync::OnceLock};
+
+use polars::{io::mmap::MmapBytesReader, prelude::*};
+use rand::seq::SliceRandom;
+
+use crate::{datatable::DataTable, random::with_rng};
+
+/// Reads a CSV, Parquet or IPC file by chunks of rows,
+/// so that datasets larger than the memory can be trained on epoch by epoch (see `Model::train_epoch_streaming`).
+///
+/// Each epoch reads a CSV file once from start to end with a batched reader, whose chunks hold about `chunk_size` rows.
+/// The chunks of Parquet and IPC files are read with polars' lazy scans, which skip the rest of the file.
+///
+/// With a shuffle buffer, the rows are shuffled within a buffer of at least `shuffle_buffer` rows,
+/// which gets closer to a full shuffle as the buffer grows. The chunks of Parquet and IPC files are also read in a random order,
+/// while the rows of a CSV file can't be reached without reading the previous ones.
+///
+/// Only `SplitTraining::run_streaming` trains on streams, the folds of `KFolds` require the data in memory.
+///
+/// The Parquet and IPC files require the `parquet` and `ipc` features.
+#[derive(Clone, Debug)]
+pub struct DataStream {
+    pub path: PathBuf,
+    pub chunk_size: usize,
+    pub shuffle_buffer: Option<usize>,
+    // Counted once, see `num_rows`
+    rows: OnceLock<usize>,
+}
+
+impl DataStream {
+    pub fn from_file<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            chunk_size: 10_000,
+            shuffle_buffer: None,
+            rows: OnceLock::new(),
+        }
+    }
+
+    /// Sets the number of rows read from the file at once (10 000 by default).
+    pub fn chunk_size(self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "The chunks must not be empty");
+        Self { chunk_size, ..self }
+    }
+
+    /// Shuffles the rows within a buffer of `rows` rows, which should span several chunks.
+    pub fn shuffle_buffer(self, rows: usize) -> Self {
+        Self {
+            shuffle_buffer: Some(rows),
+            ..self
+        }
+    }
+
+    fn extension(&self) -> &str {
+        self.path.extension().unwrap().to_str().unwrap()
+    }
+
+    fn scan(&self) -> LazyFrame {
+        let extension = self.extension();
+        match extension {
+            "csv" => LazyCsvReader::new(&self.path).finish().unwrap(),
+            #[cfg(feature = "ipc")]
+            "ipc" => LazyFrame::scan_ipc(&self.path, ScanArgsIpc::default()).unwrap(),
+            #[cfg(not(feature = "ipc"))]
+            "ipc" => panic!("You need to enable the ipc feature to read ipc files"),
+            #[cfg(feature = "parquet")]
+            "parquet" => LazyFrame::scan_parquet(&self.path, ScanArgsParquet::default()).unwrap(),
+            #[cfg(not(feature = "parquet"))]
+            "parquet" => panic!("You need to enable the parquet feature to read parquet files"),
+            _ => panic!("Unsupported file format: {}", extension),
+        }
+    }
+
+    /// Counts the rows of the file without loading it.
+    ///
+    /// The file is only read the first time, the count is then kept by the stream (and its clones).
+    pub fn num_rows(&self) -> usize {
+        *self.rows.get_or_init(|| {
+            let count = self.scan().select([count()]).collect().unwrap();
+            count.get_columns()[0]
+                .cast(&DataType::UInt64)
+                .unwrap()
+                .u64()
+                .unwrap()
+                .get(0)
+                .unwrap_or(0) as usize
+        })
+    }
+
+    /// Reads at most `len` rows, starting at the `offset`-th one.
+    pub fn read_rows(&self, offset: usize, len: usize) -> DataTable {
+        let frame = self.scan().slice(offset as i64, len as IdxSize).collect().unwrap();
+        DataTable::from_dataframe(frame)
+    }
+
+    /// Iterates over batches of `batch_size` rows, the last one holding the remaining rows.
+    pub fn batches(&self, batch_size: usize) -> DataStreamBatches {
+        assert!(batch_size > 0, "The batches must not be empty");
+        let chunks: Box<dyn Iterator<Item = DataTable> + Send> = match self.extension() {
+            // Polars' batched CSV reader fails on a file without rows
+            _ if self.num_rows() == 0 => Box::new(std::iter::empty()),
+            "csv" => Box::new(self.csv_chunks()),
+            _ => {
+                let mut offsets = (0..self.num_rows()).step_by(self.chunk_size).collect::<Vec<_>>();
+                if self.shuffle_buffer.is_some() {
+                    with_rng(|rng| offsets.shuffle(rng));
+                }
+                let stream = self.clone();
+                Box::new(
+                    offsets
+                        .into_iter()
+                        .map(move |offset| stream.read_rows(offset, stream.chunk_size)),
+                )
+            }
+        };
+        DataStreamBatches {
+            chunks,
+            buffer: None,
+            batch_size,
+            shuffle_buffer: self.shuffle_buffer,
+        }
+    }
+
+    // Reads the CSV file from start to end, with the schema of its lazy scan
+    fn csv_chunks(&self) -> impl Iterator<Item = DataTable> + Send {
+        let schema = self.scan().schema().unwrap();
+        let file: Box<dyn MmapBytesReader> = Box::new(File::open(&self.path).unwrap());
+        let mut reader = CsvReader::new(file)
+            .has_header(true)
+            .with_chunk_size(self.chunk_size)
+            .batched_read(Some(schema))
+            .unwrap();
+        std::iter::from_fn(move || reader.next_batches(1).unwrap())
+            .flatten()
+            .map(DataTable::from_dataframe)
+    }
+}
+
+/// Batches of a `DataStream`, see `DataStream::batches`.
+pub struct DataStreamBatches {
+    chunks: Box<dyn Iterator<Item = DataTable> + Send>,
+    buffer: Option<DataTable>,
+    batch_size: usize,
+    shuffle_buffer: Option<usize>,
+}
+
+impl Iterator for DataStreamBatches {
+    type Item = DataTable;
+
+    fn next(&mut self) -> Option<DataTable> {
+        let buffer_size = self.shuffle_buffer.unwrap_or(0).max(self.batch_size);
+        let mut refilled = false;
+        while self.buffer.as_ref().map_or(0, |b| b.num_rows()) < buffer_size {
+            let Some(chunk) = self.chunks.next() else {
+                break;
+            };
+            self.buffer = Some(match self.buffer.take() {
+                Some(buffer) => buffer.apppend(&chunk),
+                None => chunk,
+            });
+            refilled = true;
+        }
+
+        let mut buffer = self.buffer.take()?;
+        // The rows left from the previous chunks are mixed with the new ones
+        if refilled && self.shuffle_buffer.is_some() {
+            buffer = buffer.shuffle();
+        }
+
+        let rows = buffer.num_rows();
+        if rows <= self.batch_size {
+            return (rows > 0).then_some(buffer);
+        }
+        let (batch, rest) = buffer.split(self.batch_size, rows - self.batch_size);
+        self.buffer = Some(rest);
+        Some(batch)
+    }
+}
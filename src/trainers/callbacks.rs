@@ -0,0 +1,320 @@
+use std::{collections::BTreeSet, fs::File, io::Write, path::PathBuf};
+
+use crate::{
+    benchmarking::EpochEvaluation,
+    linalg::Scalar,
+    metrics::Metric,
+    network::{params::NetworkParams, Network},
+};
+
+/// Whether the training should go on after a callback's hook.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TrainingControl {
+    Continue,
+    Stop,
+}
+
+/// Hooks called by the trainers during the training of a network.
+///
+/// Every hook has access to the network and does nothing by default.
+///
+/// The training stops at the end of the epoch in which a hook returns `TrainingControl::Stop`,
+/// and the final validation is then done with the network as it is after `on_train_end`.
+pub trait TrainingCallback {
+    fn on_epoch_start(&mut self, _epoch: usize, _network: &mut Network) {}
+
+    /// Called after each mini-batch with its training loss.
+    fn on_batch_end(
+        &mut self,
+        _epoch: usize,
+        _batch: usize,
+        _loss: Scalar,
+        _network: &Network,
+    ) -> TrainingControl {
+        TrainingControl::Continue
+    }
+
+    /// Called with the evaluation of the epoch, which only has a validation loss and metrics
+    /// if they were computed at this epoch (see `all_epochs_validation` and `all_epochs_metrics`).
+    fn on_epoch_end(
+        &mut self,
+        _epoch: usize,
+        _eval: &EpochEvaluation,
+        _network: &mut Network,
+    ) -> TrainingControl {
+        TrainingControl::Continue
+    }
+
+    /// Called once the training is over, returns whether it changed the network's parameters.
+    fn on_train_end(&mut self, _network: &mut Network) -> bool {
+        false
+    }
+}
+
+/// Creates the callbacks of each fold of a `KFolds` training from the fold's index.
+pub type CallbackFactory = dyn Fn(usize) -> Box<dyn TrainingCallback> + Send + Sync;
+
+/// The callbacks of one training, called in the order they were added.
+pub struct Callbacks(pub Vec<Box<dyn TrainingCallback>>);
+
+impl Callbacks {
+    pub fn on_epoch_start(&mut self, epoch: usize, network: &mut Network) {
+        for callback in self.0.iter_mut() {
+            callback.on_epoch_start(epoch, network);
+        }
+    }
+
+    // Every callback is called even if a previous one requested to stop
+
+    /// Returns whether any callback requested to stop.
+    pub fn on_batch_end(&mut self, epoch: usize, batch: usize, loss: Scalar, network: &Network) -> bool {
+        let mut stop = false;
+        for callback in self.0.iter_mut() {
+            stop |= callback.on_batch_end(epoch, batch, loss, network) == TrainingControl::Stop;
+        }
+        stop
+    }
+
+    /// Returns whether any callback requested to stop.
+    pub fn on_epoch_end(&mut self, epoch: usize, eval: &EpochEvaluation, network: &mut Network) -> bool {
+        let mut stop = false;
+        for callback in self.0.iter_mut() {
+            stop |= callback.on_epoch_end(epoch, eval, network) == TrainingControl::Stop;
+        }
+        stop
+    }
+
+    /// Returns whether any callback changed the network's parameters.
+    pub fn on_train_end(&mut self, network: &mut Network) -> bool {
+        let mut changed = false;
+        for callback in self.0.iter_mut() {
+            changed |= callback.on_train_end(network);
+        }
+        changed
+    }
+}
+
+/// Value of the epochs' evaluations watched by `EarlyStopping` and `ModelCheckpoint`.
+#[derive(Clone, Debug)]
+pub enum Monitor {
+    /// The average validation loss (default), requires `all_epochs_validation`.
+    TestLoss,
+    /// A metric of the trainer, requires `all_epochs_metrics`.
+    Metric { name: String, higher_is_better: bool },
+}
+
+impl Monitor {
+    /// Watches a metric, which must also be one of the trainer's metrics.
+    pub fn metric<M: Metric>(metric: M) -> Self {
+        Monitor::Metric {
+            name: metric.name(),
+            higher_is_better: metric.higher_is_better(),
+        }
+    }
+
+    /// The watched value, if it was computed at this epoch.
+    pub fn value(&self, eval: &EpochEvaluation) -> Option<Scalar> {
+        match self {
+            // The targets' losses are only empty if the validation was skipped
+            Monitor::TestLoss => (!eval.test_targets_losses.is_empty()).then_some(eval.test_loss_avg),
+            Monitor::Metric { name, .. } => eval.get_metric(name),
+        }
+    }
+
+    /// Whether `value` is better than `best` by more than `min_delta`.
+    pub fn improves(&self, value: Scalar, best: Option<Scalar>, min_delta: Scalar) -> bool {
+        match (self, best) {
+            (_, None) => true,
+            (Monitor::Metric { higher_is_better: true, .. }, Some(best)) => value > best + min_delta,
+            (_, Some(best)) => value < best - min_delta,
+        }
+    }
+}
+
+/// Stops the training once the monitored value hasn't improved by more than `min_delta`
+/// for `patience` consecutive evaluations.
+///
+/// With `restore_best`, the parameters of the best epoch are loaded back at the end of the training.
+pub struct EarlyStopping {
+    pub patience: usize,
+    pub min_delta: Scalar,
+    pub restore_best: bool,
+    pub monitor: Monitor,
+    best: Option<Scalar>,
+    best_params: Option<NetworkParams>,
+    wait: usize,
+}
+
+impl EarlyStopping {
+    pub fn new(patience: usize, min_delta: Scalar, restore_best: bool) -> Self {
+        Self {
+            patience,
+            min_delta,
+            restore_best,
+            monitor: Monitor::TestLoss,
+            best: None,
+            best_params: None,
+            wait: 0,
+        }
+    }
+
+    /// Sets the watched value (the validation loss by default).
+    pub fn monitor(self, monitor: Monitor) -> Self {
+        Self { monitor, ..self }
+    }
+}
+
+impl TrainingCallback for EarlyStopping {
+    fn on_epoch_end(
+        &mut self,
+        _epoch: usize,
+        eval: &EpochEvaluation,
+        network: &mut Network,
+    ) -> TrainingControl {
+        let Some(value) = self.monitor.value(eval) else {
+            return TrainingControl::Continue;
+        };
+
+        if self.monitor.improves(value, self.best, self.min_delta) {
+            self.best = Some(value);
+            self.wait = 0;
+            if self.restore_best {
+                self.best_params = Some(network.get_params());
+            }
+            return TrainingControl::Continue;
+        }
+
+        self.wait += 1;
+        if self.wait >= self.patience {
+            TrainingControl::Stop
+        } else {
+            TrainingControl::Continue
+        }
+    }
+
+    fn on_train_end(&mut self, network: &mut Network) -> bool {
+        // The last epoch is the best one if it was not followed by worse ones
+        match self.best_params.as_ref() {
+            Some(best_params) if self.wait > 0 => {
+                network.load_params(best_params);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Saves the network's parameters as JSON at the end of each epoch,
+/// or only when the monitored value improves with `best_only`.
+///
+/// `{epoch}` in the path is replaced by the (1-based) epoch number.
+pub struct ModelCheckpoint {
+    pub path: String,
+    pub best_only: bool,
+    pub monitor: Monitor,
+    best: Option<Scalar>,
+}
+
+impl ModelCheckpoint {
+    pub fn new<S: ToString>(path: S, best_only: bool) -> Self {
+        Self {
+            path: path.to_string(),
+            best_only,
+            monitor: Monitor::TestLoss,
+            best: None,
+        }
+    }
+
+    /// Sets the watched value (the validation loss by default).
+    pub fn monitor(self, monitor: Monitor) -> Self {
+        Self { monitor, ..self }
+    }
+}
+
+impl TrainingCallback for ModelCheckpoint {
+    fn on_epoch_end(
+        &mut self,
+        epoch: usize,
+        eval: &EpochEvaluation,
+        network: &mut Network,
+    ) -> TrainingControl {
+        if self.best_only {
+            match self.monitor.value(eval) {
+                Some(value) if self.monitor.improves(value, self.best, 0.) => {
+                    self.best = Some(value);
+                }
+                _ => return TrainingControl::Continue,
+            }
+        }
+
+        let path = self.path.replace("{epoch}", &(epoch + 1).to_string());
+        network.get_params().to_json(path);
+
+        TrainingControl::Continue
+    }
+}
+
+/// Writes the losses and metrics of each epoch to a CSV file.
+///
+/// The file is rewritten at each epoch, so that metrics computed only
+/// at some epochs get their own (partially empty) columns.
+pub struct CsvLogger {
+    pub path: PathBuf,
+    rows: Vec<(usize, EpochEvaluation)>,
+}
+
+impl CsvLogger {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            rows: vec![],
+        }
+    }
+
+    fn write(&self) {
+        let metrics_names = self
+            .rows
+            .iter()
+            .flat_map(|(_, eval)| eval.metrics.keys().cloned())
+            .collect::<BTreeSet<_>>();
+
+        let mut csv = String::from("epoch,train_loss,test_loss_avg,test_loss_std");
+        for name in metrics_names.iter() {
+            csv.push_str(&format!(",{}", name));
+        }
+        csv.push('\n');
+
+        for (epoch, eval) in self.rows.iter() {
+            csv.push_str(&format!(
+                "{},{},{},{}",
+                epoch + 1,
+                eval.train_loss,
+                eval.test_loss_avg,
+                eval.test_loss_std
+            ));
+            for name in metrics_names.iter() {
+                match eval.get_metric(name) {
+                    Some(value) => csv.push_str(&format!(",{}", value)),
+                    None => csv.push(','),
+                }
+            }
+            csv.push('\n');
+        }
+
+        let mut file = File::create(&self.path).unwrap();
+        file.write_all(csv.as_bytes()).unwrap();
+    }
+}
+
+impl TrainingCallback for CsvLogger {
+    fn on_epoch_end(
+        &mut self,
+        epoch: usize,
+        eval: &EpochEvaluation,
+        _network: &mut Network,
+    ) -> TrainingControl {
+        self.rows.push((epoch, eval.clone()));
+        self.write();
+        TrainingControl::Continue
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+
+use crate::{
+    datatable::DataTable, linalg::Scalar, model::Model, random::with_rng, vec_utils::class_of,
+};
+
+/// Rows of the training and validation sets of a fold.
+pub type FoldIndices = (Vec<usize>, Vec<usize>);
+
+/// Strategies splitting the data into the folds of a cross validation (see `KFolds`).
+#[derive(Clone, Debug)]
+pub enum CrossValidation {
+    /// `k` contiguous folds, each one being validated on once.
+    KFold { k: usize },
+    /// `k` folds in which each class (see `vec_utils::class_of`) of the predicted features is evenly represented.
+    StratifiedKFold { k: usize },
+    /// `k` contiguous folds of the shuffled data, `repeats` times with a different shuffle.
+    RepeatedKFold { k: usize, repeats: usize },
+    /// `k` folds that never split the samples of a group (see `FeatureTags::Group`).
+    ///
+    /// The largest groups are assigned first, each to the fold with the fewest samples.
+    GroupKFold { k: usize },
+    /// `splits` independent random splits, each validated on a `validation_ratio` of the data.
+    ShuffleSplit { splits: usize, validation_ratio: Scalar },
+    /// Walk-forward validation of data in chronological order (see `DataTable::sort_by_column`).
+    ///
+    /// The data is cut into `splits + 1` blocks, and the `i`-th split trains on the first `i + 1` blocks
+    /// and is validated on the next one, so that no fold is validated on samples older than its training ones.
+    TimeSeriesSplit { splits: usize },
+}
+
+impl CrossValidation {
+    /// Number of folds, hence of trained networks.
+    pub fn n_folds(&self) -> usize {
+        match self {
+            CrossValidation::KFold { k }
+            | CrossValidation::StratifiedKFold { k }
+            | CrossValidation::GroupKFold { k } => *k,
+            CrossValidation::RepeatedKFold { k, repeats } => k * repeats,
+            CrossValidation::ShuffleSplit { splits, .. } | CrossValidation::TimeSeriesSplit { splits } => {
+                *splits
+            }
+        }
+    }
+
+    /// Computes the rows of the training and validation sets of every fold.
+    ///
+    /// The stratified and group k-folds require the model's dataset configuration
+    /// for the predicted features and the group column.
+    ///
+    /// Panics if a fold has no training or validation row, when there are too few rows (or groups) for the folds.
+    pub fn folds(&self, model: &Model, data: &DataTable) -> Vec<FoldIndices> {
+        assert!(self.n_folds() > 0, "A cross validation requires at least one fold");
+        let n = data.num_rows();
+        let folds = match self {
+            CrossValidation::KFold { k } => k_folds(&(0..n).collect::<Vec<_>>(), *k),
+            CrossValidation::StratifiedKFold { k } => {
+                let predicted_features = model.dataset_config.predicted_features_names();
+                let targets = data.select_columns(&predicted_features).to_vectors_with_missing();
+                stratified_k_folds(&targets, *k)
+            }
+            CrossValidation::RepeatedKFold { k, repeats } => (0..*repeats)
+                .flat_map(|_| {
+                    let mut rows = (0..n).collect::<Vec<_>>();
+                    with_rng(|rng| rows.shuffle(rng));
+                    k_folds(&rows, *k)
+                })
+                .collect(),
+            CrossValidation::GroupKFold { k } => {
+                let group_column = model
+                    .dataset_config
+                    .get_group_column()
+                    .expect("A feature must be tagged as the group for a group k-fold");
+                group_k_folds(&data.column_to_strings(group_column), *k)
+            }
+            CrossValidation::ShuffleSplit {
+                splits,
+                validation_ratio,
+            } => (0..*splits)
+                .map(|_| {
+                    let mut rows = (0..n).collect::<Vec<_>>();
+                    with_rng(|rng| rows.shuffle(rng));
+                    let validation_rows = (n as Scalar * validation_ratio) as usize;
+                    let (validation, train) = rows.split_at(validation_rows);
+                    (train.to_vec(), validation.to_vec())
+                })
+                .collect(),
+            CrossValidation::TimeSeriesSplit { splits } => {
+                let block = n / (splits + 1);
+                (0..*splits)
+                    .map(|i| {
+                        let train_end = block * (i + 1);
+                        let validation_end = if i == splits - 1 { n } else { train_end + block };
+                        ((0..train_end).collect(), (train_end..validation_end).collect())
+                    })
+                    .collect()
+            }
+        };
+
+        for (i, (train, validation)) in folds.iter().enumerate() {
+            assert!(
+                !train.is_empty() && !validation.is_empty(),
+                "The fold {} of the {:?} cross validation of {} rows has no training or no validation row",
+                i,
+                self,
+                n
+            );
+        }
+        folds
+    }
+}
+
+// Like `DataTable::split_k_folds`, the remaining rows are always in the training set
+fn k_folds(rows: &[usize], k: usize) -> Vec<FoldIndices> {
+    let rows_per_fold = rows.len() / k;
+    (0..k)
+        .map(|i| {
+            let (start, end) = (rows_per_fold * i, rows_per_fold * (i + 1));
+            let train = rows[..start].iter().chain(rows[end..].iter()).copied().collect();
+            (train, rows[start..end].to_vec())
+        })
+        .collect()
+}
+
+// The samples of each class are dealt to the folds in turn
+fn stratified_k_folds(targets: &[Vec<Scalar>], k: usize) -> Vec<FoldIndices> {
+    let mut classes_counts: HashMap<usize, usize> = HashMap::new();
+    let mut folds_of_rows = Vec::with_capacity(targets.len());
+    for target in targets.iter() {
+        let count = classes_counts.entry(class_of(target)).or_insert(0);
+        folds_of_rows.push(*count % k);
+        *count += 1;
+    }
+
+    folds_from_assignments(&folds_of_rows, k)
+}
+
+fn group_k_folds(groups: &[String], k: usize) -> Vec<FoldIndices> {
+    let mut groups_sizes: HashMap<&str, usize> = HashMap::new();
+    for group in groups.iter() {
+        *groups_sizes.entry(group.as_str()).or_insert(0) += 1;
+    }
+
+    // Sorted by decreasing size, then by name to be deterministic
+    let mut groups_by_size = groups_sizes.into_iter().collect::<Vec<_>>();
+    groups_by_size.sort_by(|(a_name, a_size), (b_name, b_size)| b_size.cmp(a_size).then(a_name.cmp(b_name)));
+
+    let mut folds_sizes = vec![0; k];
+    let mut groups_folds: HashMap<&str, usize> = HashMap::new();
+    for (group, size) in groups_by_size {
+        let (fold, _) = folds_sizes
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, fold_size)| **fold_size)
+            .unwrap();
+        folds_sizes[fold] += size;
+        groups_folds.insert(group, fold);
+    }
+
+    let folds_of_rows = groups
+        .iter()
+        .map(|group| groups_folds[group.as_str()])
+        .collect::<Vec<_>>();
+    folds_from_assignments(&folds_of_rows, k)
+}
+
+fn folds_from_assignments(folds_of_rows: &[usize], k: usize) -> Vec<FoldIndices> {
+    (0..k)
+        .map(|i| {
+            let (validation, train): (Vec<_>, Vec<_>) =
+                (0..folds_of_rows.len()).partition(|row| folds_of_rows[*row] == i);
+            (train, validation)
+        })
+        .collect()
+}
@@ -0,0 +1,373 @@
+use std::{
+    fmt::Debug,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::Mutex,
+    thread,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    datatable::DataTable,
+    linalg::Scalar,
+    model::Model,
+    monitor::TM,
+    network::{params::NetworkParams, sum_gradients, NetworkGradients, OutputGradient},
+    random::{derive_seed, set_seed},
+};
+
+/// How the `ParameterServer` combines the gradients of its workers.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SgdMode {
+    /// Each step waits for a mini-batch of every worker, derives the loss on all their predictions
+    /// (see `Network::output_gradient`), and applies the sum of their gradients.
+    ///
+    /// Equivalent to training on mini-batches of `workers * batch_size` samples.
+    Sync,
+    /// The gradients of each worker are applied as soon as they arrive,
+    /// even if they were computed with parameters updated since (stale gradients).
+    Async,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+enum ServerMessage {
+    Hello { worker: usize, workers: usize, mode: SgdMode },
+    Step { epoch: usize, params: NetworkParams },
+    /// The derivative of the loss for the predictions of the worker's mini-batch (synchronous mode)
+    OutputGradient(OutputGradient),
+    Stop,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+enum WorkerMessage {
+    Gradients {
+        loss: Scalar,
+        samples: usize,
+        gradients: NetworkGradients,
+    },
+    /// The predictions of a mini-batch, to be derived with the ones of the other workers (synchronous mode)
+    Predictions {
+        y_true: Vec<Vec<Scalar>>,
+        y_pred: Vec<Vec<Scalar>>,
+        weights: Option<Vec<Scalar>>,
+    },
+    /// The gradients of the last predictions (synchronous mode)
+    LayersGradients(NetworkGradients),
+    /// The worker went through all its samples for the epoch
+    EpochDone,
+}
+
+/// Holds the network's parameters of a distributed training and updates them with the gradients
+/// computed by `Worker`s on their shard of the data.
+///
+/// The server and the workers communicate over TCP with bincode messages,
+/// so the workers can be threads or processes of the same machine as well as remote ones.
+///
+/// The model must only have full dense layers (see `Network::compute_gradients`),
+/// and all the workers must use the same model.
+pub struct ParameterServer {
+    pub model: Model,
+    pub mode: SgdMode,
+    pub workers: usize,
+    listener: TcpListener,
+}
+
+impl ParameterServer {
+    /// Listens on `address` (such as `127.0.0.1:0` for any free local port) for `workers` workers.
+    pub fn bind<A: ToSocketAddrs>(model: Model, mode: SgdMode, workers: usize, address: A) -> io::Result<Self> {
+        assert!(workers > 0, "A distributed training requires workers");
+        Ok(Self {
+            model,
+            mode,
+            workers,
+            listener: TcpListener::bind(address)?,
+        })
+    }
+
+    /// Address the workers must connect to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Waits for the workers to connect, trains the network for the model's epochs and stops the workers.
+    ///
+    /// Returns the trained parameters and the average training loss of each epoch.
+    pub fn run(&self) -> io::Result<(NetworkParams, Vec<Scalar>)> {
+        TM::start("distributed");
+
+        if let Some(seed) = self.model.seed {
+            set_seed(seed);
+        }
+        let mut network = self.model.to_network();
+        assert!(
+            network.supports_data_parallel(),
+            "Only full dense networks support the distributed training"
+        );
+
+        TM::start("connect");
+        let mut streams = Vec::with_capacity(self.workers);
+        for worker in 0..self.workers {
+            let (mut stream, _) = self.listener.accept()?;
+            stream.set_nodelay(true)?;
+            send(
+                &mut stream,
+                &ServerMessage::Hello {
+                    worker,
+                    workers: self.workers,
+                    mode: self.mode,
+                },
+            )?;
+            streams.push(stream);
+        }
+        TM::end_with_message(format!("{} workers connected", self.workers));
+
+        let epochs = self.model.epochs;
+        let losses = match self.mode {
+            SgdMode::Sync => {
+                let loss_fn = self.model.loss.to_loss();
+                let mut losses = Vec::with_capacity(epochs);
+                for epoch in 0..epochs {
+                    TM::start(format!("epoch {}/{}", epoch + 1, epochs));
+                    let mut active = vec![true; streams.len()];
+                    let (mut loss_sum, mut samples_sum) = (0.0, 0);
+                    loop {
+                        let params = network.get_params();
+                        for (stream, _) in streams.iter_mut().zip(active.iter()).filter(|(_, a)| **a) {
+                            send(stream, &ServerMessage::Step { epoch, params: params.clone() })?;
+                        }
+
+                        // The workers which sent a mini-batch, with its number of samples
+                        let mut stepping = vec![];
+                        let (mut y_true, mut y_pred, mut weights) = (vec![], vec![], None::<Vec<Scalar>>);
+                        for (worker, (stream, active)) in streams.iter_mut().zip(active.iter_mut()).enumerate() {
+                            if !*active {
+                                continue;
+                            }
+                            match receive(stream)? {
+                                WorkerMessage::Predictions {
+                                    y_true: worker_y_true,
+                                    y_pred: worker_y_pred,
+                                    weights: worker_weights,
+                                } => {
+                                    stepping.push((worker, worker_y_pred.len()));
+                                    y_true.extend(worker_y_true);
+                                    y_pred.extend(worker_y_pred);
+                                    if let Some(worker_weights) = worker_weights {
+                                        weights.get_or_insert_with(Vec::new).extend(worker_weights);
+                                    }
+                                }
+                                WorkerMessage::EpochDone => *active = false,
+                                message => return Err(unexpected("worker", message)),
+                            }
+                        }
+
+                        if stepping.is_empty() {
+                            break;
+                        }
+
+                        // The loss is derived on the whole step, like on a single mini-batch
+                        let (loss, output_gradient) =
+                            network.output_gradient(&y_true, &y_pred, weights.as_deref(), &loss_fn);
+                        loss_sum += loss * y_pred.len() as Scalar;
+                        samples_sum += y_pred.len();
+
+                        let mut first_sample = 0;
+                        for (worker, samples) in stepping.iter() {
+                            let worker_gradient = output_gradient.samples(first_sample..first_sample + samples);
+                            send(&mut streams[*worker], &ServerMessage::OutputGradient(worker_gradient))?;
+                            first_sample += samples;
+                        }
+
+                        let mut step_gradients = vec![];
+                        for (worker, _) in stepping.iter() {
+                            match receive(&mut streams[*worker])? {
+                                WorkerMessage::LayersGradients(gradients) => step_gradients.push(gradients),
+                                message => return Err(unexpected("worker", message)),
+                            }
+                        }
+                        network.apply_gradients(epoch, &sum_gradients(step_gradients));
+                    }
+                    let loss = loss_sum / samples_sum as Scalar;
+                    TM::end_with_message(format!("avg_error: {:.4}", loss));
+                    losses.push(loss);
+                }
+                losses
+            }
+            SgdMode::Async => {
+                let shared_network = Mutex::new(network);
+                // Sum of the losses weighted by the samples, and number of samples of each epoch
+                let epochs_losses = Mutex::new(vec![(0.0, 0); epochs]);
+                thread::scope(|scope| {
+                    let handles = streams
+                        .iter_mut()
+                        .map(|stream| {
+                            let (network, epochs_losses) = (&shared_network, &epochs_losses);
+                            scope.spawn(move || -> io::Result<()> {
+                                for epoch in 0..epochs {
+                                    loop {
+                                        let params = network.lock().unwrap().get_params();
+                                        send(stream, &ServerMessage::Step { epoch, params })?;
+                                        match receive(stream)? {
+                                            WorkerMessage::Gradients { loss, samples, gradients } => {
+                                                network.lock().unwrap().apply_gradients(epoch, &gradients);
+                                                let (loss_sum, samples_sum) = &mut epochs_losses.lock().unwrap()[epoch];
+                                                *loss_sum += loss * samples as Scalar;
+                                                *samples_sum += samples;
+                                            }
+                                            WorkerMessage::EpochDone => break,
+                                            message => return Err(unexpected("worker", message)),
+                                        }
+                                    }
+                                }
+                                Ok(())
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    handles.into_iter().try_for_each(|h| h.join().unwrap())
+                })?;
+                network = shared_network.into_inner().unwrap();
+                epochs_losses
+                    .into_inner()
+                    .unwrap()
+                    .into_iter()
+                    .map(|(loss_sum, samples_sum)| loss_sum / samples_sum as Scalar)
+                    .collect()
+            }
+        };
+
+        for stream in streams.iter_mut() {
+            send(stream, &ServerMessage::Stop)?;
+        }
+
+        TM::end();
+        Ok((network.get_params(), losses))
+    }
+}
+
+/// Computes the gradients of the mini-batches of its shard of the data for a `ParameterServer`.
+///
+/// The shard of the i-th worker to connect out of `n` is made of the rows `r` of the data where `r % n == i`,
+/// shuffled (or resampled, see `Model::epoch_vectors`) at each epoch.
+pub struct Worker;
+
+impl Worker {
+    /// Connects to the server and computes gradients until it stops the training.
+    ///
+    /// `data` is the whole training data, the worker only keeps its shard.
+    pub fn run<A: ToSocketAddrs>(address: A, model: &Model, data: &DataTable) -> io::Result<()> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let (worker, workers, mode) = match receive(&mut stream)? {
+            ServerMessage::Hello { worker, workers, mode } => (worker, workers, mode),
+            message => return Err(unexpected("parameter server", message)),
+        };
+
+        if let Some(seed) = model.seed {
+            set_seed(derive_seed(seed, worker as u64));
+        }
+        let rows = (0..data.num_rows()).filter(|r| r % workers == worker).collect::<Vec<_>>();
+        let shard = data.take_rows(&rows);
+        let id_column = model
+            .dataset_config
+            .get_id_column()
+            .expect("One feature must be configured as an id in the dataset configuration.");
+        let loss = model.loss.to_loss();
+        let mut network = model.to_network();
+
+        let mut current_epoch = None;
+        let (mut x, mut y, mut weights) = (vec![], vec![], None);
+        let mut next_sample = 0;
+        loop {
+            match receive(&mut stream)? {
+                ServerMessage::Step { epoch, params } => {
+                    if current_epoch != Some(epoch) {
+                        (x, y, weights) = model.epoch_vectors(&shard, id_column);
+                        next_sample = 0;
+                        current_epoch = Some(epoch);
+                    }
+                    if next_sample >= x.len() {
+                        send(&mut stream, &WorkerMessage::EpochDone)?;
+                        continue;
+                    }
+
+                    let batch_end = (next_sample + model.batch_size.unwrap_or(x.len())).min(x.len());
+                    let batch = next_sample..batch_end;
+                    let batch_weights = weights.as_ref().map(|w: &Vec<Scalar>| &w[batch.clone()]);
+                    network.load_params(&params);
+                    match mode {
+                        SgdMode::Sync => {
+                            let y_pred = network.forward_gradients(&x[batch.clone()]);
+                            send(
+                                &mut stream,
+                                &WorkerMessage::Predictions {
+                                    y_true: y[batch.clone()].to_vec(),
+                                    y_pred,
+                                    weights: batch_weights.map(|w| w.to_vec()),
+                                },
+                            )?;
+                            let output_gradient = match receive(&mut stream)? {
+                                ServerMessage::OutputGradient(output_gradient) => output_gradient,
+                                message => return Err(unexpected("parameter server", message)),
+                            };
+                            let gradients = network.backward_gradients(epoch, &output_gradient);
+                            send(&mut stream, &WorkerMessage::LayersGradients(gradients))?;
+                        }
+                        SgdMode::Async => {
+                            let (loss, gradients) = network.compute_gradients(
+                                epoch,
+                                &x[batch.clone()],
+                                &y[batch.clone()],
+                                batch_weights,
+                                &loss,
+                            );
+                            send(
+                                &mut stream,
+                                &WorkerMessage::Gradients {
+                                    loss,
+                                    samples: batch.len(),
+                                    gradients,
+                                },
+                            )?;
+                        }
+                    }
+                    next_sample = batch_end;
+                }
+                ServerMessage::Stop => return Ok(()),
+                message => return Err(unexpected("parameter server", message)),
+            }
+        }
+    }
+}
+
+// Messages are sent as their bincode length followed by their bincode
+fn send<T: Serialize>(stream: &mut TcpStream, message: &T) -> io::Result<()> {
+    let bytes = bincode::serialize(message).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    stream.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    stream.write_all(&bytes)
+}
+
+// Longer messages are rejected before allocating them, in case of a corrupted or malicious length
+const MAX_MESSAGE_LEN: u64 = 1 << 30;
+
+fn receive<T: DeserializeOwned>(stream: &mut TcpStream) -> io::Result<T> {
+    let mut len = [0; 8];
+    stream.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Message of {} bytes, longer than the maximum of {}", len, MAX_MESSAGE_LEN),
+        ));
+    }
+    let mut bytes = vec![0; len as usize];
+    stream.read_exact(&mut bytes)?;
+    bincode::deserialize(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn unexpected<M: Debug>(sender: &str, message: M) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Unexpected message from the {}: {:?}", sender, message),
+    )
+}
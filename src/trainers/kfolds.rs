@@ -6,15 +6,31 @@ use std::{
 use crate::{
     benchmarking::{EpochEvaluation, ModelEvaluation, TrainingEvaluation},
     datatable::DataTable,
-    linalg::{Matrix, MatrixTrait},
+    linalg::{Matrix, MatrixTrait, Scalar},
+    loader::DataLoader,
+    metrics::{default_metrics, Metric},
     model::Model,
     monitor::TM,
-    network::{params::NetworkParams, Network},
-    vec_utils::r2_score_vector2,
+    network::{
+        ensemble::{Ensemble, EnsembleMethod},
+        params::NetworkParams,
+        params_averaging::ParamsAveraging,
+        Network,
+    },
+    preprocessing::Reverter,
+    random::{derive_seed, set_seed},
+    trainers::{
+        callbacks::{CallbackFactory, Callbacks, TrainingCallback},
+        cross_validation::CrossValidation,
+        train_network, Trainer, TrainingOptions, ValidationSet,
+    },
 };
 
 pub type ReporterClosure = dyn FnMut(usize, usize, EpochEvaluation) -> () + Send + Sync;
 
+// Builds the loader of a fold from its training rows
+type LoaderFactory<'a> = dyn Fn(&DataTable) -> Box<dyn DataLoader + Send> + 'a;
+
 /// K-Folds trainer
 ///
 /// Trains a model using K-Folds cross validation.
@@ -26,43 +42,106 @@ pub type ReporterClosure = dyn FnMut(usize, usize, EpochEvaluation) -> () + Send
 /// The model is trained on `k-1` folds and validated on the remaining fold.
 ///
 /// This process is repeated `k` times, each time using a different fold for validation.
+///
+/// Other ways of splitting the data (stratified, grouped, walk-forward...) are available with `KFolds::with_cross_validation`.
+///
+/// The folds are split from data in memory, streamed data can only be trained on with `SplitTraining::run_streaming`.
+/// The training mini-batches of each fold can come from a `DataLoader` with `KFolds::run_loader`.
 pub struct KFolds {
-    pub k: usize,
+    pub cross_validation: CrossValidation,
     pub real_time_reporter: Arc<Option<Mutex<Box<ReporterClosure>>>>,
     pub return_best: bool,
     pub return_avg: bool,
     pub best: Option<NetworkParams>,
     pub avg: Option<NetworkParams>,
+    /// The method and whether the members are weighted by their score, if an ensemble is computed
+    pub return_ensemble: Option<(EnsembleMethod, bool)>,
+    pub ensemble: Option<Ensemble>,
     pub all_epochs_validation: bool,
-    pub all_epochs_r2: bool,
+    pub all_epochs_metrics: bool,
+    pub metrics: Vec<Arc<dyn Metric>>,
+    pub best_metric: Option<String>,
+    pub seed: Option<u64>,
+    pub params_averaging: Option<ParamsAveraging>,
+    pub classification_top_k: usize,
+    pub reverter: Option<Arc<Reverter>>,
+    pub callbacks: Vec<Arc<CallbackFactory>>,
 }
 
 impl KFolds {
     pub fn new(k: usize) -> Self {
+        Self::with_cross_validation(CrossValidation::KFold { k })
+    }
+
+    /// Creates a trainer splitting the data into folds with the given strategy.
+    ///
+    /// With a repeated k-fold or a shuffle-split, the predictions hold each sample once per fold it is validated in.
+    pub fn with_cross_validation(cross_validation: CrossValidation) -> Self {
         Self {
-            k,
+            cross_validation,
             real_time_reporter: Arc::new(None),
             all_epochs_validation: false,
-            all_epochs_r2: false,
+            all_epochs_metrics: false,
+            metrics: vec![],
+            best_metric: None,
             return_best: false,
             return_avg: false,
             best: None,
             avg: None,
+            return_ensemble: None,
+            ensemble: None,
+            seed: None,
+            params_averaging: None,
+            classification_top_k: 5,
+            reverter: None,
+            callbacks: vec![],
         }
     }
 
+    /// Seeds every random operation of the training, overriding the model's seed if any.
+    ///
+    /// Each fold gets its own seed derived from it, so that the results don't depend
+    /// on whether the folds are trained in parallel or sequentially.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
     /// Enables saving the model of the best fold at the final epoch
     pub fn compute_best_model(&mut self) -> &mut Self {
         self.return_best = true;
         self
     }
 
+    /// Sets the name of the metric deciding the best fold (the first metric of the trainer by default).
+    pub fn best_metric(&mut self, name: &str) -> &mut Self {
+        self.best_metric = Some(name.to_string());
+        self
+    }
+
     /// Enables computing the average model of all folds at the final epoch
+    ///
+    /// Averaging the parameters of independently trained networks is rarely meaningful,
+    /// an ensemble of the folds' networks is usually better (see `compute_ensemble`).
     pub fn compute_avg_model(&mut self) -> &mut Self {
         self.return_avg = true;
         self
     }
 
+    /// Enables keeping the networks of all folds as an ensemble combining their predictions with `method`.
+    ///
+    /// With `score_weighted`, each network is weighted by its final value of the best fold metric
+    /// (see `best_metric` and `Ensemble::weights_from_scores`), otherwise they all have the same weight.
+    pub fn compute_ensemble(&mut self, method: EnsembleMethod, score_weighted: bool) -> &mut Self {
+        self.return_ensemble = Some((method, score_weighted));
+        self
+    }
+
+    /// Returns the ensemble of the folds' networks if computed
+    pub fn take_ensemble(&mut self) -> Ensemble {
+        self.ensemble.take().unwrap()
+    }
+
     /// Returns the best model of the folds if computed
     pub fn take_best_model(&mut self) -> NetworkParams {
         self.best.take().unwrap()
@@ -73,16 +152,69 @@ impl KFolds {
         self.avg.take().unwrap()
     }
 
-    /// Enables computing the R2 score of the model at the end of each epoch
-    /// and reporting it if a real time reporter is attached.
-    /// 
+    /// Enables keeping an exponential moving average of the parameters, updated at the end of each epoch.
+    ///
+    /// The averaged parameters replace the final ones before the final validation.
+    pub fn ema_params(&mut self, decay: Scalar) -> &mut Self {
+        self.params_averaging = Some(ParamsAveraging::ExponentialMovingAverage { decay });
+        self
+    }
+
+    /// Enables Stochastic Weight Averaging of the parameters over the last `epochs` epochs.
+    ///
+    /// The averaged parameters replace the final ones before the final validation.
+    pub fn swa_params(&mut self, epochs: usize) -> &mut Self {
+        self.params_averaging = Some(ParamsAveraging::StochasticWeightAveraging { epochs });
+        self
+    }
+
+    /// Sets the `k` of the top-k accuracy computed for classification models (5 by default).
+    pub fn classification_top_k(&mut self, k: usize) -> &mut Self {
+        self.classification_top_k = k;
+        self
+    }
+
+    /// Adds a metric computed on the validation set and stored under its name in the epochs' evaluations.
+    ///
+    /// Defaults to the accuracy for classification losses and the R2 score otherwise (see `metrics::default_metrics`).
+    pub fn metric<M: Metric + 'static>(&mut self, metric: M) -> &mut Self {
+        self.metrics.push(Arc::new(metric));
+        self
+    }
+
+    /// Computes the metrics on the validation targets and point predictions reverted
+    /// into their original units (see `Pipeline::reverter`), instead of the preprocessed ones.
+    ///
+    /// Meant for the regression metrics (mae, rmse...), for instance:
+    /// `trainer.metrics_in_original_units(pipeline.reverter()).metric(Rmse)`.
+    pub fn metrics_in_original_units(&mut self, reverter: Arc<Reverter>) -> &mut Self {
+        self.reverter = Some(reverter);
+        self
+    }
+
+    /// Adds a callback called during the training of each fold (see `TrainingCallback`), such as `EarlyStopping`.
+    ///
+    /// `make_callback` creates the callback of each fold from the fold's index, for instance
+    /// `.callback(|fold| CsvLogger::new(format!("fold_{}.csv", fold)))`.
+    pub fn callback<C, F>(&mut self, make_callback: F) -> &mut Self
+    where
+        C: TrainingCallback + 'static,
+        F: Fn(usize) -> C + Send + Sync + 'static,
+    {
+        self.callbacks.push(Arc::new(move |fold| Box::new(make_callback(fold)) as Box<dyn TrainingCallback>));
+        self
+    }
+
+    /// Enables computing the metrics of the model at the end of each epoch
+    /// and reporting them if a real time reporter is attached.
+    ///
     /// /!\ Requires `all_epochs_validation` to be enabled.
     ///
     /// /!\ Is time consuming.
     ///
-    /// Otherwise computes it only at the end of the final epoch
-    pub fn all_epochs_r2(&mut self) -> &mut Self {
-        self.all_epochs_r2 = true;
+    /// Otherwise computes them only at the end of the final epoch
+    pub fn all_epochs_metrics(&mut self) -> &mut Self {
+        self.all_epochs_metrics = true;
         self
     }
 
@@ -114,25 +246,75 @@ impl KFolds {
         self
     }
 
-    fn compute_best(&mut self, model_eval: &ModelEvaluation, trained_models: &Vec<Network>) {
+    /// The metric deciding the best fold, `best_metric` or the first metric of the trainer.
+    pub(crate) fn selection_metric(&self, model: &Model) -> Arc<dyn Metric> {
+        let metrics = self.training_options(model).metrics;
+        match &self.best_metric {
+            Some(name) => metrics
+                .into_iter()
+                .find(|m| &m.name() == name)
+                .unwrap_or_else(|| panic!("The best fold metric {} must be one of the trainer's metrics", name)),
+            None => metrics[0].clone(),
+        }
+    }
+
+    // Everything needed to train the i-th fold with the trainer's options
+    pub(crate) fn fold(
+        &self,
+        i: usize,
+        n_folds: usize,
+        model: &Model,
+        train_table: DataTable,
+        validation: DataTable,
+    ) -> Fold {
+        Fold {
+            i,
+            n_folds,
+            options: self.training_options(model),
+            seed: self.seed.or(model.seed),
+            model: model.clone(),
+            train_table,
+            validation,
+            reverter: self.reverter.clone(),
+            callbacks: self.callbacks.clone(),
+            reporter: self.real_time_reporter.clone(),
+            loader: None,
+        }
+    }
+
+    fn compute_best(&mut self, model: &Model, model_eval: &ModelEvaluation, trained_models: &Vec<Network>) {
         if self.return_best {
             TM::start("bestfold");
-            let mut best_fold = 0;
-            let mut best_fold_r2 = 0.0;
+            let metric = self.selection_metric(model);
+            let name = metric.name();
+            let higher_is_better = metric.higher_is_better();
 
+            let mut best_fold = 0;
+            let mut best_fold_value = None;
             for (i, fold) in model_eval.folds.iter().enumerate() {
-                let r2 = fold.get_final_r2();
-                if r2 > best_fold_r2 {
+                let value = fold.get_final_metric(&name).unwrap_or(Scalar::NAN);
+                let is_better = match best_fold_value {
+                    None => !value.is_nan(),
+                    Some(best) => {
+                        if higher_is_better {
+                            value > best
+                        } else {
+                            value < best
+                        }
+                    }
+                };
+                if is_better {
                     best_fold = i;
-                    best_fold_r2 = r2;
+                    best_fold_value = Some(value);
                 }
             }
 
             let best_params = trained_models[best_fold].get_params();
             TM::end_with_message(format!(
-                "Best fold: {} with R2: {} and {} parameters",
+                "Best fold: {} with {}: {:?} and {} parameters",
                 best_fold,
-                best_fold_r2,
+                name,
+                best_fold_value,
                 best_params.count()
             ));
             self.best = Some(best_params);
@@ -151,293 +333,228 @@ impl KFolds {
         }
     }
 
-    #[allow(dead_code)]
-    fn sequential_k_fold(
-        &mut self,
-        i: usize,
-        model: &Model,
-        data: &DataTable,
-        preds_and_ids: &Arc<Mutex<DataTable>>,
-        model_eval: &Arc<Mutex<ModelEvaluation>>,
-        trained_models: &Arc<Mutex<Vec<Network>>>,
-        k: usize,
-    ) {
-        TM::start(format!("{}/{}", i+1, k));
-        TM::start("init");
-        let predicted_features = model.dataset_config.predicted_features_names();
-        let id_column = model.dataset_config
-            .get_id_column()
-            .expect("One feature must be configurationified as an id in the dataset dataset_config.");
-        let mut network = model.to_network();
-
-        // Split the data between validation and training
-        let (train_table, validation) = data.split_k_folds(k, i);
-
-        // Shuffle the validation and training set and split it between x and y
-        let (validation_x_table, validation_y_table) =
-            validation.random_order_in_out(&predicted_features);
-
-        // Convert the validation set to vectors
-        let validation_x = validation_x_table.drop_column(id_column).to_vectors();
-        let validation_y = validation_y_table.to_vectors();
-
-        let mut fold_eval = TrainingEvaluation::new_empty();
-        let epochs = model.epochs;
-
-        TM::end_with_message(format!(
-            "Initialized training with {} samples\nInitialized validation with {} samples",
-            train_table.num_rows(),
-            validation_x_table.num_rows()
-        ));
-
-        TM::start("epochs");
-        for e in 0..epochs {
-            TM::start(&format!("{}/{}", e+1, epochs));
-            // Train the model with the k-th folds except the i-th
-            let train_loss = model.train_epoch(e, &mut network, &train_table, id_column);
-
-            // Predict all values in the i-th fold
-            let loss_fn = model.loss.to_loss();
-            let (preds, loss_avg, loss_std) = if e == model.epochs - 1 || self.all_epochs_validation
-            {
-                let vloss = network.predict_evaluate_many(
-                    &validation_x,
-                    &validation_y,
-                    &loss_fn,
-                    model.batch_size.unwrap_or(validation_x.len()),
-                );
-                vloss
-            } else {
-                (vec![], -1.0, -1.0)
-            };
-
-            // Compute the R2 score	if it is the last epoch
-            // (it would be very costly to do it every time)
-            let r2 = if e == model.epochs - 1 || self.all_epochs_r2 {
-                TM::start("r2");
-                let r2 = r2_score_vector2(&validation_y, &preds);
-                TM::end_with_message(format!("R2: {}", r2));
-                r2
-            } else {
-                -1.0
-            };
-
-            // Build the benchmork of the model for that epoch
-            // Useful for plotting the learning curve
-            let eval = EpochEvaluation::new(train_loss, loss_avg, loss_std, r2);
-
-            // Report the benchmark in real time if expected
-            if let Some(reporter) = self.real_time_reporter.as_ref() {
-                reporter.lock().unwrap()(i, e, eval.clone());
+    fn compute_ensemble_of(&mut self, model: &Model, model_eval: &ModelEvaluation, trained_models: &[Network]) {
+        if let Some((method, score_weighted)) = self.return_ensemble {
+            let members = trained_models.iter().map(|n| n.get_params()).collect::<Vec<_>>();
+            let network = model
+                .network
+                .clone()
+                .expect("The model must have a neural network to compute an ensemble");
+            let in_dims = model.dataset_config.in_features_names().len();
+            let mut ensemble = Ensemble::new(network, in_dims, members).method(method);
+
+            if score_weighted {
+                let metric = self.selection_metric(model);
+                let scores = model_eval
+                    .folds
+                    .iter()
+                    .map(|fold| fold.get_final_metric(&metric.name()).unwrap_or(Scalar::NAN))
+                    .collect::<Vec<_>>();
+                ensemble = ensemble.weights(Ensemble::weights_from_scores(&scores, metric.higher_is_better()));
             }
 
-            // Save the predictions if it is the last epoch
-            if e == model.epochs - 1 {
-                let mut vp = preds_and_ids.lock().unwrap();
-                *vp = vp.apppend(
-                    &DataTable::from_vectors(&predicted_features, &preds)
-                        .add_column_from(&validation_x_table, id_column),
-                );
-            };
-            TM::end_with_message(format!("Training Loss: {}\n ", train_loss));
-
-            fold_eval.add_epoch(eval);
+            self.ensemble = Some(ensemble);
         }
-        TM::end_with_message(format!("Final performance: {:#?}", fold_eval.get_final_epoch()));
+    }
 
-        trained_models.lock().unwrap().push(network);
-        model_eval.lock().unwrap().add_fold(fold_eval);
+    /// Runs the k-fold cross validation
+    ///
+    /// Assumes the data has all the columns corresponding to the model's dataset.
+    ///
+    /// Assumes both the data and the model's dataset include an id feature.
+    /// 
+    pub fn run(&mut self, model: &Model, data: &DataTable) -> (DataTable, ModelEvaluation) {
+        self.run_folds(model, data, None)
     }
 
-    #[allow(dead_code)]
-    fn parallel_k_fold(
+    /// Same as `run` but the training mini-batches of each fold come from the `DataLoader` built by `make_loader`
+    /// from the fold's training rows (see `Model::train_epoch_loader`), for instance `VecLoader::from_table`.
+    ///
+    /// The validation fold is evaluated as in `run`.
+    pub fn run_loader<L, F>(&mut self, model: &Model, data: &DataTable, make_loader: F) -> (DataTable, ModelEvaluation)
+    where
+        L: DataLoader + Send + 'static,
+        F: Fn(&DataTable) -> L,
+    {
+        self.run_folds(model, data, Some(&|table: &DataTable| -> Box<dyn DataLoader + Send> {
+            Box::new(make_loader(table))
+        }))
+    }
+
+    fn run_folds(
         &mut self,
-        i: usize,
         model: &Model,
         data: &DataTable,
-        preds_and_ids: &Arc<Mutex<DataTable>>,
-        model_eval: &Arc<Mutex<ModelEvaluation>>,
-        trained_models: &Arc<Mutex<Vec<Network>>>,
-        k: usize,
-    ) -> thread::JoinHandle<()> {
-        TM::start("parr");
-        TM::start("init");
-        let i = i.clone();
-        let model = model.clone();
-        let data = data.clone();
-        let preds_and_ids = preds_and_ids.clone();
-        let model_eval = model_eval.clone();
-        let all_epochs_r2 = self.all_epochs_r2;
-        let all_epochs_validation = self.all_epochs_validation;
-        let reporter = self.real_time_reporter.clone();
-        let trained_models = trained_models.clone();
+        make_loader: Option<&LoaderFactory>,
+    ) -> (DataTable, ModelEvaluation) {
+        assert!(!self.all_epochs_metrics || self.all_epochs_validation);
 
-        TM::end_with_message(format!(
-            "Will train {} networks with each:\n{} training samples\n{} validation samples",
-            k,
-            data.num_rows() - data.num_rows() / k,
-            data.num_rows() / k
-        ));
-
-        let handle = thread::spawn(move || {
-            TM::start(&format!("parrfolds[{}]", i));
-            TM::start("init");
-            let predicted_features = model.dataset_config.predicted_features_names();
-            let id_column = model.dataset_config.get_id_column().unwrap();
-            let mut network = model.to_network();
-
-            // Split the data between validation and training
-            let (train_table, validation) = data.split_k_folds(k, i);
-
-            // Shuffle the validation and training set and split it between x and y
-            let (validation_x_table, validation_y_table) =
-                validation.random_order_in_out(&predicted_features);
-
-            // Convert the validation set to vectors
-            let validation_x = validation_x_table.drop_column(id_column).to_vectors();
-            let validation_y = validation_y_table.to_vectors();
-
-            TM::end();
-
-            TM::start("epochs");
-            let mut fold_eval = TrainingEvaluation::new_empty();
-            let epochs = model.epochs;
-            for e in 0..epochs {
-                TM::start(&format!("{}/{}", e+1, epochs));
-                // Train the model with the k-th folds except the i-th
-                let train_loss = model.train_epoch(e, &mut network, &train_table, id_column);
-
-                // Predict all values in the i-th fold
-                // It is costly and should be done only during the last epoch
-                // and made optional for all the others in the future
-                let loss_fn = model.loss.to_loss();
-                let (preds, loss_avg, loss_std) = if e == model.epochs - 1 || all_epochs_validation
-                {
-                    let vloss = network.predict_evaluate_many(
-                        &validation_x,
-                        &validation_y,
-                        &loss_fn,
-                        model.batch_size.unwrap_or(validation_x.len()),
-                    );
-                    vloss
-                } else {
-                    (vec![], -1.0, -1.0)
-                };
+        TM::start("kfolds");
 
-                // Compute the R2 score	if it is the last epoch
-                // (it would be very costly to do it every time)
-                let r2 = if e == model.epochs - 1 || (all_epochs_r2 && all_epochs_validation) {
-                    TM::start("r2");
-                    let r2 = r2_score_vector2(&validation_y, &preds);
-                    TM::end_with_message(format!("R2: {}", r2));
-                    r2
-                } else {
-                    -1.0
-                };
+        // The folds are drawn once for all, so that they don't depend on the threads
+        if let Some(seed) = self.seed.or(model.seed) {
+            set_seed(seed);
+        }
+        let folds = self.cross_validation.folds(model, data);
+        let n_folds = folds.len();
 
-                // Build the benchmork of the model for that epoch
-                // Useful for plotting the learning curve
-                let eval = EpochEvaluation::new(train_loss, loss_avg, loss_std, r2);
+        // The results are gathered in the order of the folds, whatever the order the threads end in
+        let mut results = Vec::with_capacity(n_folds);
+        let mut handles = Vec::new();
 
-                // Report the benchmark in real time if expected
-                if let Some(reporter) = reporter.as_ref() {
-                    reporter.lock().unwrap()(i, e, eval.clone());
+        TM::start("folds");
+        for (i, (train_rows, validation_rows)) in folds.into_iter().enumerate() {
+            let fold = self.fold(
+                i,
+                n_folds,
+                model,
+                data.take_rows(&train_rows),
+                data.take_rows(&validation_rows),
+            );
+            let fold = match make_loader {
+                Some(make_loader) => {
+                    let loader = make_loader(&fold.train_table);
+                    fold.with_loader(loader)
                 }
+                None => fold,
+            };
 
-                // Save the predictions if it is the last epoch
-                if e == model.epochs - 1 {
-                    let mut vp = preds_and_ids.lock().unwrap();
-                    *vp = vp.apppend(
-                        &DataTable::from_vectors(&predicted_features, &preds)
-                            .add_column_from(&validation_x_table, id_column),
-                    );
-                };
+            if Matrix::is_backend_thread_safe() {
+                handles.push(thread::spawn(move || fold.train()));
+            } else {
+                results.push(fold.train());
+            }
+        }
+        TM::end();
 
-                TM::end_with_message(format!("Training Loss: {}\n ", train_loss));
+        results.extend(handles.into_iter().map(|handle| handle.join().unwrap()));
 
-                fold_eval.add_epoch(eval);
-            }
-            TM::end_with_message(format!(
-                "Final performance: {:#?}",
-                fold_eval.get_final_epoch()
-            ));
+        // The networks and the evaluations are kept in the same order
+        let mut preds_and_ids = DataTable::new_empty();
+        let mut model_eval = ModelEvaluation::new_empty();
+        let mut trained_models = Vec::with_capacity(n_folds);
+        for (preds, eval, network) in results {
+            preds_and_ids = preds_and_ids.apppend(&preds);
+            model_eval.add_fold(eval);
+            trained_models.push(network);
+        }
 
-            trained_models.lock().unwrap().push(network);
-            model_eval.lock().unwrap().add_fold(fold_eval);
-        });
+        // Compute the best and average models
+        // and store them internally if necessary
+        self.compute_best(model, &model_eval, &trained_models);
+        self.compute_avg(&trained_models);
+        self.compute_ensemble_of(model, &model_eval, &trained_models);
 
         TM::end();
 
-        handle
+        (preds_and_ids, model_eval)
     }
+}
 
-    /// Runs the k-fold cross validation
-    ///
-    /// Assumes the data has all the columns corresponding to the model's dataset.
-    ///
-    /// Assumes both the data and the model's dataset include an id feature.
-    /// 
-    pub fn run(&mut self, model: &Model, data: &DataTable) -> (DataTable, ModelEvaluation) {
-        assert!(!self.all_epochs_r2 || self.all_epochs_validation);
+impl Trainer for KFolds {
+    fn run(&mut self, model: &Model, data: &DataTable) -> (DataTable, ModelEvaluation) {
+        KFolds::run(self, model, data)
+    }
 
-        TM::start("kfolds");
+    fn training_options(&self, model: &Model) -> TrainingOptions {
+        TrainingOptions {
+            all_epochs_validation: self.all_epochs_validation,
+            all_epochs_metrics: self.all_epochs_metrics,
+            metrics: if self.metrics.is_empty() {
+                default_metrics(&model.loss)
+            } else {
+                self.metrics.clone()
+            },
+            params_averaging: self.params_averaging,
+            classification_top_k: self.classification_top_k,
+        }
+    }
+}
 
-        // Init the data structures for parallel computing
-        let preds_and_ids = Arc::new(Mutex::new(DataTable::new_empty()));
-        let model_eval = Arc::new(Mutex::new(ModelEvaluation::new_empty()));
-        let trained_models = Arc::new(Mutex::new(Vec::new()));
-        let mut handles = Vec::new();
-        let k = self.k;
+// Everything needed to train a fold, possibly on its own thread
+pub(crate) struct Fold {
+    i: usize,
+    n_folds: usize,
+    options: TrainingOptions,
+    seed: Option<u64>,
+    model: Model,
+    train_table: DataTable,
+    validation: DataTable,
+    reverter: Option<Arc<Reverter>>,
+    callbacks: Vec<Arc<CallbackFactory>>,
+    reporter: Arc<Option<Mutex<Box<ReporterClosure>>>>,
+    // Source of the training mini-batches instead of the training table, if any
+    loader: Option<Box<dyn DataLoader + Send>>,
+}
 
-        TM::start("folds");
-        for i in 0..k {
-            if Matrix::is_backend_thread_safe() {
-                let handle = self.parallel_k_fold(
-                    i,
-                    model,
-                    data,
-                    &preds_and_ids,
-                    &model_eval,
-                    &trained_models,
-                    k,
-                );
-                handles.push(handle);
-            } else {
-                self.sequential_k_fold(
-                    i,
-                    model,
-                    data,
-                    &preds_and_ids,
-                    &model_eval,
-                    &trained_models,
-                    k,
-                );
-            }
+impl Fold {
+    pub(crate) fn with_loader(self, loader: Box<dyn DataLoader + Send>) -> Self {
+        Self {
+            loader: Some(loader),
+            ..self
         }
-        TM::end();
+    }
+
+    // Returns the predictions with the ids, the evaluation and the network of the fold
+    pub(crate) fn train(mut self) -> (DataTable, TrainingEvaluation, Network) {
+        let (i, model) = (self.i, &self.model);
+        let mut loader = self.loader.take();
 
-        for handle in handles.into_iter() {
-            handle.join().unwrap();
+        TM::start(format!("{}/{}", i + 1, self.n_folds));
+        TM::start("init");
+        if let Some(seed) = self.seed {
+            set_seed(derive_seed(seed, i as u64));
         }
+        let predicted_features = model.dataset_config.predicted_features_names();
+        let id_column = model
+            .dataset_config
+            .get_id_column()
+            .expect("One feature must be configurationified as an id in the dataset dataset_config.");
+        let mut network = model.to_network();
 
-        // Destroy the datastructures for parallel computing
-        let preds_and_ids = Arc::try_unwrap(preds_and_ids)
-            .unwrap()
-            .into_inner()
-            .unwrap();
-        let model_eval = Arc::try_unwrap(model_eval).unwrap().into_inner().unwrap();
-        let trained_models = Arc::try_unwrap(trained_models)
-            .unwrap()
-            .into_inner()
-            .unwrap();
+        // Shuffle the validation set and split it between x and y
+        let (validation_x_table, validation_y_table) =
+            self.validation.random_order_in_out(&predicted_features);
 
-        // Compute the best and average models
-        // and store them internally if necessary
-        self.compute_best(&model_eval, &trained_models);
-        self.compute_avg(&trained_models);
+        // Convert the validation set to vectors
+        let validation_x = model.inputs_to_vectors(&validation_x_table, id_column);
+        let validation_y = validation_y_table.to_vectors_with_missing();
+        let validation_weights = model.samples_weights(&validation_x_table, &validation_y);
+        let validation_set = ValidationSet::new(validation_x, validation_y, validation_weights)
+            .with_reverter(self.reverter.clone(), &predicted_features);
+
+        TM::end_with_message(format!(
+            "Initialized training with {} samples\nInitialized validation with {} samples",
+            self.train_table.num_rows(),
+            validation_x_table.num_rows()
+        ));
+
+        let mut callbacks = Callbacks(self.callbacks.iter().map(|make| make(i)).collect());
+        let (preds, eval) = train_network(
+            &self.options,
+            model,
+            &mut network,
+            // Train the model with the k-th folds except the i-th
+            &mut |e, network, on_batch_end| match loader.as_mut() {
+                Some(loader) => model.train_epoch_loader(e, network, loader.as_mut(), on_batch_end),
+                None => model.train_epoch_with_hook(e, network, &self.train_table, id_column, on_batch_end),
+            },
+            &validation_set,
+            &mut callbacks,
+            // Report the benchmark in real time if expected
+            &mut |e, eval| {
+                if let Some(reporter) = self.reporter.as_ref() {
+                    reporter.lock().unwrap()(i, e, eval.clone());
+                }
+            },
+        );
+
+        let preds_and_ids =
+            DataTable::from_vectors(&model.preds_columns_names(), &model.outputs_to_preds(&preds))
+                .add_column_from(&validation_x_table, id_column);
 
         TM::end();
 
-        (preds_and_ids, model_eval)
+        (preds_and_ids, eval, network)
     }
 }
@@ -0,0 +1,224 @@
+use crate::{
+    benchmarking::LearningRateFinderReport,
+    datatable::DataTable,
+    learning_rate::{exponential_decay::ExponentialDecay, LearningRateSchedule},
+    linalg::Scalar,
+    model::Model,
+    monitor::TM,
+    random::set_seed,
+};
+
+/// Learning rate range test (aka. "LR finder")
+///
+/// Trains a fresh network built from the model for a few mini-batches while exponentially
+/// increasing the learning rate of all its optimizers, from `min_learning_rate` to `max_learning_rate`.
+///
+/// The loss is recorded at each step and the test stops early once the loss diverges.
+///
+/// The suggested learning rate is the one at which the smoothed loss decreases the fastest.
+///
+/// Resources: https://arxiv.org/pdf/1506.01186.pdf
+pub struct LearningRateFinder {
+    pub min_learning_rate: Scalar,
+    pub max_learning_rate: Scalar,
+    pub steps: usize,
+    pub smoothing: Scalar,
+    pub divergence_threshold: Scalar,
+    pub seed: Option<u64>,
+}
+
+impl Default for LearningRateFinder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LearningRateFinder {
+    pub fn new() -> Self {
+        Self {
+            min_learning_rate: 1e-7,
+            max_learning_rate: 10.,
+            steps: 100,
+            smoothing: 0.98,
+            divergence_threshold: 4.,
+            seed: None,
+        }
+    }
+
+    /// Sets the range of learning rates that will be explored
+    pub fn learning_rate_range(&mut self, min: Scalar, max: Scalar) -> &mut Self {
+        assert!(min > 0. && min < max);
+        self.min_learning_rate = min;
+        self.max_learning_rate = max;
+        self
+    }
+
+    /// Sets the number of mini-batches the network is trained on (one learning rate per mini-batch)
+    pub fn steps(&mut self, steps: usize) -> &mut Self {
+        self.steps = steps;
+        self
+    }
+
+    /// Sets the exponential moving average factor used to smooth the recorded losses
+    pub fn smoothing(&mut self, smoothing: Scalar) -> &mut Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Stops the test as soon as the smoothed loss exceeds `threshold` times the best smoothed loss
+    pub fn divergence_threshold(&mut self, threshold: Scalar) -> &mut Self {
+        self.divergence_threshold = threshold;
+        self
+    }
+
+    /// Seeds every random operation of the test, overriding the model's seed if any.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    fn learning_rate_at(&self, step: usize) -> Scalar {
+        self.schedule().get_learning_rate(step)
+    }
+
+    fn schedule(&self) -> LearningRateSchedule {
+        LearningRateSchedule::ExponentialDecay(ExponentialDecay::new(
+            self.min_learning_rate,
+            self.steps as Scalar,
+            self.max_learning_rate / self.min_learning_rate,
+            false,
+        ))
+    }
+
+    /// Picks the learning rate at the steepest descent of the smoothed loss
+    /// against the logarithm of the learning rate.
+    ///
+    /// Falls back to a tenth of the learning rate reaching the lowest loss if there are too few steps.
+    fn suggest(learning_rates: &[Scalar], smoothed_losses: &[Scalar]) -> Scalar {
+        let n = learning_rates.len();
+        if n < 3 {
+            let mut best = 0;
+            for i in 0..n {
+                if smoothed_losses[i] < smoothed_losses[best] {
+                    best = i;
+                }
+            }
+            return learning_rates[best] / 10.;
+        }
+
+        let mut steepest = 1;
+        let mut steepest_slope = Scalar::MAX;
+        for i in 1..n - 1 {
+            let slope = (smoothed_losses[i + 1] - smoothed_losses[i - 1])
+                / (learning_rates[i + 1].log10() - learning_rates[i - 1].log10());
+            if slope < steepest_slope {
+                steepest = i;
+                steepest_slope = slope;
+            }
+        }
+        learning_rates[steepest]
+    }
+
+    /// Runs the learning rate range test
+    ///
+    /// Assumes the data has all the columns corresponding to the model's dataset.
+    ///
+    /// Assumes both the data and the model's dataset include an id feature.
+    pub fn run(&mut self, model: &Model, data: &DataTable) -> LearningRateFinderReport {
+        TM::start("lrfinder");
+
+        TM::start("init");
+        if let Some(seed) = self.seed.or(model.seed) {
+            set_seed(seed);
+        }
+        let predicted_features = model.dataset_config.predicted_features_names();
+        let id_column = model
+            .dataset_config
+            .get_id_column()
+            .expect("One feature must be configurationified as an id in the dataset dataset_config.");
+
+        let mut model = model.clone();
+        model
+            .network
+            .as_mut()
+            .expect("You cannot find a learning rate if the network is not configurationified")
+            .set_learning_rate(self.schedule());
+
+        let mut network = model.to_network();
+        let loss_fn = model.loss.to_loss();
+        let batch_size = model.batch_size.unwrap_or(data.num_rows());
+        TM::end();
+
+        let mut learning_rates = vec![];
+        let mut losses = vec![];
+        let mut smoothed_losses = vec![];
+        let mut avg_loss = 0.;
+        let mut best_loss = Scalar::MAX;
+        let mut diverged_at = None;
+        let mut batches = vec![];
+
+        TM::start("steps");
+        for step in 0..self.steps {
+            // Start a new shuffled epoch whenever all the batches have been consumed
+            if batches.is_empty() {
+                let (x_table, y_table) = data.random_order_in_out(&predicted_features);
+                let x = model.inputs_to_vectors(&x_table, id_column);
+                let y = y_table.to_vectors_with_missing();
+                let weights = model.samples_weights(&x_table, &y);
+                batches = (0..x.len())
+                    .step_by(batch_size)
+                    .map(|i| {
+                        let end = (i + batch_size).min(x.len());
+                        let weights = weights.as_ref().map(|w| w[i..end].to_vec());
+                        (x[i..end].to_vec(), y[i..end].to_vec(), weights)
+                    })
+                    .rev()
+                    .collect();
+            }
+            let (x_batch, y_batch, weights_batch) = batches.pop().unwrap();
+
+            // The step is used as the "epoch" so that the schedule grows at every mini-batch
+            let loss = network.train_weighted(
+                step,
+                &x_batch,
+                &y_batch,
+                weights_batch.as_deref(),
+                &loss_fn,
+                batch_size,
+            );
+
+            avg_loss = self.smoothing * avg_loss + (1. - self.smoothing) * loss;
+            let smoothed_loss = avg_loss / (1. - self.smoothing.powi(step as i32 + 1));
+
+            learning_rates.push(self.learning_rate_at(step));
+            losses.push(loss);
+            smoothed_losses.push(smoothed_loss);
+
+            if smoothed_loss.is_nan() || smoothed_loss > self.divergence_threshold * best_loss {
+                diverged_at = Some(step);
+                break;
+            }
+            if smoothed_loss < best_loss {
+                best_loss = smoothed_loss;
+            }
+        }
+        match diverged_at {
+            Some(step) => TM::end_with_message(format!("Loss diverged at step {}", step)),
+            None => TM::end(),
+        }
+
+        let suggested_learning_rate = Self::suggest(&learning_rates, &smoothed_losses);
+
+        TM::end_with_message(format!(
+            "Suggested learning rate: {}",
+            suggested_learning_rate
+        ));
+
+        LearningRateFinderReport::new(
+            learning_rates,
+            losses,
+            smoothed_losses,
+            suggested_learning_rate,
+        )
+    }
+}
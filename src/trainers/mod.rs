@@ -1,4 +1,256 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    benchmarking::{ClassificationMetrics, EpochEvaluation, TrainingEvaluation},
+    linalg::Scalar,
+    metrics::{compute_metrics, Metric, R2},
+    model::Model,
+    monitor::TM,
+    network::{
+        params_averaging::{ParamsAverager, ParamsAveraging},
+        Network,
+    },
+};
+
+#[cfg(feature = "data")]
+use crate::{benchmarking::ModelEvaluation, datatable::DataTable, preprocessing::Reverter};
+
+use self::callbacks::Callbacks;
+
+pub mod callbacks;
+#[cfg(feature = "data")]
+pub mod cross_validation;
+#[cfg(feature = "data")]
+pub mod distributed;
 #[cfg(feature = "data")]
 pub mod kfolds;
+#[cfg(feature = "data")]
+pub mod lr_finder;
+#[cfg(feature = "data")]
+pub mod nested;
+#[cfg(feature = "data")]
+pub mod search;
+
+pub mod split;
+
+/// Common interface of the trainers, which all train their networks with the same epoch loop.
+pub trait Trainer {
+    /// Trains and validates the model on the data,
+    /// returning the validation predictions (with the ids) and the evaluation of each trained network.
+    #[cfg(feature = "data")]
+    fn run(&mut self, model: &Model, data: &DataTable) -> (DataTable, ModelEvaluation);
+
+    /// Options of the epoch loop the trainer uses for the model.
+    fn training_options(&self, model: &Model) -> TrainingOptions;
+}
+
+/// Options of the epoch loop shared by the trainers.
+#[derive(Clone)]
+pub struct TrainingOptions {
+    pub all_epochs_validation: bool,
+    pub all_epochs_metrics: bool,
+    pub metrics: Vec<Arc<dyn Metric>>,
+    pub params_averaging: Option<ParamsAveraging>,
+    pub classification_top_k: usize,
+}
+
+/// The validation set of a trained network.
+pub struct ValidationSet {
+    pub x: Vec<Vec<Scalar>>,
+    pub y: Vec<Vec<Scalar>>,
+    pub weights: Option<Vec<Scalar>>,
+    // The targets and the function reverting the point predictions into the metrics' units
+    metrics_y: Vec<Vec<Scalar>>,
+    #[cfg(feature = "data")]
+    reverter: Option<(Arc<Reverter>, Vec<String>)>,
+}
+
+impl ValidationSet {
+    pub fn new(x: Vec<Vec<Scalar>>, y: Vec<Vec<Scalar>>, weights: Option<Vec<Scalar>>) -> Self {
+        Self {
+            metrics_y: y.clone(),
+            x,
+            y,
+            weights,
+            #[cfg(feature = "data")]
+            reverter: None,
+        }
+    }
+
+    /// Computes the metrics in the original units of the targets `columns` (see `Pipeline::reverter`).
+    #[cfg(feature = "data")]
+    pub fn with_reverter<S: AsRef<str>>(self, reverter: Option<Arc<Reverter>>, columns: &[S]) -> Self {
+        match reverter {
+            Some(reverter) => {
+                let columns = columns.iter().map(|c| c.as_ref().to_string()).collect::<Vec<_>>();
+                Self {
+                    metrics_y: revert_vectors(reverter.as_ref(), &columns, &self.y),
+                    reverter: Some((reverter, columns)),
+                    ..self
+                }
+            }
+            None => self,
+        }
+    }
+
+    fn metrics_preds(&self, point_preds: Vec<Vec<Scalar>>) -> Vec<Vec<Scalar>> {
+        #[cfg(feature = "data")]
+        if let Some((reverter, columns)) = &self.reverter {
+            return revert_vectors(reverter.as_ref(), columns, &point_preds);
+        }
+        point_preds
+    }
+}
+
+/// Called after each mini-batch with its index and loss, returns whether to stop the epoch.
+pub type BatchHook<'a> = dyn FnMut(usize, Scalar, &Network) -> bool + 'a;
+
+/// Trains a network for the model's epochs and validates it, calling the callbacks and the reporter along the way.
+///
+/// `train_epoch` trains the network for an epoch, calling the given hook after each mini-batch
+/// (see `Model::train_epoch_with_hook`), and returns the training loss.
+///
+/// Returns the outputs of the final network on the validation set and the evaluation of each epoch.
+pub fn train_network(
+    options: &TrainingOptions,
+    model: &Model,
+    network: &mut Network,
+    train_epoch: &mut dyn FnMut(usize, &mut Network, &mut BatchHook) -> Scalar,
+    validation: &ValidationSet,
+    callbacks: &mut Callbacks,
+    report: &mut dyn FnMut(usize, &EpochEvaluation),
+) -> (Vec<Vec<Scalar>>, TrainingEvaluation) {
+    let mut averager = options.params_averaging.map(ParamsAverager::new);
+    let loss_fn = model.loss.to_loss();
+
+    // Predicts and evaluates the validation set, computing the metrics only if `with_metrics`
+    let validate = |network: &mut Network, train_loss: Scalar, with_metrics: bool| {
+        let (preds, loss_avg, loss_std) = network.predict_evaluate_many_weighted(
+            &validation.x,
+            &validation.y,
+            validation.weights.as_deref(),
+            &loss_fn,
+            model.batch_size.unwrap_or(validation.x.len()),
+        );
 
-pub mod split;
\ No newline at end of file
+        // The R2 score is also kept in `EpochEvaluation::r2`, computed if it isn't one of the metrics
+        let (metrics_values, r2) = if with_metrics {
+            TM::start("metrics");
+            let point_preds = validation.metrics_preds(loss_fn.point_predictions_vec(&preds));
+            let weights = validation.weights.as_deref();
+            let values = compute_metrics(&options.metrics, &validation.metrics_y, &point_preds, weights);
+            let r2 = values
+                .get("r2")
+                .copied()
+                .unwrap_or_else(|| R2.compute(&validation.metrics_y, &point_preds, weights));
+            TM::end_with_message(format!("Metrics: {:?}", values));
+            (values, r2)
+        } else {
+            (BTreeMap::new(), -1.0)
+        };
+
+        // Build the benchmark of the model for that epoch
+        // Useful for plotting the learning curve
+        let targets_losses = loss_fn.targets_losses_vec(&validation.y, &preds);
+
+        let mut eval = EpochEvaluation::new(train_loss, loss_avg, loss_std, r2)
+            .with_test_targets_losses(targets_losses)
+            .with_metrics(metrics_values);
+        if model.loss.is_classification() {
+            if let Some(classification) = ClassificationMetrics::compute(
+                &validation.y,
+                &preds,
+                validation.weights.as_deref(),
+                options.classification_top_k,
+            ) {
+                eval = eval.with_classification(classification);
+            }
+        }
+
+        (preds, eval)
+    };
+
+    TM::start("epochs");
+
+    let mut training_eval = TrainingEvaluation::new_empty();
+    let mut final_preds = None;
+    let epochs = model.epochs;
+    for e in 0..epochs {
+        TM::start(format!("{}/{}", e + 1, epochs));
+        callbacks.on_epoch_start(e, network);
+
+        let mut stop = false;
+        let train_loss = train_epoch(e, network, &mut |batch, loss, network| {
+            stop |= callbacks.on_batch_end(e, batch, loss, network);
+            stop
+        });
+
+        // Accumulate the parameters and validate the averaged ones at the end of the training
+        if let Some(averager) = averager.as_mut() {
+            averager.update(e, epochs, network.get_params());
+            if e == epochs - 1 {
+                network.load_params(averager.get_params().unwrap());
+            }
+        }
+
+        // Validate and compute the metrics if it is the last epoch
+        // (it would be very costly to do it every time)
+        let (preds, eval) = if e == epochs - 1 || options.all_epochs_validation {
+            validate(network, train_loss, e == epochs - 1 || options.all_epochs_metrics)
+        } else {
+            (vec![], EpochEvaluation::new(train_loss, -1.0, -1.0, -1.0))
+        };
+
+        // Report the benchmark in real time if expected
+        report(e, &eval);
+
+        stop |= callbacks.on_epoch_end(e, &eval, network);
+
+        if e == epochs - 1 {
+            final_preds = Some(preds);
+        }
+
+        TM::end_with_message(format!("Training Loss: {}\n ", train_loss));
+
+        training_eval.add_epoch(eval);
+
+        if stop {
+            break;
+        }
+    }
+
+    // The averaged parameters are loaded at the last epoch, or here if the training stopped early
+    if final_preds.is_none() {
+        if let Some(params) = averager.as_ref().and_then(|averager| averager.get_params()) {
+            network.load_params(params);
+        }
+    }
+
+    // Validate again if the training stopped early or if the callbacks changed the parameters
+    let changed = callbacks.on_train_end(network);
+    let preds = match final_preds {
+        Some(preds) if !changed => preds,
+        _ => {
+            let train_loss = training_eval.get_final_epoch().train_loss;
+            let (preds, final_eval) = validate(network, train_loss, true);
+            training_eval.replace_final_epoch(final_eval);
+            preds
+        }
+    };
+
+    TM::end_with_message(format!("Final performance: {:#?}", training_eval.get_final_epoch()));
+
+    (preds, training_eval)
+}
+
+/// Reverts the samples' values of the given columns into their original units.
+#[cfg(feature = "data")]
+pub(crate) fn revert_vectors<S: AsRef<str>>(
+    reverter: &Reverter,
+    columns: &[S],
+    vectors: &Vec<Vec<Scalar>>,
+) -> Vec<Vec<Scalar>> {
+    reverter(&DataTable::from_vectors(columns, vectors))
+        .select_columns(columns)
+        .to_vectors_with_missing()
+}
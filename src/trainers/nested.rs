@@ -0,0 +1,140 @@
+use crate::{
+    benchmarking::{ModelEvaluation, SelectedCandidate},
+    datatable::DataTable,
+    linalg::Scalar,
+    model::Model,
+    monitor::TM,
+    random::set_seed,
+    trainers::{cross_validation::CrossValidation, kfolds::KFolds},
+};
+
+/// Nested cross validation trainer
+///
+/// Estimates the performance of a model selection procedure without the optimistic bias
+/// of reporting the scores of the folds used for the selection.
+///
+/// For each fold of the `outer` cross validation, each candidate is evaluated by the `inner`
+/// trainer on the outer training set only. The candidate with the best average of the inner
+/// trainer's selection metric (see `KFolds::best_metric`) is then trained on the whole outer
+/// training set and validated on the outer validation set.
+///
+/// The outer folds are run one after the other, the inner trainer parallelizing its own folds.
+pub struct NestedCrossValidation {
+    pub outer: CrossValidation,
+    pub inner: KFolds,
+    pub candidates: Vec<Model>,
+    pub seed: Option<u64>,
+}
+
+impl NestedCrossValidation {
+    /// Creates a nested cross validation of the candidates, configured by the `inner` trainer
+    /// (metrics, selection metric, callbacks...) which is also used to train the selected candidates.
+    pub fn new(outer: CrossValidation, inner: KFolds, candidates: Vec<Model>) -> Self {
+        assert!(!candidates.is_empty(), "A nested cross validation requires candidates");
+        Self {
+            outer,
+            inner,
+            candidates,
+            seed: None,
+        }
+    }
+
+    /// Seeds the outer splits, overriding the first candidate's seed if any.
+    ///
+    /// The inner trainings are seeded by the inner trainer or the candidates.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Runs the nested cross validation
+    ///
+    /// Returns the outer validation predictions of the selected candidates (with the ids) and their evaluations,
+    /// along with the selected candidate of each outer fold (see `ModelEvaluation::selected_candidates`).
+    ///
+    /// Assumes the candidates share the same dataset configuration, which includes an id feature.
+    pub fn run(&mut self, data: &DataTable) -> (DataTable, ModelEvaluation) {
+        TM::start("nested");
+
+        if let Some(seed) = self.seed.or(self.candidates[0].seed) {
+            set_seed(seed);
+        }
+        let folds = self.outer.folds(&self.candidates[0], data);
+        let n_folds = folds.len();
+
+        let mut preds_and_ids = DataTable::new_empty();
+        let mut model_eval = ModelEvaluation::new_empty();
+
+        for (i, (train_rows, validation_rows)) in folds.into_iter().enumerate() {
+            TM::start(format!("outer {}/{}", i + 1, n_folds));
+            let train_table = data.take_rows(&train_rows);
+
+            let selected = self.select_candidate(&train_table);
+            let model = &self.candidates[selected.candidate];
+
+            let fold = self.inner.fold(
+                i,
+                n_folds,
+                model,
+                train_table,
+                data.take_rows(&validation_rows),
+            );
+            let (preds, eval, _) = fold.train();
+
+            preds_and_ids = preds_and_ids.apppend(&preds);
+            model_eval.add_selected_fold(eval, selected);
+
+            TM::end();
+        }
+
+        TM::end();
+
+        (preds_and_ids, model_eval)
+    }
+
+    // Evaluates every candidate with the inner trainer and returns the best one
+    fn select_candidate(&mut self, train_table: &DataTable) -> SelectedCandidate {
+        TM::start("selection");
+
+        let mut selected: Option<SelectedCandidate> = None;
+        for (c, candidate) in self.candidates.iter().enumerate() {
+            let metric = self.inner.selection_metric(candidate);
+            let (_, eval) = self.inner.run(candidate, train_table);
+
+            // Folds whose metric couldn't be computed are ignored
+            let values = eval
+                .folds
+                .iter()
+                .filter_map(|fold| fold.get_final_metric(&metric.name()))
+                .filter(|value| !value.is_nan())
+                .collect::<Vec<_>>();
+            let score = if values.is_empty() {
+                Scalar::NAN
+            } else {
+                values.iter().sum::<Scalar>() / values.len() as Scalar
+            };
+
+            let is_better = match selected.as_ref() {
+                None => true,
+                Some(best) if best.inner_score.is_nan() => !score.is_nan(),
+                Some(best) if metric.higher_is_better() => score > best.inner_score,
+                Some(best) => score < best.inner_score,
+            };
+            if is_better {
+                selected = Some(SelectedCandidate {
+                    candidate: c,
+                    model_hash: candidate.hashed_repr(),
+                    metric: metric.name(),
+                    inner_score: score,
+                });
+            }
+        }
+
+        let selected = selected.unwrap();
+        TM::end_with_message(format!(
+            "Selected candidate {} with {}: {}",
+            selected.candidate, selected.metric, selected.inner_score
+        ));
+        selected
+    }
+}
@@ -0,0 +1,581 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    thread,
+};
+
+use polars::prelude::*;
+use rand::seq::SliceRandom;
+
+use crate::{
+    activation::Activation,
+    datatable::DataTable,
+    layer::defaults::default_weights_initializer_for,
+    learning_rate::LearningRateSchedule,
+    linalg::{Matrix, MatrixTrait, Scalar},
+    model::{network_model::NetworkLayerModels, Model},
+    monitor::TM,
+    optimizer::Optimizers,
+    random::{set_seed, with_rng},
+    trainers::Trainer,
+};
+
+/// Values tried for each hyperparameter of a `HyperparameterSearch`.
+///
+/// A hyperparameter without values keeps the base model's value.
+#[derive(Clone, Debug, Default)]
+pub struct SearchSpace {
+    /// Sizes of the hidden full dense layers (the full dense layers before the output one)
+    pub hidden_layers: Vec<Vec<usize>>,
+    /// Activation of the hidden full dense layers
+    pub activations: Vec<Activation>,
+    /// Optimizer of the weights and biases of every full dense layer
+    pub optimizers: Vec<Optimizers>,
+    /// Constant learning rate of every optimizer of the network
+    pub learning_rates: Vec<Scalar>,
+    /// Dropout of the hidden full dense layers
+    pub dropouts: Vec<Option<Scalar>>,
+    pub batch_sizes: Vec<usize>,
+    pub epochs: Vec<usize>,
+}
+
+impl SearchSpace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tries each list of hidden layers' sizes, the first hidden layer of the base model
+    /// being the template of the new ones.
+    pub fn hidden_layers(self, hidden_layers: Vec<Vec<usize>>) -> Self {
+        Self { hidden_layers, ..self }
+    }
+
+    /// Tries each activation on the hidden layers, along with its default weights initializer.
+    pub fn activations(self, activations: Vec<Activation>) -> Self {
+        Self { activations, ..self }
+    }
+
+    pub fn optimizers(self, optimizers: Vec<Optimizers>) -> Self {
+        Self { optimizers, ..self }
+    }
+
+    pub fn learning_rates(self, learning_rates: Vec<Scalar>) -> Self {
+        Self { learning_rates, ..self }
+    }
+
+    pub fn dropouts(self, dropouts: Vec<Option<Scalar>>) -> Self {
+        Self { dropouts, ..self }
+    }
+
+    pub fn batch_sizes(self, batch_sizes: Vec<usize>) -> Self {
+        Self { batch_sizes, ..self }
+    }
+
+    pub fn epochs(self, epochs: Vec<usize>) -> Self {
+        Self { epochs, ..self }
+    }
+
+    /// Every combination of the values of the hyperparameters.
+    pub fn grid(&self) -> Vec<Hyperparameters> {
+        let mut grid = vec![Hyperparameters::default()];
+        grid = expand(grid, &self.hidden_layers, |h, v| h.hidden_layers = Some(v.clone()));
+        grid = expand(grid, &self.activations, |h, v| h.activation = Some(*v));
+        grid = expand(grid, &self.optimizers, |h, v| h.optimizer = Some(v.clone()));
+        grid = expand(grid, &self.learning_rates, |h, v| h.learning_rate = Some(*v));
+        grid = expand(grid, &self.dropouts, |h, v| h.dropout = Some(*v));
+        grid = expand(grid, &self.batch_sizes, |h, v| h.batch_size = Some(*v));
+        expand(grid, &self.epochs, |h, v| h.epochs = Some(*v))
+    }
+
+    /// Number of combinations of the grid, without building it.
+    pub fn size(&self) -> usize {
+        [
+            self.hidden_layers.len(),
+            self.activations.len(),
+            self.optimizers.len(),
+            self.learning_rates.len(),
+            self.dropouts.len(),
+            self.batch_sizes.len(),
+            self.epochs.len(),
+        ]
+        .iter()
+        .filter(|len| **len > 0)
+        .fold(1, |size, len| size.saturating_mul(*len))
+    }
+
+    /// A combination of values drawn uniformly for each hyperparameter.
+    pub fn sample(&self) -> Hyperparameters {
+        with_rng(|rng| Hyperparameters {
+            hidden_layers: self.hidden_layers.choose(rng).cloned(),
+            activation: self.activations.choose(rng).copied(),
+            optimizer: self.optimizers.choose(rng).cloned(),
+            learning_rate: self.learning_rates.choose(rng).copied(),
+            dropout: self.dropouts.choose(rng).copied(),
+            batch_size: self.batch_sizes.choose(rng).copied(),
+            epochs: self.epochs.choose(rng).copied(),
+        })
+    }
+}
+
+fn expand<T, F: Fn(&mut Hyperparameters, &T)>(
+    grid: Vec<Hyperparameters>,
+    values: &[T],
+    set: F,
+) -> Vec<Hyperparameters> {
+    if values.is_empty() {
+        return grid;
+    }
+    grid.iter()
+        .flat_map(|h| {
+            values.iter().map(|v| {
+                let mut h = h.clone();
+                set(&mut h, v);
+                h
+            })
+        })
+        .collect()
+}
+
+/// A combination of hyperparameters, `None` keeping the base model's value.
+#[derive(Clone, Debug, Default)]
+pub struct Hyperparameters {
+    pub hidden_layers: Option<Vec<usize>>,
+    pub activation: Option<Activation>,
+    pub optimizer: Option<Optimizers>,
+    pub learning_rate: Option<Scalar>,
+    pub dropout: Option<Option<Scalar>>,
+    pub batch_size: Option<usize>,
+    pub epochs: Option<usize>,
+}
+
+impl Hyperparameters {
+    /// Returns the base model with these hyperparameters.
+    pub fn apply(&self, base: &Model) -> Model {
+        let mut model = base.clone();
+        if let Some(epochs) = self.epochs {
+            model.epochs = epochs;
+        }
+        if let Some(batch_size) = self.batch_size {
+            model.batch_size = Some(batch_size);
+        }
+
+        let network = match model.network.as_mut() {
+            Some(network) => network,
+            None => return model,
+        };
+
+        let full_dense_indices = network
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| matches!(layer, NetworkLayerModels::FullDense(_)))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let output_index = *full_dense_indices
+            .last()
+            .expect("The searched model must end with a full dense layer");
+
+        let layers_count = network.layers.len();
+        if let Some(sizes) = &self.hidden_layers {
+            let first_hidden = full_dense_indices[0];
+            let template = match &network.layers[first_hidden] {
+                NetworkLayerModels::FullDense(layer) if first_hidden != output_index => layer.clone(),
+                _ => panic!("The searched model must have a hidden full dense layer to search its hidden layers"),
+            };
+            network.layers.drain(first_hidden..output_index);
+            for (i, size) in sizes.iter().enumerate() {
+                let mut layer = template.clone();
+                layer.size = *size;
+                network
+                    .layers
+                    .insert(first_hidden + i, NetworkLayerModels::FullDense(layer));
+            }
+        }
+
+        let output_index = output_index + network.layers.len() - layers_count;
+        for (i, layer) in network.layers.iter_mut().enumerate() {
+            if let NetworkLayerModels::FullDense(layer) = layer {
+                if i != output_index {
+                    if let Some(activation) = self.activation {
+                        layer.activation = activation;
+                        layer.weights_initializer = default_weights_initializer_for(activation);
+                    }
+                    if let Some(dropout) = self.dropout {
+                        layer.dropout = dropout;
+                    }
+                }
+                if let Some(optimizer) = &self.optimizer {
+                    layer.biases_optimizer = optimizer.clone();
+                    layer.weights_optimizer = optimizer.clone();
+                }
+            }
+        }
+
+        if let Some(learning_rate) = self.learning_rate {
+            network.set_learning_rate(LearningRateSchedule::Constant(learning_rate));
+        }
+
+        model
+    }
+}
+
+/// How the configurations of a `HyperparameterSearch` are chosen and trained.
+#[derive(Clone, Debug)]
+pub enum SearchStrategy {
+    /// Every combination of the search space (see `SearchSpace::grid`).
+    Grid,
+    /// `trials` distinct random combinations of the search space (all of them if there are fewer).
+    Random { trials: usize },
+    /// `candidates` distinct random combinations trained for `min_epochs`, the best `1 / eta` of them being trained again
+    /// with `eta` times more epochs, until one remains or the base model's epochs are reached.
+    SuccessiveHalving {
+        candidates: usize,
+        min_epochs: usize,
+        eta: usize,
+    },
+    /// Successive halvings trading the number of candidates against their minimum epochs,
+    /// up to `max_epochs` (Li et al., 2018).
+    ///
+    /// A combination is drawn by a single bracket, so the last ones may get fewer candidates.
+    Hyperband { max_epochs: usize, eta: usize },
+}
+
+/// Hyperparameter search
+///
+/// Trains variations of a base model with a trainer (`KFolds`, `SplitTraining`...)
+/// and ranks them by the average over the folds of a metric of the trainer.
+///
+/// The configurations are trained in parallel if the backend is thread safe.
+pub struct HyperparameterSearch {
+    pub base: Model,
+    pub space: SearchSpace,
+    pub strategy: SearchStrategy,
+    pub metric: Option<String>,
+    pub seed: Option<u64>,
+    /// The trained configurations by `Model::hashed_repr`
+    pub models: BTreeMap<String, Model>,
+}
+
+// Larger grids are sampled one combination at a time instead of being built and shuffled
+const MAX_SHUFFLED_GRID: usize = 4096;
+// Random draws per configuration before giving up on finding new ones
+const MAX_DRAW_ATTEMPTS: usize = 100;
+
+// A trained configuration
+struct Trial {
+    hyperparameters: Hyperparameters,
+    model: Model,
+    score: Scalar,
+    score_std: Scalar,
+}
+
+impl HyperparameterSearch {
+    pub fn new(base: Model, space: SearchSpace, strategy: SearchStrategy) -> Self {
+        Self {
+            base,
+            space,
+            strategy,
+            metric: None,
+            seed: None,
+            models: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the name of the trainer's metric ranking the configurations (its first metric by default).
+    pub fn metric(&mut self, name: &str) -> &mut Self {
+        self.metric = Some(name.to_string());
+        self
+    }
+
+    /// Seeds the random choice of the configurations.
+    ///
+    /// The trainings are seeded by the trainer or the base model.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Returns a trained configuration from its `Model::hashed_repr`.
+    pub fn get_model(&self, hash: &str) -> Option<&Model> {
+        self.models.get(hash)
+    }
+
+    /// Runs the search, creating a trainer for each configuration with `make_trainer`.
+    ///
+    /// Returns the configurations ranked from best to worst, with the columns:
+    /// - `rank`, `model` (the `Model::hashed_repr` of the configuration, see `get_model`)
+    /// - `score` and `score_std`, the average and standard deviation of the metric over the folds
+    /// - `epochs`, `batch_size`, and the searched `hidden_layers`, `activation`, `optimizer`,
+    ///   `learning_rate` and `dropout` (null when the base model's value is kept)
+    ///
+    /// With successive halving and Hyperband, each configuration appears once with the most epochs it was trained for.
+    pub fn run<T, F>(&mut self, make_trainer: F, data: &DataTable) -> DataTable
+    where
+        T: Trainer,
+        F: Fn() -> T + Sync,
+    {
+        TM::start("search");
+
+        if let Some(seed) = self.seed {
+            set_seed(seed);
+        }
+
+        let metric_direction = self.higher_is_better(&make_trainer);
+        let mut drawn = BTreeSet::new();
+        let trials = match self.strategy.clone() {
+            SearchStrategy::Grid => self.evaluate(&make_trainer, self.space.grid(), data),
+            SearchStrategy::Random { trials } => {
+                let configurations = self.draw(&self.space, trials, &mut drawn);
+                self.evaluate(&make_trainer, configurations, data)
+            }
+            SearchStrategy::SuccessiveHalving {
+                candidates,
+                min_epochs,
+                eta,
+            } => {
+                let configurations = self.draw(&self.halving_space(), candidates, &mut drawn);
+                let max_epochs = self.base.epochs;
+                self.successive_halving(&make_trainer, configurations, min_epochs, max_epochs, eta, metric_direction, data)
+            }
+            SearchStrategy::Hyperband { max_epochs, eta } => {
+                assert!(eta > 1, "Hyperband requires eta > 1");
+                let mut s_max = 0;
+                while eta.pow(s_max + 1) <= max_epochs {
+                    s_max += 1;
+                }
+                let space = self.halving_space();
+                let mut trials = vec![];
+                for s in (0..=s_max).rev() {
+                    let candidates = ((s_max + 1) as usize * eta.pow(s)).div_ceil(s as usize + 1);
+                    let min_epochs = (max_epochs / eta.pow(s)).max(1);
+                    let configurations = self.draw(&space, candidates, &mut drawn);
+                    trials.extend(self.successive_halving(
+                        &make_trainer,
+                        configurations,
+                        min_epochs,
+                        max_epochs,
+                        eta,
+                        metric_direction,
+                        data,
+                    ));
+                }
+                trials
+            }
+        };
+
+        let results = self.rank(trials, metric_direction);
+
+        TM::end();
+
+        results
+    }
+
+    // Draws up to `count` configurations of the space giving distinct models, and which weren't drawn before
+    fn draw(&self, space: &SearchSpace, count: usize, drawn: &mut BTreeSet<String>) -> Vec<Hyperparameters> {
+        if space.size() <= MAX_SHUFFLED_GRID {
+            let mut grid = space.grid();
+            with_rng(|rng| grid.shuffle(rng));
+            return grid
+                .into_iter()
+                .filter(|h| drawn.insert(h.apply(&self.base).hashed_repr()))
+                .take(count)
+                .collect();
+        }
+
+        // The combinations drawn twice are rejected, the attempts being bounded in case the space runs out of them
+        let mut configurations = vec![];
+        let mut attempts = 0;
+        while configurations.len() < count && attempts < count.saturating_mul(MAX_DRAW_ATTEMPTS) {
+            let hyperparameters = space.sample();
+            if drawn.insert(hyperparameters.apply(&self.base).hashed_repr()) {
+                configurations.push(hyperparameters);
+            }
+            attempts += 1;
+        }
+        configurations
+    }
+
+    // The successive halvings set the epochs themselves
+    fn halving_space(&self) -> SearchSpace {
+        SearchSpace {
+            epochs: vec![],
+            ..self.space.clone()
+        }
+    }
+
+    fn higher_is_better<T: Trainer, F: Fn() -> T>(&self, make_trainer: &F) -> bool {
+        let metrics = make_trainer().training_options(&self.base).metrics;
+        match &self.metric {
+            Some(name) => metrics
+                .iter()
+                .find(|m| &m.name() == name)
+                .unwrap_or_else(|| panic!("The search metric {} must be one of the trainer's metrics", name))
+                .higher_is_better(),
+            None => metrics[0].higher_is_better(),
+        }
+    }
+
+    // Trains the configurations, possibly in parallel, and scores them
+    fn evaluate<T, F>(&self, make_trainer: &F, configurations: Vec<Hyperparameters>, data: &DataTable) -> Vec<Trial>
+    where
+        T: Trainer,
+        F: Fn() -> T + Sync,
+    {
+        let trial = |hyperparameters: Hyperparameters| {
+            let model = hyperparameters.apply(&self.base);
+            let mut trainer = make_trainer();
+            let name = match &self.metric {
+                Some(name) => name.clone(),
+                None => trainer.training_options(&model).metrics[0].name(),
+            };
+            let (_, eval) = trainer.run(&model, data);
+
+            let values = eval
+                .folds
+                .iter()
+                .map(|fold| fold.get_final_metric(&name).unwrap_or(Scalar::NAN))
+                .collect::<Vec<_>>();
+            let score = values.iter().sum::<Scalar>() / values.len() as Scalar;
+            let score_std = (values.iter().map(|v| (v - score).powi(2)).sum::<Scalar>()
+                / values.len() as Scalar)
+                .sqrt();
+
+            Trial {
+                hyperparameters,
+                model,
+                score,
+                score_std,
+            }
+        };
+
+        if !Matrix::is_backend_thread_safe() {
+            return configurations.into_iter().map(trial).collect();
+        }
+
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let mut configurations = configurations.into_iter().peekable();
+        let mut trials = vec![];
+        while configurations.peek().is_some() {
+            let batch = configurations.by_ref().take(threads).collect::<Vec<_>>();
+            thread::scope(|scope| {
+                let handles = batch
+                    .into_iter()
+                    .map(|hyperparameters| scope.spawn(|| trial(hyperparameters)))
+                    .collect::<Vec<_>>();
+                trials.extend(handles.into_iter().map(|h| h.join().unwrap()));
+            });
+        }
+        trials
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn successive_halving<T, F>(
+        &self,
+        make_trainer: &F,
+        configurations: Vec<Hyperparameters>,
+        min_epochs: usize,
+        max_epochs: usize,
+        eta: usize,
+        higher_is_better: bool,
+        data: &DataTable,
+    ) -> Vec<Trial>
+    where
+        T: Trainer,
+        F: Fn() -> T + Sync,
+    {
+        assert!(eta > 1, "Successive halving requires eta > 1");
+
+        let mut epochs = min_epochs.min(max_epochs);
+        let mut alive = configurations;
+        let mut eliminated = vec![];
+        loop {
+            let with_epochs = alive
+                .into_iter()
+                .map(|h| Hyperparameters {
+                    epochs: Some(epochs),
+                    ..h
+                })
+                .collect();
+            let mut trials = self.evaluate(make_trainer, with_epochs, data);
+            sort_trials(&mut trials, higher_is_better);
+
+            if trials.len() <= 1 || epochs >= max_epochs {
+                eliminated.extend(trials);
+                return eliminated;
+            }
+
+            let kept = (trials.len() / eta).max(1);
+            alive = trials[..kept].iter().map(|t| t.hyperparameters.clone()).collect();
+            eliminated.extend(trials.drain(kept..));
+            epochs = (epochs * eta).min(max_epochs);
+        }
+    }
+
+    fn rank(&mut self, mut trials: Vec<Trial>, higher_is_better: bool) -> DataTable {
+        sort_trials(&mut trials, higher_is_better);
+
+        let hashes = trials.iter().map(|t| t.model.hashed_repr()).collect::<Vec<_>>();
+        let hidden_layers = trials
+            .iter()
+            .map(|t| {
+                t.hyperparameters.hidden_layers.as_ref().map(|sizes| {
+                    sizes.iter().map(|s| s.to_string()).collect::<Vec<_>>().join("-")
+                })
+            })
+            .collect::<Vec<_>>();
+        let activations = trials
+            .iter()
+            .map(|t| t.hyperparameters.activation.map(|a| format!("{:?}", a)))
+            .collect::<Vec<_>>();
+        let optimizers = trials
+            .iter()
+            .map(|t| {
+                t.hyperparameters.optimizer.as_ref().map(|o| match o {
+                    Optimizers::SGD(_) => "sgd".to_string(),
+                    Optimizers::Momentum(_) => "momentum".to_string(),
+                    Optimizers::Adam(_) => "adam".to_string(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let results = DataTable::from_columns(vec![
+            Series::new("rank", (1..=trials.len() as u32).collect::<Vec<_>>()),
+            Series::new("model", hashes.clone()),
+            Series::new("score", trials.iter().map(|t| t.score).collect::<Vec<_>>()),
+            Series::new("score_std", trials.iter().map(|t| t.score_std).collect::<Vec<_>>()),
+            Series::new("epochs", trials.iter().map(|t| t.model.epochs as u32).collect::<Vec<_>>()),
+            Series::new(
+                "batch_size",
+                trials.iter().map(|t| t.model.batch_size.map(|b| b as u32)).collect::<Vec<_>>(),
+            ),
+            Series::new("hidden_layers", hidden_layers),
+            Series::new("activation", activations),
+            Series::new("optimizer", optimizers),
+            Series::new(
+                "learning_rate",
+                trials.iter().map(|t| t.hyperparameters.learning_rate).collect::<Vec<_>>(),
+            ),
+            Series::new(
+                "dropout",
+                trials.iter().map(|t| t.hyperparameters.dropout.flatten()).collect::<Vec<_>>(),
+            ),
+        ]);
+
+        for (hash, trial) in hashes.into_iter().zip(trials) {
+            self.models.insert(hash, trial.model);
+        }
+
+        results
+    }
+}
+
+// Best first, the configurations whose score couldn't be computed last
+fn sort_trials(trials: &mut [Trial], higher_is_better: bool) {
+    trials.sort_by(|a, b| match (a.score.is_nan(), b.score.is_nan()) {
+        (true, true) => std::cmp::Ordering::Equal,
+        (true, false) => std::cmp::Ordering::Greater,
+        (false, true) => std::cmp::Ordering::Less,
+        (false, false) if higher_is_better => b.score.partial_cmp(&a.score).unwrap(),
+        (false, false) => a.score.partial_cmp(&b.score).unwrap(),
+    });
+}
+
@@ -1,17 +1,31 @@
+use std::sync::Arc;
+
 use crate::{
-    benchmarking::{EpochEvaluation, ModelEvaluation, TrainingEvaluation},
+    benchmarking::{EpochEvaluation, ModelEvaluation},
     linalg::Scalar,
+    metrics::{default_metrics, Metric},
     model::Model,
     monitor::TM,
-    network::params::NetworkParams,
-    vec_utils::r2_score_vector2,
+    network::{params::NetworkParams, params_averaging::ParamsAveraging},
+    random::set_seed,
+    trainers::{
+        callbacks::{Callbacks, TrainingCallback},
+        train_network, Trainer, TrainingOptions, ValidationSet,
+    },
 };
 
 #[cfg(feature = "data")]
-use crate::datatable::DataTable;
+use crate::{
+    datatable::DataTable,
+    loader::DataLoader,
+    network::Network,
+    preprocessing::Reverter,
+    streaming::DataStream,
+    trainers::BatchHook,
+};
 
 #[cfg(not(feature = "data"))]
-use rand::thread_rng;
+use crate::random::with_rng;
 #[cfg(not(feature = "data"))]
 use rand::seq::SliceRandom;
 
@@ -23,7 +37,14 @@ pub struct SplitTraining {
     pub real_time_reporter: Option<Box<ReporterClosure>>,
     pub model: Option<NetworkParams>,
     pub all_epochs_validation: bool,
-    pub all_epochs_r2: bool,
+    pub all_epochs_metrics: bool,
+    pub metrics: Vec<Arc<dyn Metric>>,
+    pub seed: Option<u64>,
+    pub params_averaging: Option<ParamsAveraging>,
+    pub classification_top_k: usize,
+    #[cfg(feature = "data")]
+    pub reverter: Option<Arc<Reverter>>,
+    pub callbacks: Vec<Box<dyn TrainingCallback>>,
 }
 
 impl SplitTraining {
@@ -32,8 +53,15 @@ impl SplitTraining {
             ratio,
             real_time_reporter: None,
             all_epochs_validation: false,
-            all_epochs_r2: false,
+            all_epochs_metrics: false,
+            metrics: vec![],
             model: None,
+            seed: None,
+            params_averaging: None,
+            classification_top_k: 5,
+            #[cfg(feature = "data")]
+            reverter: None,
+            callbacks: vec![],
         }
     }
 
@@ -41,16 +69,71 @@ impl SplitTraining {
         self.model.take().unwrap()
     }
 
-    /// Enables computing the R2 score of the model at the end of each epoch
-    /// and reporting it if a real time reporter is attached.
+    /// Seeds every random operation of the training, overriding the model's seed if any.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Enables keeping an exponential moving average of the parameters, updated at the end of each epoch.
+    ///
+    /// The averaged parameters replace the final ones before the final validation.
+    pub fn ema_params(&mut self, decay: Scalar) -> &mut Self {
+        self.params_averaging = Some(ParamsAveraging::ExponentialMovingAverage { decay });
+        self
+    }
+
+    /// Enables Stochastic Weight Averaging of the parameters over the last `epochs` epochs.
+    ///
+    /// The averaged parameters replace the final ones before the final validation.
+    pub fn swa_params(&mut self, epochs: usize) -> &mut Self {
+        self.params_averaging = Some(ParamsAveraging::StochasticWeightAveraging { epochs });
+        self
+    }
+
+    /// Sets the `k` of the top-k accuracy computed for classification models (5 by default).
+    pub fn classification_top_k(&mut self, k: usize) -> &mut Self {
+        self.classification_top_k = k;
+        self
+    }
+
+    /// Adds a metric computed on the validation set and stored under its name in the epochs' evaluations.
+    ///
+    /// Defaults to the accuracy for classification losses and the R2 score otherwise (see `metrics::default_metrics`).
+    pub fn metric<M: Metric + 'static>(&mut self, metric: M) -> &mut Self {
+        self.metrics.push(Arc::new(metric));
+        self
+    }
+
+    /// Computes the metrics on the validation targets and point predictions reverted
+    /// into their original units (see `Pipeline::reverter`), instead of the preprocessed ones.
+    ///
+    /// Meant for the regression metrics (mae, rmse...), for instance:
+    /// `trainer.metrics_in_original_units(pipeline.reverter()).metric(Rmse)`.
+    #[cfg(feature = "data")]
+    pub fn metrics_in_original_units(&mut self, reverter: Arc<Reverter>) -> &mut Self {
+        self.reverter = Some(reverter);
+        self
+    }
+
+    /// Adds a callback called during the training (see `TrainingCallback`), such as `EarlyStopping`.
+    ///
+    /// The callbacks keep their state from one `run` to the next.
+    pub fn callback<C: TrainingCallback + 'static>(&mut self, callback: C) -> &mut Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Enables computing the metrics of the model at the end of each epoch
+    /// and reporting them if a real time reporter is attached.
     ///
     /// /!\ Requires `all_epochs_validation` to be enabled.
     ///
     /// /!\ Is time consuming.
     ///
-    /// Otherwise computes it only at the end of the final epoch
-    pub fn all_epochs_r2(&mut self) -> &mut Self {
-        self.all_epochs_r2 = true;
+    /// Otherwise computes them only at the end of the final epoch
+    pub fn all_epochs_metrics(&mut self) -> &mut Self {
+        self.all_epochs_metrics = true;
         self
     }
 
@@ -81,116 +164,202 @@ impl SplitTraining {
 
     #[cfg(feature = "data")]
     pub fn run(&mut self, model: &Model, data: &DataTable) -> (DataTable, ModelEvaluation) {
-        assert!(!self.all_epochs_r2 || self.all_epochs_validation);
+        assert!(!self.all_epochs_metrics || self.all_epochs_validation);
         
         TM::start("split");
 
         TM::start("init");
 
-        let mut preds_and_ids = DataTable::new_empty();
-        let mut model_eval = ModelEvaluation::new_empty();
+        if let Some(seed) = self.seed.or(model.seed) {
+            set_seed(seed);
+        }
 
-        let predicted_features = model.dataset_config.predicted_features_names();
         let id_column = model
             .dataset_config
             .get_id_column()
             .expect("One feature must be configurationified as an id in the dataset dataset_config.");
-        let mut network = model.to_network();
+        let network = model.to_network();
 
         // Split the data between validation and training
         let (train_table, validation) = data.split_ratio(self.ratio);
 
-        // Shuffle the validation and training set and split it between x and y
+        let result = self.train_and_validate(
+            model,
+            network,
+            &validation,
+            train_table.num_rows(),
+            &mut |e, network, on_batch_end| {
+                model.train_epoch_with_hook(e, network, &train_table, id_column, on_batch_end)
+            },
+        );
+
+        TM::end();
+
+        result
+    }
+
+    /// Same as `run` but the training data is read by mini-batches from a `DataStream`
+    /// (see `Model::train_epoch_streaming`), and validated on the `validation` data held in memory.
+    ///
+    /// The ratio is not used since the data is already split.
+    #[cfg(feature = "data")]
+    pub fn run_streaming(
+        &mut self,
+        model: &Model,
+        train: &DataStream,
+        validation: &DataTable,
+    ) -> (DataTable, ModelEvaluation) {
+        assert!(!self.all_epochs_metrics || self.all_epochs_validation);
+
+        TM::start("split");
+
+        TM::start("init");
+
+        if let Some(seed) = self.seed.or(model.seed) {
+            set_seed(seed);
+        }
+
+        let id_column = model
+            .dataset_config
+            .get_id_column()
+            .expect("One feature must be configurationified as an id in the dataset dataset_config.");
+        let network = model.to_network();
+
+        let result = self.train_and_validate(
+            model,
+            network,
+            validation,
+            train.num_rows(),
+            &mut |e, network, on_batch_end| {
+                model.train_epoch_streaming(e, network, train, id_column, on_batch_end)
+            },
+        );
+
+        TM::end();
+
+        result
+    }
+
+    /// Same as `run` but the training mini-batches come from a `DataLoader` (see `Model::train_epoch_loader`),
+    /// and the network is validated on the `validation` data.
+    ///
+    /// The ratio is not used since the data is already split.
+    #[cfg(feature = "data")]
+    pub fn run_loader(
+        &mut self,
+        model: &Model,
+        loader: &mut dyn DataLoader,
+        validation: &DataTable,
+    ) -> (DataTable, ModelEvaluation) {
+        assert!(!self.all_epochs_metrics || self.all_epochs_validation);
+
+        TM::start("split");
+
+        TM::start("init");
+
+        if let Some(seed) = self.seed.or(model.seed) {
+            set_seed(seed);
+        }
+
+        let network = model.to_network();
+        let train_samples = loader.num_samples();
+
+        let result = self.train_and_validate(
+            model,
+            network,
+            validation,
+            train_samples,
+            &mut |e, network, on_batch_end| model.train_epoch_loader(e, network, loader, on_batch_end),
+        );
+
+        TM::end();
+
+        result
+    }
+
+    // Validates the network trained with `train_epoch` on the validation data, ends the "init" monitoring task
+    #[cfg(feature = "data")]
+    fn train_and_validate(
+        &mut self,
+        model: &Model,
+        mut network: Network,
+        validation: &DataTable,
+        train_rows: usize,
+        train_epoch: &mut dyn FnMut(usize, &mut Network, &mut BatchHook) -> Scalar,
+    ) -> (DataTable, ModelEvaluation) {
+        let mut model_eval = ModelEvaluation::new_empty();
+
+        let predicted_features = model.dataset_config.predicted_features_names();
+        let id_column = model
+            .dataset_config
+            .get_id_column()
+            .expect("One feature must be configurationified as an id in the dataset dataset_config.");
+
+        // Shuffle the validation set and split it between x and y
         let (validation_x_table, validation_y_table) =
             validation.random_order_in_out(&predicted_features);
 
         // Convert the validation set to vectors
-        let validation_x = validation_x_table.drop_column(id_column).to_vectors();
-        let validation_y = validation_y_table.to_vectors();
+        let validation_x = model.inputs_to_vectors(&validation_x_table, id_column);
+        let validation_y = validation_y_table.to_vectors_with_missing();
+        let validation_weights = model.samples_weights(&validation_x_table, &validation_y);
+        let validation_set = ValidationSet::new(validation_x, validation_y, validation_weights)
+            .with_reverter(self.reverter.clone(), &predicted_features);
 
         TM::end_with_message(format!(
             "Initialized training with {} samples\nInitialized validation with {} samples",
-            train_table.num_rows(),
+            train_rows,
             validation_x_table.num_rows()
         ));
 
-        TM::start("epochs");
-
-        let mut eval = TrainingEvaluation::new_empty();
-        let epochs = model.epochs;
-        for e in 0..epochs {
-            TM::start(&format!("{}/{}", e + 1, epochs));
-
-            let train_loss = model.train_epoch(e, &mut network, &train_table, id_column);
-
-            let loss_fn = model.loss.to_loss();
-            let (preds, loss_avg, loss_std) = if e == model.epochs - 1 || self.all_epochs_validation
-            {
-                let vloss = network.predict_evaluate_many(
-                    &validation_x,
-                    &validation_y,
-                    &loss_fn,
-                    model.batch_size.unwrap_or(validation_x.len()),
-                );
-                vloss
-            } else {
-                (vec![], -1.0, -1.0)
-            };
-
-            let r2 = if e == model.epochs - 1 || self.all_epochs_r2 {
-                TM::start("r2");
-                let r2 = r2_score_vector2(&validation_y, &preds);
-                TM::end_with_message(format!("R2: {}", r2));
-                r2
-            } else {
-                -1.0
-            };
-
-            let epoch_eval = EpochEvaluation::new(train_loss, loss_avg, loss_std, r2);
-
-            // Report the benchmark in real time if expected
-            if let Some(reporter) = self.real_time_reporter.as_mut() {
-                reporter(e, epoch_eval.clone());
-            }
-
-            // Save the predictions if it is the last epoch
-            if e == model.epochs - 1 {
-                preds_and_ids = preds_and_ids.apppend(
-                    &DataTable::from_vectors(&predicted_features, &preds)
-                        .add_column_from(&validation_x_table, id_column),
-                );
-            };
-
-            TM::end_with_message(format!("Training Loss: {}\n ", train_loss));
-
-            eval.add_epoch(epoch_eval);
-        }
-        TM::end_with_message(format!("Final performance: {:#?}", eval.get_final_epoch()));
+        let options = self.training_options(model);
+        let mut callbacks = Callbacks(std::mem::take(&mut self.callbacks));
+        let reporter = &mut self.real_time_reporter;
+        let (preds, eval) = train_network(
+            &options,
+            model,
+            &mut network,
+            train_epoch,
+            &validation_set,
+            &mut callbacks,
+            &mut |e, eval| {
+                if let Some(reporter) = reporter.as_mut() {
+                    reporter(e, eval.clone());
+                }
+            },
+        );
+        self.callbacks = callbacks.0;
+
+        let preds_and_ids =
+            DataTable::from_vectors(&model.preds_columns_names(), &model.outputs_to_preds(&preds))
+                .add_column_from(&validation_x_table, id_column);
 
         model_eval.add_fold(eval);
         self.model = Some(network.get_params());
 
-        TM::end();
-
         (preds_and_ids, model_eval)
     }
 
     #[cfg(not(feature = "data"))]
     pub fn run(&mut self, model: &Model, data_x: &Vec<Vec<Scalar>>, data_y: &Vec<Vec<Scalar>>) -> (Vec<Vec<Scalar>>, ModelEvaluation) {
-        assert!(!self.all_epochs_r2 || self.all_epochs_validation);
+        assert!(!self.all_epochs_metrics || self.all_epochs_validation);
         assert!(data_x.len() == data_y.len());
         
         TM::start("split");
 
         TM::start("init");
 
+        if let Some(seed) = self.seed.or(model.seed) {
+            set_seed(seed);
+        }
+
         let mut model_eval = ModelEvaluation::new_empty();
         let mut network = model.to_network(data_x[0].len());
         
         // Split the data between validation and training
         let split_at = (self.ratio * data_x.len() as Scalar) as usize;
         let mut ids = (0..data_x.len()).map(|x| x as Scalar).collect::<Vec<_>>();
-        ids.shuffle(&mut thread_rng());
+        with_rng(|rng| ids.shuffle(rng));
 
         let data_x = ids.iter().map(|&i| data_x[i as usize].clone()).collect::<Vec<_>>();
         let data_y = ids.iter().map(|&i| data_y[i as usize].clone()).collect::<Vec<_>>();
@@ -202,65 +371,36 @@ impl SplitTraining {
         let train_y = train_y.to_vec();
         let validation_x = validation_x.to_vec();
         let validation_y = validation_y.to_vec();
+        let validation_weights = model.classes_weights(&validation_y);
+        let validation_set = ValidationSet::new(validation_x, validation_y, validation_weights);
 
         TM::end_with_message(format!(
             "Initialized training with {} samples\nInitialized validation with {} samples",
             train_x.len(),
-            validation_x.len()
+            validation_set.x.len()
         ));
 
-        TM::start("epochs");
-
-        let mut final_predictions = vec![];
-
-        let mut eval = TrainingEvaluation::new_empty();
-        let epochs = model.epochs;
-        for e in 0..epochs {
-            TM::start(&format!("{}/{}", e + 1, epochs));
-
-            let train_loss = model.train_epoch(e, &mut network, &train_x, &train_y);
-
-            let loss_fn = model.loss.to_loss();
-            let (preds, loss_avg, loss_std) = if e == model.epochs - 1 || self.all_epochs_validation
-            {
-                let vloss = network.predict_evaluate_many(
-                    &validation_x,
-                    &validation_y,
-                    &loss_fn,
-                    model.batch_size.unwrap_or(validation_x.len()),
-                );
-                vloss
-            } else {
-                (vec![], -1.0, -1.0)
-            };
-
-            let r2 = if e == model.epochs - 1 || self.all_epochs_r2 {
-                TM::start("r2");
-                let r2 = r2_score_vector2(&validation_y, &preds);
-                TM::end_with_message(format!("R2: {}", r2));
-                r2
-            } else {
-                -1.0
-            };
+        let options = self.training_options(model);
+        let mut callbacks = Callbacks(std::mem::take(&mut self.callbacks));
+        let reporter = &mut self.real_time_reporter;
+        let (preds, eval) = train_network(
+            &options,
+            model,
+            &mut network,
+            &mut |e, network, on_batch_end| {
+                model.train_epoch_with_hook(e, network, &train_x, &train_y, on_batch_end)
+            },
+            &validation_set,
+            &mut callbacks,
+            &mut |e, eval| {
+                if let Some(reporter) = reporter.as_mut() {
+                    reporter(e, eval.clone());
+                }
+            },
+        );
+        self.callbacks = callbacks.0;
+        let final_predictions = model.outputs_to_preds(&preds);
 
-            let epoch_eval = EpochEvaluation::new(train_loss, loss_avg, loss_std, r2);
-
-            // Report the benchmark in real time if expected
-            if let Some(reporter) = self.real_time_reporter.as_mut() {
-                reporter(e, epoch_eval.clone());
-            }
-
-            // Save the predictions if it is the last epoch
-            if e == model.epochs - 1 {
-                final_predictions = preds.clone();
-            };
-
-            TM::end_with_message(format!("Training Loss: {}\n ", train_loss));
-
-            eval.add_epoch(epoch_eval);
-        }
-        TM::end_with_message(format!("Final performance: {:#?}", eval.get_final_epoch()));
-        
         model_eval.add_fold(eval);
         self.model = Some(network.get_params());
 
@@ -275,3 +415,24 @@ impl SplitTraining {
         (reordered_predictions, model_eval)
     }
 }
+
+impl Trainer for SplitTraining {
+    #[cfg(feature = "data")]
+    fn run(&mut self, model: &Model, data: &DataTable) -> (DataTable, ModelEvaluation) {
+        SplitTraining::run(self, model, data)
+    }
+
+    fn training_options(&self, model: &Model) -> TrainingOptions {
+        TrainingOptions {
+            all_epochs_validation: self.all_epochs_validation,
+            all_epochs_metrics: self.all_epochs_metrics,
+            metrics: if self.metrics.is_empty() {
+                default_metrics(&model.loss)
+            } else {
+                self.metrics.clone()
+            },
+            params_averaging: self.params_averaging,
+            classification_top_k: self.classification_top_k,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::linalg::Scalar;
+use crate::{linalg::Scalar, random::with_rng};
 
 use rand::{seq::SliceRandom, Rng};
 
@@ -16,39 +16,147 @@ pub fn std_vector(vec: &Vec<Scalar>) -> Scalar {
 }
 
 pub fn shuffle_vec<T>(vec: &mut Vec<T>) {
-    let mut rng = rand::thread_rng();
-    vec.shuffle(&mut rng);
+    with_rng(|rng| vec.shuffle(rng));
 }
 
 pub fn shuffle_column<T: Clone>(vec: &mut Vec<Vec<T>>, col: usize) {
-    let mut rng = rand::thread_rng();
-
     for i in 0..vec.len() {
-        let j = rng.gen_range(0..vec.len());
+        let j = with_rng(|rng| rng.gen_range(0..vec.len()));
         let swap = vec[j][col].clone();
         vec[j][col] = vec[i][col].clone();
         vec[i][col] = swap;
     }
 }
 
-pub fn r2_score_vector2(y: &Vec<Vec<Scalar>>, y_hat: &Vec<Vec<Scalar>>) -> Scalar {
+/// R2 score of multiple outputs, ignoring the missing (NaN) values of `y`.
+pub fn r2_score_vector2(y: &[Vec<Scalar>], y_hat: &[Vec<Scalar>]) -> Scalar {
     assert!(y.len() == y_hat.len());
     assert!(y[0].len() == y_hat[0].len());
 
-    let y_avg = avg_vector(&y.iter().map(|x| avg_vector(x)).collect());
+    weighted_r2_score_vector2(y, y_hat, &vec![1.0; y.len()])
+}
+
+/// R2 score where the squared errors of each sample (row) are weighted by `weights`,
+/// ignoring the missing (NaN) values of `y`.
+pub fn weighted_r2_score_vector2(
+    y: &[Vec<Scalar>],
+    y_hat: &[Vec<Scalar>],
+    weights: &[Scalar],
+) -> Scalar {
+    assert!(y.len() == y_hat.len());
+    assert!(y.len() == weights.len());
+
+    let mut total_weight = 0.0;
+    let mut y_sum = 0.0;
+    for (sample, weight) in y.iter().zip(weights.iter()) {
+        for value in sample.iter().filter(|v| !v.is_nan()) {
+            total_weight += weight;
+            y_sum += weight * value;
+        }
+    }
+    let y_avg = y_sum / total_weight;
     let mut ssr = 0.0;
     let mut sst = 0.0;
 
     for i in 0..y.len() {
         for j in 0..y[0].len() {
-            ssr += (y[i][j] - y_hat[i][j]).powi(2);
-            sst += (y[i][j] - y_avg).powi(2);
+            if y[i][j].is_nan() {
+                continue;
+            }
+            ssr += weights[i] * (y[i][j] - y_hat[i][j]).powi(2);
+            sst += weights[i] * (y[i][j] - y_avg).powi(2);
         }
     }
 
     1.0 - (ssr / sst)
 }
 
+// Errors of every known (not NaN) target of every sample
+fn errors_vector2(y: &[Vec<Scalar>], y_hat: &[Vec<Scalar>]) -> Vec<Scalar> {
+    assert!(y.len() == y_hat.len());
+
+    y.iter()
+        .zip(y_hat.iter())
+        .flat_map(|(y, y_hat)| y.iter().zip(y_hat.iter()).map(|(y, y_hat)| y - y_hat))
+        .filter(|e| !e.is_nan())
+        .collect()
+}
+
+/// Mean absolute error of multiple outputs, ignoring the missing (NaN) values of `y`.
+pub fn mean_absolute_error_vector2(y: &[Vec<Scalar>], y_hat: &[Vec<Scalar>]) -> Scalar {
+    let errors = errors_vector2(y, y_hat);
+    errors.iter().map(|e| e.abs()).sum::<Scalar>() / errors.len() as Scalar
+}
+
+/// Root mean squared error of multiple outputs, ignoring the missing (NaN) values of `y`.
+pub fn root_mean_squared_error_vector2(y: &[Vec<Scalar>], y_hat: &[Vec<Scalar>]) -> Scalar {
+    let errors = errors_vector2(y, y_hat);
+    (errors.iter().map(|e| e * e).sum::<Scalar>() / errors.len() as Scalar).sqrt()
+}
+
+/// Mean absolute percentage error (as a fraction, not multiplied by 100) of multiple outputs,
+/// ignoring the missing (NaN) and zero values of `y`.
+pub fn mean_absolute_percentage_error_vector2(y: &[Vec<Scalar>], y_hat: &[Vec<Scalar>]) -> Scalar {
+    assert!(y.len() == y_hat.len());
+
+    let percentages = y
+        .iter()
+        .zip(y_hat.iter())
+        .flat_map(|(y, y_hat)| y.iter().zip(y_hat.iter()))
+        .filter(|(y, _)| !y.is_nan() && **y != 0.0)
+        .map(|(y, y_hat)| ((y - y_hat) / y).abs())
+        .collect::<Vec<_>>();
+    percentages.iter().sum::<Scalar>() / percentages.len() as Scalar
+}
+
+/// Median absolute error of multiple outputs, ignoring the missing (NaN) values of `y`.
+pub fn median_absolute_error_vector2(y: &[Vec<Scalar>], y_hat: &[Vec<Scalar>]) -> Scalar {
+    let errors = errors_vector2(y, y_hat);
+    median_vector(&errors.iter().map(|e| e.abs()).collect())
+}
+
+/// Largest absolute error of multiple outputs, ignoring the missing (NaN) values of `y`.
+pub fn max_error_vector2(y: &[Vec<Scalar>], y_hat: &[Vec<Scalar>]) -> Scalar {
+    errors_vector2(y, y_hat)
+        .iter()
+        .map(|e| e.abs())
+        .fold(0.0, Scalar::max)
+}
+
+/// Explained variance score of multiple outputs, ignoring the missing (NaN) values of `y`.
+///
+/// Same as the R2 score except that a constant bias of the predictions is not penalized.
+pub fn explained_variance_score_vector2(y: &[Vec<Scalar>], y_hat: &[Vec<Scalar>]) -> Scalar {
+    let errors = errors_vector2(y, y_hat);
+    let values = y
+        .iter()
+        .flat_map(|y| y.iter().copied())
+        .filter(|v| !v.is_nan())
+        .collect::<Vec<_>>();
+
+    let errors_avg = avg_vector(&errors);
+    let errors_variance = errors.iter().map(|e| (e - errors_avg).powi(2)).sum::<Scalar>() / errors.len() as Scalar;
+    let y_avg = avg_vector(&values);
+    let y_variance = values.iter().map(|v| (v - y_avg).powi(2)).sum::<Scalar>() / values.len() as Scalar;
+
+    1.0 - errors_variance / y_variance
+}
+
+/// Class of a sample's targets: the index of its largest value (one-hot encoded classes),
+/// or its value if there is only one (class index).
+pub fn class_of(sample: &[Scalar]) -> usize {
+    if sample.len() == 1 {
+        sample[0].round() as usize
+    } else {
+        sample
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+            .unwrap()
+    }
+}
+
 pub fn r2_score(y: &Vec<Scalar>, y_hat: &Vec<Scalar>) -> Scalar {
     assert!(y.len() == y_hat.len());
 
@@ -218,9 +326,8 @@ pub fn vectors_correlation(vec1: &[Scalar], vec2: &[Scalar]) -> Option<Scalar> {
 }
 
 pub fn vector_sample(vec: &Vec<Scalar>, sample_size: usize) -> Vec<Scalar> {
-    let mut rng = rand::thread_rng();
     let mut vec = vec.clone();
-    vec.shuffle(&mut rng);
+    with_rng(|rng| vec.shuffle(rng));
     vec.truncate(sample_size);
     vec
 }
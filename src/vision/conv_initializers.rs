@@ -1,30 +1,78 @@
 use serde::{Deserialize, Serialize};
 
 use super::image::Image;
-use crate::{linalg::Scalar, vision::image::ImageTrait};
+use crate::{initializers::Initializers, linalg::Scalar, vision::image::ImageTrait};
 
+/// See `Initializers` for the description of each initializer.
 #[derive(Serialize, Debug, Deserialize, Clone)]
 pub enum ConvInitializers {
     Zeros,
+    Constant(Scalar),
     Uniform,
     UniformSigned,
     GlorotUniform,
+    GlorotNormal,
+    HeUniform,
+    HeNormal,
+    LeCunUniform,
+    LeCunNormal,
+    TruncatedNormal { mean: Scalar, std_dev: Scalar },
+    Orthogonal,
 }
 
 impl ConvInitializers {
+    /// Generates `nsample` kernels of `nrow` rows, `ncol` columns and `nchan` channels,
+    /// each kernel being fully connected to the `nchan` input channels.
     pub fn gen_image(&self, nrow: usize, ncol: usize, nchan: usize, nsample: usize) -> Image {
+        let receptive_field = nrow * ncol;
+        self.gen_image_with_fans(
+            nrow,
+            ncol,
+            nchan,
+            nsample,
+            receptive_field * nchan,
+            receptive_field * nsample,
+        )
+    }
+
+    /// `fan_in` and `fan_out` are the number of inputs and outputs each parameter is connected to.
+    /// They are only used by the variance scaling initializers (Glorot, He, LeCun).
+    pub fn gen_image_with_fans(
+        &self,
+        nrow: usize,
+        ncol: usize,
+        nchan: usize,
+        nsample: usize,
+        fan_in: usize,
+        fan_out: usize,
+    ) -> Image {
+        // Each sample (kernel) is generated as a column so that orthogonal kernels are orthogonal to each other
+        let samples = self.to_initializers().gen_matrix_with_fans(
+            nrow * ncol * nchan,
+            nsample,
+            fan_in,
+            fan_out,
+        );
+        Image::from_samples(&samples, nchan)
+    }
+
+    fn to_initializers(&self) -> Initializers {
         match self {
-            ConvInitializers::Zeros => Image::zeros(nrow, ncol, nchan, nsample),
-            ConvInitializers::Uniform => {
-                Image::random_uniform(nrow, ncol, nchan, nsample, 0.0, 1.0)
-            }
-            ConvInitializers::UniformSigned => {
-                Image::random_uniform(nrow, ncol, nchan, nsample, -1.0, 1.0)
-            }
-            ConvInitializers::GlorotUniform => {
-                let limit = (6. / (ncol * nrow + ncol * nrow) as Scalar).sqrt();
-                Image::random_uniform(nrow, ncol, nchan, nsample, -limit, limit)
-            }
+            ConvInitializers::Zeros => Initializers::Zeros,
+            ConvInitializers::Constant(value) => Initializers::Constant(*value),
+            ConvInitializers::Uniform => Initializers::Uniform,
+            ConvInitializers::UniformSigned => Initializers::UniformSigned,
+            ConvInitializers::GlorotUniform => Initializers::GlorotUniform,
+            ConvInitializers::GlorotNormal => Initializers::GlorotNormal,
+            ConvInitializers::HeUniform => Initializers::HeUniform,
+            ConvInitializers::HeNormal => Initializers::HeNormal,
+            ConvInitializers::LeCunUniform => Initializers::LeCunUniform,
+            ConvInitializers::LeCunNormal => Initializers::LeCunNormal,
+            ConvInitializers::TruncatedNormal { mean, std_dev } => Initializers::TruncatedNormal {
+                mean: *mean,
+                std_dev: *std_dev,
+            },
+            ConvInitializers::Orthogonal => Initializers::Orthogonal,
         }
     }
 }
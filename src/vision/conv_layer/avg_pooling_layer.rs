@@ -64,6 +64,14 @@ impl ParameterableLayer for AvgPoolingLayer {
     fn as_dropout_layer(&mut self) -> Option<&mut dyn crate::layer::DropoutLayer> {
         None
     }
+
+    fn as_trainable_layer(&self) -> Option<&dyn crate::layer::TrainableLayer> {
+        None
+    }
+
+    fn as_trainable_layer_mut(&mut self) -> Option<&mut dyn crate::layer::TrainableLayer> {
+        None
+    }
 }
 
 impl ConvNetworkLayer for AvgPoolingLayer {
@@ -1,4 +1,5 @@
 use crate::vision::{
+    conv_activation::ConvActivation,
     conv_initializers::ConvInitializers,
     conv_optimizer::{conv_sgd, ConvOptimizers},
 };
@@ -11,6 +12,16 @@ pub fn default_kernels_initializer() -> ConvInitializers {
     ConvInitializers::GlorotUniform
 }
 
+/// He initialization for ReLU layers, Glorot initialization for the others.
+pub fn default_kernels_initializer_for(activation: ConvActivation) -> ConvInitializers {
+    match activation {
+        ConvActivation::ConvReLU => ConvInitializers::HeUniform,
+        ConvActivation::ConvTanh | ConvActivation::ConvSigmoid | ConvActivation::ConvLinear => {
+            default_kernels_initializer()
+        }
+    }
+}
+
 pub fn default_biases_optimizer() -> ConvOptimizers {
     conv_sgd()
 }
@@ -1,5 +1,5 @@
 use crate::{
-    layer::LearnableLayer,
+    layer::{LearnableLayer, TrainableLayer},
     linalg::{Matrix, MatrixTrait, Scalar},
     vision::{
         conv_initializers::ConvInitializers, conv_optimizer::ConvOptimizers, image::Image,
@@ -9,7 +9,7 @@ use crate::{
 
 use crate::vision::image_layer::ImageLayer;
 
-use super::ConvLayer;
+use super::{scale_image_parameters_update, ConvLayer};
 
 #[derive(Debug)]
 pub struct DirectConvLayer {
@@ -18,6 +18,8 @@ pub struct DirectConvLayer {
     input: Option<Image>,
     kernels_optimizer: ConvOptimizers,
     biases_optimizer: ConvOptimizers,
+    trainable: bool,
+    learning_rate_multiplier: Scalar,
 }
 
 impl DirectConvLayer {
@@ -31,11 +33,21 @@ impl DirectConvLayer {
         biases_optimizer: ConvOptimizers,
     ) -> Self {
         Self {
-            kernels: kernels_initializer.gen_image(krows, kcols, in_chans, 1),
+            // each channel has its own kernel, which is only connected to that channel
+            kernels: kernels_initializer.gen_image_with_fans(
+                krows,
+                kcols,
+                in_chans,
+                1,
+                krows * kcols,
+                krows * kcols,
+            ),
             biases: biases_initializer.gen_image(1, 1, in_chans, 1),
             input: None,
             kernels_optimizer,
             biases_optimizer,
+            trainable: true,
+            learning_rate_multiplier: 1.0,
         }
     }
 
@@ -107,12 +119,16 @@ impl ImageLayer for DirectConvLayer {
         }
         let biases_grad = Image::join_channels(biases_grad_channels);
 
-        self.kernels = self
-            .kernels_optimizer
-            .update_parameters(epoch, &self.kernels, &kern_grad);
-        self.biases = self
-            .biases_optimizer
-            .update_parameters(epoch, &self.biases, &biases_grad);
+        if self.trainable {
+            let kernels = self
+                .kernels_optimizer
+                .update_parameters(epoch, &self.kernels, &kern_grad);
+            let biases = self
+                .biases_optimizer
+                .update_parameters(epoch, &self.biases, &biases_grad);
+            self.kernels = scale_image_parameters_update(&self.kernels, kernels, self.learning_rate_multiplier);
+            self.biases = scale_image_parameters_update(&self.biases, biases, self.learning_rate_multiplier);
+        }
         input_grad
     }
 }
@@ -136,6 +152,24 @@ impl LearnableLayer for DirectConvLayer {
     }
 }
 
+impl TrainableLayer for DirectConvLayer {
+    fn is_trainable(&self) -> bool {
+        self.trainable
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.trainable = trainable;
+    }
+
+    fn get_learning_rate_multiplier(&self) -> Scalar {
+        self.learning_rate_multiplier
+    }
+
+    fn set_learning_rate_multiplier(&mut self, multiplier: Scalar) {
+        self.learning_rate_multiplier = multiplier;
+    }
+}
+
 impl ConvLayer for DirectConvLayer {
     fn scale_kernels(&mut self, scale: Scalar) {
         self.kernels = self.kernels.scalar_mul(scale);
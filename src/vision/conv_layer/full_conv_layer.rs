@@ -2,8 +2,9 @@ use std::cmp::Ordering;
 
 use rand::Rng;
 
-use crate::layer::{DropoutLayer, LearnableLayer, ParameterableLayer};
+use crate::layer::{DropoutLayer, LearnableLayer, ParameterableLayer, TrainableLayer};
 use crate::linalg::Scalar;
+use crate::random::with_rng;
 use crate::vision::conv_network::ConvNetworkLayer;
 
 use super::{ConvLayer, Image};
@@ -41,24 +42,25 @@ impl FullConvLayer {
         nkern: usize,
     ) -> Option<(Image, Scalar)> {
         if let Some(dropout_rate) = self.dropout_rate {
-            let mut rng = rand::thread_rng();
-            let dropout_mask = Image::from_fn(
-                kern_size.0,
-                kern_size.1,
-                kern_size.2,
-                nkern,
-                |_, _, _, _| {
-                    if rng
-                        .gen_range((0.0 as Scalar)..(1.0 as Scalar))
-                        .total_cmp(&self.dropout_rate.unwrap())
-                        == Ordering::Greater
-                    {
-                        1.0
-                    } else {
-                        0.0
-                    }
-                },
-            );
+            let dropout_mask = with_rng(|rng| {
+                Image::from_fn(
+                    kern_size.0,
+                    kern_size.1,
+                    kern_size.2,
+                    nkern,
+                    |_, _, _, _| {
+                        if rng
+                            .gen_range((0.0 as Scalar)..(1.0 as Scalar))
+                            .total_cmp(&dropout_rate)
+                            == Ordering::Greater
+                        {
+                            1.0
+                        } else {
+                            0.0
+                        }
+                    },
+                )
+            });
             Some((dropout_mask, dropout_rate))
         } else {
             None
@@ -111,6 +113,24 @@ impl LearnableLayer for FullConvLayer {
     }
 }
 
+impl TrainableLayer for FullConvLayer {
+    fn is_trainable(&self) -> bool {
+        self.conv.is_trainable()
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.conv.set_trainable(trainable)
+    }
+
+    fn get_learning_rate_multiplier(&self) -> Scalar {
+        self.conv.get_learning_rate_multiplier()
+    }
+
+    fn set_learning_rate_multiplier(&mut self, multiplier: Scalar) {
+        self.conv.set_learning_rate_multiplier(multiplier)
+    }
+}
+
 impl DropoutLayer for FullConvLayer {
     fn enable_dropout(&mut self) {
         self.dropout_enabled = true;
@@ -133,6 +153,14 @@ impl ParameterableLayer for FullConvLayer {
     fn as_dropout_layer(&mut self) -> Option<&mut dyn crate::layer::DropoutLayer> {
         Some(self)
     }
+
+    fn as_trainable_layer(&self) -> Option<&dyn TrainableLayer> {
+        Some(self)
+    }
+
+    fn as_trainable_layer_mut(&mut self) -> Option<&mut dyn TrainableLayer> {
+        Some(self)
+    }
 }
 
 impl ConvNetworkLayer for FullConvLayer {}
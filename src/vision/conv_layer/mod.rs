@@ -1,8 +1,14 @@
 use std::fmt::Debug;
 
-use crate::{layer::LearnableLayer, linalg::Scalar};
+use crate::{
+    layer::{LearnableLayer, TrainableLayer},
+    linalg::Scalar,
+};
 
-use super::{image::Image, image_layer::ImageLayer};
+use super::{
+    image::{Image, ImageTrait},
+    image_layer::ImageLayer,
+};
 
 pub mod defaults;
 pub mod dense_conv_layer;
@@ -10,6 +16,17 @@ pub mod direct_conv_layer;
 pub mod avg_pooling_layer;
 pub mod full_conv_layer;
 
-pub trait ConvLayer: ImageLayer + LearnableLayer + Send + Debug {
+pub trait ConvLayer: ImageLayer + LearnableLayer + TrainableLayer + Send + Debug {
     fn scale_kernels(&mut self, scale: Scalar);
 }
+
+/// Scales the step between `parameters` and the `updated` parameters returned by a convolutional optimizer.
+///
+/// See `crate::layer::scale_parameters_update`.
+pub fn scale_image_parameters_update(parameters: &Image, updated: Image, multiplier: Scalar) -> Image {
+    if multiplier == 1.0 {
+        updated
+    } else {
+        parameters.component_add(&updated.component_sub(parameters).scalar_mul(multiplier))
+    }
+}
@@ -1,7 +1,7 @@
 use std::fmt::Debug;
 
 use crate::{
-    layer::{DropoutLayer, Layer, LearnableLayer, ParameterableLayer},
+    layer::{DropoutLayer, Layer, LearnableLayer, ParameterableLayer, TrainableLayer},
     linalg::{Matrix, Scalar},
     network::NetworkLayer,
     vision::{image::Image, image::ImageTrait}, monitor::TM,
@@ -71,6 +71,14 @@ impl ParameterableLayer for ConvNetwork {
     fn as_dropout_layer(&mut self) -> Option<&mut dyn DropoutLayer> {
         Some(self)
     }
+
+    fn as_trainable_layer(&self) -> Option<&dyn TrainableLayer> {
+        Some(self)
+    }
+
+    fn as_trainable_layer_mut(&mut self) -> Option<&mut dyn TrainableLayer> {
+        Some(self)
+    }
 }
 
 impl LearnableLayer for ConvNetwork {
@@ -103,6 +111,41 @@ impl LearnableLayer for ConvNetwork {
     }
 }
 
+impl TrainableLayer for ConvNetwork {
+    /// A convolutional network is trainable as long as one of its layers is.
+    fn is_trainable(&self) -> bool {
+        self.layers
+            .iter()
+            .filter_map(|l| l.as_trainable_layer())
+            .any(|l| l.is_trainable())
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.layers.iter_mut().for_each(|l| {
+            if let Some(l) = l.as_trainable_layer_mut() {
+                l.set_trainable(trainable);
+            }
+        });
+    }
+
+    fn get_learning_rate_multiplier(&self) -> Scalar {
+        self.layers
+            .iter()
+            .filter_map(|l| l.as_trainable_layer())
+            .map(|l| l.get_learning_rate_multiplier())
+            .next()
+            .unwrap_or(1.0)
+    }
+
+    fn set_learning_rate_multiplier(&mut self, multiplier: Scalar) {
+        self.layers.iter_mut().for_each(|l| {
+            if let Some(l) = l.as_trainable_layer_mut() {
+                l.set_learning_rate_multiplier(multiplier);
+            }
+        });
+    }
+}
+
 impl DropoutLayer for ConvNetwork {
     fn enable_dropout(&mut self) {
         self.layers.iter_mut().for_each(|l| {
@@ -63,6 +63,10 @@ impl ConvAdam {
         }
     }
 
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        self.learning_rate = learning_rate;
+    }
+
     pub fn update_parameters(
         &mut self,
         epoch: usize,
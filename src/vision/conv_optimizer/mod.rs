@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use self::{adam::ConvAdam, momentum::ConvMomentum, sgd::ConvSGD};
 
 use super::image::Image;
+use crate::learning_rate::LearningRateSchedule;
 
 pub mod adam;
 pub mod momentum;
@@ -34,6 +35,14 @@ impl ConvOptimizers {
             }
         }
     }
+
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        match self {
+            ConvOptimizers::ConvSGD(sgd) => sgd.set_learning_rate(learning_rate),
+            ConvOptimizers::ConvMomentum(momentum) => momentum.set_learning_rate(learning_rate),
+            ConvOptimizers::ConvAdam(adam) => adam.set_learning_rate(learning_rate),
+        }
+    }
 }
 
 pub fn conv_adam() -> ConvOptimizers {
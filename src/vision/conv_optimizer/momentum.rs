@@ -36,6 +36,10 @@ impl ConvMomentum {
         }
     }
 
+    pub fn set_learning_rate(&mut self, learning_rate: LearningRateSchedule) {
+        self.learning_rate = learning_rate;
+    }
+
     pub fn update_parameters(
         &mut self,
         epoch: usize,
@@ -5,9 +5,9 @@ use arrayfire::{
     random_normal, random_uniform, sign, sqrt, sum, sum_all, unwrap, wrap, Array, Dim4,
     RandomEngine, Seq, tile, convolve2_nn,
 };
-use rand::Rng;
 
 use crate::linalg::{Matrix, MatrixTrait, Scalar};
+use crate::random::gen_seed;
 
 use super::ImageTrait;
 
@@ -45,7 +45,6 @@ impl ImageTrait for Image {
         min: Scalar,
         max: Scalar,
     ) -> Self {
-        let mut rng = rand::thread_rng();
         Self(
             random_uniform::<Scalar>(
                 Dim4::new(&[
@@ -56,7 +55,7 @@ impl ImageTrait for Image {
                 ]),
                 &RandomEngine::new(
                     arrayfire::RandomEngineType::MERSENNE_GP11213,
-                    Some(rng.gen()),
+                    Some(gen_seed()),
                 ),
             ) * (max - min)
                 + constant!(min;
@@ -76,7 +75,6 @@ impl ImageTrait for Image {
         mean: Scalar,
         stddev: Scalar,
     ) -> Self {
-        let mut rng = rand::thread_rng();
         Self(
             random_normal::<Scalar>(
                 Dim4::new(&[
@@ -87,7 +85,7 @@ impl ImageTrait for Image {
                 ]),
                 &RandomEngine::new(
                     arrayfire::RandomEngineType::MERSENNE_GP11213,
-                    Some(rng.gen()),
+                    Some(gen_seed()),
                 ),
             ) * stddev
                 + constant!(mean;
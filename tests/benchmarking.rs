@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use jiro_nn::{
+    benchmarking::{
+        roc_auc, ClassificationMetrics, EpochEvaluation, ModelEvaluation, TrainingEvaluation,
+    },
+    linalg::Scalar,
+    metrics::{compute_metrics, Accuracy, F1Macro, LogLoss, Metric, PrAuc, RocAuc, R2},
+};
+
+#[test]
+fn test_binary_classification_metrics() {
+    let y_true = vec![vec![1.0], vec![0.0], vec![1.0], vec![0.0]];
+    let y_pred = vec![vec![0.9], vec![0.2], vec![0.4], vec![0.6]];
+
+    let metrics = ClassificationMetrics::compute(&y_true, &y_pred, None, 1).unwrap();
+
+    assert_eq!(metrics.accuracy, 0.5);
+    assert_eq!(metrics.top_k_accuracy, 0.5);
+    assert_eq!(metrics.confusion_matrix, vec![vec![1, 1], vec![1, 1]]);
+    assert_eq!(metrics.f1_macro, 0.5);
+    // positives are ranked 1st and 3rd
+    assert_eq!(metrics.roc_auc, Some(0.75));
+    assert!((metrics.pr_auc.unwrap() - (1.0 + 2.0 / 3.0) / 2.0).abs() < 1e-6);
+    let expected_log_loss = -[0.9, 0.8, 0.4, 0.4].iter().map(|p: &Scalar| p.ln()).sum::<Scalar>() / 4.0;
+    assert!((metrics.log_loss - expected_log_loss).abs() < 1e-5);
+}
+
+#[test]
+fn test_weighted_classification_metrics() {
+    // Only the correctly classified samples weigh, the one with a missing target is left out
+    let y_true = vec![vec![1.0], vec![0.0], vec![1.0], vec![0.0], vec![Scalar::NAN]];
+    let y_pred = vec![vec![0.9], vec![0.2], vec![0.4], vec![0.6], vec![0.5]];
+    let weights = [3.0, 1.0, 0.0, 0.0, 5.0];
+
+    let metrics = ClassificationMetrics::compute(&y_true, &y_pred, Some(&weights), 1).unwrap();
+
+    assert_eq!(metrics.accuracy, 1.0);
+    assert_eq!(metrics.top_k_accuracy, 1.0);
+    assert_eq!(metrics.f1_macro, 1.0);
+    // The confusion matrix still counts the samples
+    assert_eq!(metrics.confusion_matrix, vec![vec![1, 1], vec![1, 1]]);
+    let expected_log_loss = -(3.0 * (0.9 as Scalar).ln() + (0.8 as Scalar).ln()) / 4.0;
+    assert!((metrics.log_loss - expected_log_loss).abs() < 1e-5);
+
+    let values = compute_metrics(
+        &[Arc::new(Accuracy), Arc::new(F1Macro), Arc::new(LogLoss)],
+        &y_true,
+        &y_pred,
+        Some(&weights),
+    );
+    assert_eq!(values["accuracy"], 1.0);
+    assert_eq!(values["f1_macro"], 1.0);
+    assert!((values["log_loss"] - expected_log_loss).abs() < 1e-5);
+}
+
+#[test]
+#[should_panic(expected = "The class 2 of a sample is not one of the 2 predicted classes")]
+fn test_classification_metrics_reject_unknown_classes() {
+    ClassificationMetrics::compute(&[vec![1.0], vec![2.0]], &[vec![0.9], vec![0.8]], None, 1);
+}
+
+#[test]
+fn test_multiclass_classification_metrics() {
+    let y_true = vec![vec![0.0, 0.0, 1.0], vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]];
+    let y_pred = vec![vec![0.1, 0.3, 0.6], vec![0.3, 0.5, 0.2], vec![0.2, 0.7, 0.1]];
+
+    let metrics = ClassificationMetrics::compute(&y_true, &y_pred, None, 2).unwrap();
+
+    assert!((metrics.accuracy - 2.0 / 3.0).abs() < 1e-6);
+    assert_eq!(metrics.top_k_accuracy, 1.0);
+    assert_eq!(metrics.confusion_matrix, vec![vec![0, 1, 0], vec![0, 1, 0], vec![0, 0, 1]]);
+    assert_eq!(metrics.precision_micro, metrics.accuracy);
+}
+
+#[test]
+fn test_single_class_fold_has_no_curve_metrics() {
+    let y_true = vec![vec![1.0], vec![1.0], vec![1.0]];
+    let y_pred = vec![vec![0.9], vec![0.2], vec![0.6]];
+
+    let metrics = ClassificationMetrics::compute(&y_true, &y_pred, None, 1).unwrap();
+    assert!((metrics.accuracy - 2.0 / 3.0).abs() < 1e-6);
+    assert_eq!(metrics.roc_auc, None);
+    assert_eq!(metrics.pr_auc, None);
+
+    let values = compute_metrics(
+        &[Arc::new(Accuracy), Arc::new(RocAuc), Arc::new(PrAuc)],
+        &y_true,
+        &y_pred,
+        None,
+    );
+    assert_eq!(values.keys().collect::<Vec<_>>(), vec!["accuracy"]);
+
+    // The evaluation can be saved and loaded back
+    let mut fold = TrainingEvaluation::new_empty();
+    fold.add_epoch(
+        EpochEvaluation::new(0.1, 0.2, 0.0, -1.0)
+            .with_metrics(values)
+            .with_classification(metrics.clone()),
+    );
+    let mut eval = ModelEvaluation::new_empty();
+    eval.add_fold(fold);
+    let path = std::env::temp_dir().join("jiro_nn_test_single_class_eval.json");
+    eval.to_json_file(path.to_str().unwrap());
+    let loaded = ModelEvaluation::from_json_file(path.to_str().unwrap());
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(loaded.folds[0].get_final_classification(), Some(metrics));
+}
+
+#[test]
+fn test_empty_fold_has_no_classification_metrics() {
+    assert_eq!(ClassificationMetrics::compute(&[], &[], None, 1), None);
+    // Samples with missing targets are left out
+    assert_eq!(
+        ClassificationMetrics::compute(&[vec![Scalar::NAN]], &[vec![0.5]], None, 1),
+        None
+    );
+}
+
+#[test]
+fn test_roc_auc_averages_ties() {
+    assert_eq!(roc_auc(&[0.5, 0.5], &[true, false]), 0.5);
+}
+
+struct MaxError;
+
+impl Metric for MaxError {
+    fn name(&self) -> String {
+        "max_error".to_string()
+    }
+
+    fn compute(&self, y_true: &[Vec<Scalar>], y_pred: &[Vec<Scalar>], _weights: Option<&[Scalar]>) -> Scalar {
+        y_true
+            .iter()
+            .zip(y_pred.iter())
+            .map(|(y, p)| (y[0] - p[0]).abs())
+            .fold(0., Scalar::max)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn test_metrics_are_stored_by_name() {
+    let y_true = vec![vec![1.0], vec![0.0], vec![1.0], vec![0.0]];
+    let y_pred = vec![vec![0.9], vec![0.2], vec![0.4], vec![0.6]];
+    let metrics: Vec<Arc<dyn Metric>> = vec![Arc::new(R2), Arc::new(Accuracy), Arc::new(MaxError)];
+
+    let values = compute_metrics(&metrics, &y_true, &y_pred, None);
+    assert_eq!(values["accuracy"], 0.5);
+    assert!((values["max_error"] - 0.6).abs() < 1e-6);
+    assert!(!LogLoss.higher_is_better());
+
+    let eval = EpochEvaluation::new(0.1, 0.2, 0.0, -1.0).with_metrics(values.clone());
+    assert_eq!(eval.r2, values["r2"]);
+    assert_eq!(eval.get_metric("accuracy"), Some(0.5));
+    assert_eq!(eval.get_metric("f1_macro"), None);
+}
@@ -0,0 +1,265 @@
+use jiro_nn::{
+    benchmarking::EpochEvaluation,
+    loss::Losses,
+    model::network_model::NetworkModelBuilder,
+    trainers::callbacks::{EarlyStopping, TrainingCallback, TrainingControl},
+};
+#[cfg(feature = "data")]
+use jiro_nn::{
+    dataset::{Dataset, FeatureTags},
+    datatable::DataTable,
+    linalg::Scalar,
+    metrics::Mae,
+    model::{Model, ModelBuilder},
+    network::{
+        params::NetworkParams,
+        params_averaging::{ParamsAverager, ParamsAveraging},
+        Network,
+    },
+    trainers::{
+        callbacks::{CsvLogger, ModelCheckpoint},
+        split::SplitTraining,
+    },
+};
+
+#[test]
+fn test_early_stopping_restores_best_params() {
+    let x = vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0]];
+    let y = vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]];
+    let mut network = NetworkModelBuilder::new()
+        .full_dense(1)
+            .tanh()
+        .end()
+        .build()
+        .to_network(2);
+    let loss = Losses::MSE.to_loss();
+
+    let mut early_stopping = EarlyStopping::new(2, 0.01, true);
+    let mut best_params = None;
+    let mut controls = vec![];
+    for (epoch, test_loss) in [1.0, 0.5, 0.495, 0.7].into_iter().enumerate() {
+        network.train(epoch, &x, &y, &loss, 4);
+        if epoch == 1 {
+            best_params = Some(network.get_params());
+        }
+        let eval = EpochEvaluation::new(0.0, test_loss, 0.0, -1.0).with_test_targets_losses(vec![test_loss]);
+        controls.push(early_stopping.on_epoch_end(epoch, &eval, &mut network));
+    }
+
+    // 0.495 is not an improvement by more than 0.01
+    assert_eq!(
+        controls,
+        vec![
+            TrainingControl::Continue,
+            TrainingControl::Continue,
+            TrainingControl::Continue,
+            TrainingControl::Stop
+        ]
+    );
+    assert!(early_stopping.on_train_end(&mut network));
+    assert_eq!(network.get_params().0, best_params.unwrap().0);
+}
+
+// Learns y = 2x - 1 over 40 samples
+#[cfg(feature = "data")]
+fn model_and_data(epochs: usize) -> (Model, DataTable) {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    let rows = (0..40)
+        .map(|i| {
+            let x = i as Scalar / 39.;
+            vec![i as Scalar, x, 2. * x - 1.]
+        })
+        .collect::<Vec<_>>();
+    let data = DataTable::from_vectors(&["id", "x", "y"], &rows);
+    let model = ModelBuilder::new(dataset_config)
+        .epochs(epochs)
+        .batch_size(8)
+        .neural_network()
+            .full_dense(4)
+                .tanh()
+            .end()
+            .full_dense(1)
+                .linear()
+            .end()
+        .end()
+        .build();
+    (model, data)
+}
+
+#[cfg(feature = "data")]
+#[test]
+fn test_early_stopping_stops_the_trainer() {
+    let (model, data) = model_and_data(10);
+
+    // Nothing improves by more than the huge delta after the first epoch
+    let mut training = SplitTraining::new(0.75);
+    training
+        .seed(3)
+        .all_epochs_validation()
+        .callback(EarlyStopping::new(2, 1e6, false));
+    let (preds, eval) = training.run(&model, &data);
+
+    let epochs = &eval.folds[0].epochs;
+    assert_eq!(epochs.len(), 3);
+    assert!(epochs.iter().all(|epoch| epoch.test_loss_avg >= 0.));
+    assert_eq!(preds.num_rows(), 10);
+}
+
+#[cfg(feature = "data")]
+#[test]
+fn test_early_stopping_keeps_the_averaged_params() {
+    let (model, data) = model_and_data(10);
+    let dir = std::env::temp_dir();
+    let path = dir.join("jiro_nn_test_ema_checkpoint_{epoch}.json");
+
+    let mut training = SplitTraining::new(0.75);
+    training
+        .seed(3)
+        .all_epochs_validation()
+        .ema_params(0.5)
+        .callback(ModelCheckpoint::new(path.to_str().unwrap(), false))
+        .callback(EarlyStopping::new(2, 1e6, false));
+    let (_, eval) = training.run(&model, &data);
+    assert_eq!(eval.folds[0].epochs.len(), 3);
+
+    // The checkpoints hold the raw parameters of the epochs the training went through
+    let mut averager = ParamsAverager::new(ParamsAveraging::ExponentialMovingAverage { decay: 0.5 });
+    for epoch in 1..=3 {
+        let path = dir.join(format!("jiro_nn_test_ema_checkpoint_{}.json", epoch));
+        averager.update(epoch - 1, 10, NetworkParams::from_json(&path));
+        std::fs::remove_file(path).unwrap();
+    }
+    assert_eq!(training.take_model().0, averager.get_params().unwrap().0);
+}
+
+#[cfg(feature = "data")]
+struct StopAtBatch {
+    epoch: usize,
+    batch: usize,
+}
+
+#[cfg(feature = "data")]
+impl TrainingCallback for StopAtBatch {
+    fn on_batch_end(
+        &mut self,
+        epoch: usize,
+        batch: usize,
+        _loss: Scalar,
+        _network: &Network,
+    ) -> TrainingControl {
+        if epoch == self.epoch && batch == self.batch {
+            TrainingControl::Stop
+        } else {
+            TrainingControl::Continue
+        }
+    }
+}
+
+#[cfg(feature = "data")]
+#[test]
+fn test_callback_stopping_at_batch_end_stops_the_trainer() {
+    let (model, data) = model_and_data(10);
+
+    let mut training = SplitTraining::new(0.75);
+    training.seed(3).callback(StopAtBatch { epoch: 1, batch: 0 });
+    let (preds, eval) = training.run(&model, &data);
+
+    // The training stops at the end of the second epoch and is still validated
+    let fold = &eval.folds[0];
+    assert_eq!(fold.epochs.len(), 2);
+    assert!(fold.get_final_test_loss_avg() >= 0.);
+    assert_eq!(preds.num_rows(), 10);
+}
+
+#[cfg(feature = "data")]
+#[test]
+fn test_model_checkpoint_saves_each_epoch() {
+    let (model, data) = model_and_data(3);
+    let dir = std::env::temp_dir();
+    let path = dir.join("jiro_nn_test_checkpoint_{epoch}.json");
+
+    let mut training = SplitTraining::new(0.75);
+    training
+        .seed(3)
+        .callback(ModelCheckpoint::new(path.to_str().unwrap(), false));
+    training.run(&model, &data);
+
+    let checkpoints = (1..=3)
+        .map(|epoch| {
+            let path = dir.join(format!("jiro_nn_test_checkpoint_{}.json", epoch));
+            let params = NetworkParams::from_json(&path);
+            std::fs::remove_file(path).unwrap();
+            params
+        })
+        .collect::<Vec<_>>();
+
+    // The last checkpoint holds the final parameters
+    assert_ne!(checkpoints[0].0, checkpoints[2].0);
+    assert_eq!(checkpoints[2].0, training.take_model().0);
+}
+
+#[cfg(feature = "data")]
+#[test]
+fn test_model_checkpoint_best_only_skips_unvalidated_epochs() {
+    let (model, data) = model_and_data(3);
+    let dir = std::env::temp_dir();
+    let path = dir.join("jiro_nn_test_best_checkpoint_{epoch}.json");
+
+    // Without `all_epochs_validation`, only the last epoch has a validation loss
+    let mut training = SplitTraining::new(0.75);
+    training
+        .seed(3)
+        .callback(ModelCheckpoint::new(path.to_str().unwrap(), true));
+    training.run(&model, &data);
+
+    for epoch in 1..=2 {
+        assert!(!dir.join(format!("jiro_nn_test_best_checkpoint_{}.json", epoch)).exists());
+    }
+    let path = dir.join("jiro_nn_test_best_checkpoint_3.json");
+    let params = NetworkParams::from_json(&path);
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(params.0, training.take_model().0);
+}
+
+#[cfg(feature = "data")]
+#[test]
+fn test_csv_logger_writes_each_epoch() {
+    let (model, data) = model_and_data(3);
+    let path = std::env::temp_dir().join("jiro_nn_test_csv_logger.csv");
+
+    let mut training = SplitTraining::new(0.75);
+    training
+        .seed(3)
+        .all_epochs_validation()
+        .all_epochs_metrics()
+        .metric(Mae)
+        .callback(CsvLogger::new(&path));
+    let (_, eval) = training.run(&model, &data);
+
+    let csv = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "epoch,train_loss,test_loss_avg,test_loss_std,mae");
+    assert_eq!(lines.len(), 4);
+
+    for (i, (line, epoch)) in lines[1..].iter().zip(eval.folds[0].epochs.iter()).enumerate() {
+        let values = line
+            .split(',')
+            .map(|value| value.parse::<Scalar>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                (i + 1) as Scalar,
+                epoch.train_loss,
+                epoch.test_loss_avg,
+                epoch.test_loss_std,
+                epoch.get_metric("mae").unwrap(),
+            ]
+        );
+    }
+}
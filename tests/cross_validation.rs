@@ -0,0 +1,164 @@
+#![cfg(feature = "data")]
+
+use jiro_nn::{
+    benchmarking::ModelEvaluation,
+    dataset::{Dataset, FeatureTags},
+    datatable::DataTable,
+    linalg::Scalar,
+    metrics::{Mae, Metric, R2},
+    model::{Model, ModelBuilder},
+    random::set_seed,
+    trainers::{cross_validation::CrossValidation, kfolds::KFolds, nested::NestedCrossValidation},
+};
+
+fn model_and_data() -> (Model, DataTable) {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("group"), FeatureTags::Group],
+        &[FeatureTags::Name("label"), FeatureTags::Predicted],
+    ]);
+    let rows = (0..12)
+        .map(|i| vec![i as Scalar, i as Scalar, (i / 2) as Scalar, (i % 3 == 0) as u8 as Scalar])
+        .collect::<Vec<_>>();
+    let data = DataTable::from_vectors(&["id", "x", "group", "label"], &rows);
+    (ModelBuilder::new(dataset_config).build(), data)
+}
+
+#[test]
+fn test_cross_validation_folds() {
+    let (model, data) = model_and_data();
+
+    let folds = CrossValidation::StratifiedKFold { k: 2 }.folds(&model, &data);
+    assert_eq!(folds.len(), 2);
+    for (_, validation) in folds.iter() {
+        let positives = validation.iter().filter(|i| *i % 3 == 0).count();
+        assert_eq!(positives, 2);
+    }
+
+    let folds = CrossValidation::GroupKFold { k: 3 }.folds(&model, &data);
+    for (train, validation) in folds.iter() {
+        assert_eq!(validation.len(), 4);
+        assert!(validation.iter().all(|v| train.iter().all(|t| t / 2 != v / 2)));
+    }
+
+    let folds = CrossValidation::TimeSeriesSplit { splits: 3 }.folds(&model, &data);
+    assert_eq!(folds[0], ((0..3).collect(), (3..6).collect()));
+    assert_eq!(folds[2], ((0..9).collect(), (9..12).collect()));
+}
+
+#[test]
+#[should_panic(expected = "The fold 0 of the TimeSeriesSplit { splits: 3 } cross validation of 3 rows has no training or no validation row")]
+fn test_time_series_split_rejects_too_few_rows() {
+    let (model, data) = model_and_data();
+    CrossValidation::TimeSeriesSplit { splits: 3 }.folds(&model, &data.take_rows(&[0, 1, 2]));
+}
+
+#[test]
+#[should_panic(expected = "has no training or no validation row")]
+fn test_k_fold_rejects_more_folds_than_rows() {
+    let (model, data) = model_and_data();
+    CrossValidation::KFold { k: 13 }.folds(&model, &data);
+}
+
+#[test]
+fn test_nested_cross_validation_records_selected_candidates() {
+    let (model, data) = model_and_data();
+    let candidates = [1, 20]
+        .into_iter()
+        .map(|epochs| {
+            ModelBuilder::new(model.dataset_config.clone())
+                .epochs(epochs)
+                .batch_size(4)
+                .neural_network()
+                    .full_dense(1)
+                        .sigmoid()
+                    .end()
+                .end()
+                .build()
+        })
+        .collect::<Vec<_>>();
+
+    let mut nested = NestedCrossValidation::new(
+        CrossValidation::KFold { k: 3 },
+        KFolds::new(2),
+        candidates.clone(),
+    );
+    nested.seed(42);
+    let (preds, eval) = nested.run(&data);
+
+    assert_eq!(preds.num_rows(), 12);
+    assert_eq!(eval.folds.len(), 3);
+    assert_eq!(eval.selected_candidates.len(), 3);
+    for selected in eval.selected_candidates.iter() {
+        assert_eq!(selected.model_hash, candidates[selected.candidate].hashed_repr());
+        assert_eq!(selected.metric, "r2");
+    }
+}
+
+#[test]
+fn test_kfolds_keeps_the_folds_order() {
+    let (model, data) = model_and_data();
+    let model = ModelBuilder::new(model.dataset_config.clone())
+        .epochs(5)
+        .batch_size(4)
+        .neural_network()
+            .full_dense(1)
+                .sigmoid()
+            .end()
+        .end()
+        .build();
+
+    set_seed(7);
+    let folds = CrossValidation::KFold { k: 3 }.folds(&model, &data);
+
+    let mut kfolds = KFolds::new(3);
+    kfolds.seed(7);
+    let (preds, eval) = kfolds.run(&model, &data);
+    let mut kfolds = KFolds::new(3);
+    kfolds.seed(7);
+    let (_, other_eval) = kfolds.run(&model, &data);
+
+    // Each fold's predictions come in the order of the folds, whichever thread ends first
+    let ids = preds.column_to_vector("id");
+    for (i, (_, validation)) in folds.iter().enumerate() {
+        let mut fold_ids = ids[i * 4..(i + 1) * 4].iter().map(|id| *id as usize).collect::<Vec<_>>();
+        fold_ids.sort();
+        let mut validation = validation.clone();
+        validation.sort();
+        assert_eq!(fold_ids, validation);
+    }
+    let losses = |eval: &ModelEvaluation| {
+        eval.folds.iter().map(|fold| fold.get_final_test_loss_avg()).collect::<Vec<_>>()
+    };
+    assert_eq!(losses(&eval), losses(&other_eval));
+}
+
+#[test]
+fn test_kfolds_fills_r2_without_the_r2_metric() {
+    let (model, data) = model_and_data();
+    let model = ModelBuilder::new(model.dataset_config.clone())
+        .epochs(3)
+        .batch_size(4)
+        .neural_network()
+            .full_dense(1)
+                .sigmoid()
+            .end()
+        .end()
+        .build();
+
+    let mut kfolds = KFolds::new(2);
+    kfolds.seed(0).metric(Mae);
+    let (preds, eval) = kfolds.run(&model, &data);
+
+    let final_epoch = eval.folds[0].get_final_epoch();
+    assert_eq!(final_epoch.get_metric("r2"), None);
+    assert!(final_epoch.r2.is_finite() && final_epoch.r2 != -1.0);
+
+    // It is the R2 score of the first fold's predictions, which come first
+    let fold_preds = preds.column_to_vector("label")[..6].iter().map(|p| vec![*p]).collect::<Vec<_>>();
+    let ids = preds.column_to_vector("id");
+    let labels = data.column_to_vector("label");
+    let fold_y = ids[..6].iter().map(|id| vec![labels[*id as usize]]).collect::<Vec<_>>();
+    assert!((final_epoch.r2 - R2.compute(&fold_y, &fold_preds, None)).abs() < 1e-5);
+}
@@ -0,0 +1,146 @@
+#![cfg(feature = "data")]
+
+use std::{
+    io::{ErrorKind, Write},
+    net::TcpListener,
+    thread,
+};
+
+use jiro_nn::{
+    dataset::{Dataset, FeatureTags},
+    datatable::DataTable,
+    linalg::Scalar,
+    model::ModelBuilder,
+    optimizer::{sgd::SGD, Optimizers},
+    random::set_seed,
+    trainers::distributed::{ParameterServer, SgdMode, Worker},
+};
+
+#[test]
+fn test_distributed_training_reduces_loss() {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    let rows = (0..60)
+        .map(|i| vec![i as Scalar, i as Scalar / 60., 2. * i as Scalar / 60.])
+        .collect::<Vec<_>>();
+    let data = DataTable::from_vectors(&["id", "x", "y"], &rows);
+    let model = ModelBuilder::new(dataset_config)
+        .epochs(15)
+        .batch_size(4)
+        .seed(0)
+        .neural_network()
+            .full_dense(4)
+                .tanh()
+            .end()
+            .full_dense(1)
+                .linear()
+            .end()
+        .end()
+        .build();
+
+    for mode in [SgdMode::Sync, SgdMode::Async] {
+        let server = ParameterServer::bind(model.clone(), mode, 3, "127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        let (params, losses) = thread::scope(|scope| {
+            for _ in 0..3 {
+                scope.spawn(|| Worker::run(address, &model, &data).unwrap());
+            }
+            server.run().unwrap()
+        });
+
+        assert_eq!(losses.len(), 15);
+        assert!(losses.iter().all(|l| l.is_finite()));
+        assert!(losses[14] < losses[0], "{:?}: {:?}", mode, losses);
+        assert_eq!(params.0.len(), 2);
+    }
+}
+
+#[test]
+fn test_sync_distributed_training_matches_single_thread() {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("weight"), FeatureTags::SampleWeight],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    // The first worker's samples have no weight
+    let rows = (0..30)
+        .map(|i| {
+            let x = i as Scalar / 30.;
+            let weight = if i % 3 == 0 { 0. } else { (i % 4) as Scalar };
+            vec![i as Scalar, x, weight, 2. * x - 1.]
+        })
+        .collect::<Vec<_>>();
+    let data = DataTable::from_vectors(&["id", "x", "weight", "y"], &rows);
+    // Without a batch size, each step is made of all the samples, whatever their order
+    let model = ModelBuilder::new(dataset_config)
+        .epochs(5)
+        .seed(0)
+        .neural_network()
+            .full_dense(4)
+                .tanh()
+                .optimizer(Optimizers::SGD(SGD::with_const_lr(0.01)))
+            .end()
+            .full_dense(1)
+                .linear()
+                .optimizer(Optimizers::SGD(SGD::with_const_lr(0.01)))
+            .end()
+        .end()
+        .build();
+
+    let server = ParameterServer::bind(model.clone(), SgdMode::Sync, 3, "127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+    let (params, losses) = thread::scope(|scope| {
+        for _ in 0..3 {
+            scope.spawn(|| Worker::run(address, &model, &data).unwrap());
+        }
+        server.run().unwrap()
+    });
+
+    set_seed(0);
+    let mut network = model.to_network();
+    let x = rows.iter().map(|row| vec![row[1]]).collect::<Vec<_>>();
+    let y = rows.iter().map(|row| vec![row[3]]).collect::<Vec<_>>();
+    let weights = rows.iter().map(|row| row[2]).collect::<Vec<_>>();
+    let loss = model.loss.to_loss();
+    for (epoch, distributed_loss) in losses.iter().enumerate() {
+        let loss = network.train_weighted_with_hook(epoch, &x, &y, Some(&weights), &loss, 30, &mut |_, _, _| false);
+        assert!((loss - distributed_loss).abs() < 1e-4);
+    }
+
+    let expected = network.get_params().0.concat().concat();
+    for (a, b) in expected.iter().zip(params.0.concat().concat().iter()) {
+        assert!((a - b).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn test_worker_rejects_oversized_messages() {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    let data = DataTable::from_vectors(&["id", "x", "y"], &vec![vec![0., 0., 0.]]);
+    let model = ModelBuilder::new(dataset_config)
+        .neural_network()
+            .full_dense(1)
+                .linear()
+            .end()
+        .end()
+        .build();
+
+    // A server announcing a message far too long to be allocated
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let error = thread::scope(|scope| {
+        let worker = scope.spawn(|| Worker::run(address, &model, &data));
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(&u64::MAX.to_le_bytes()).unwrap();
+        worker.join().unwrap().unwrap_err()
+    });
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
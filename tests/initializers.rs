@@ -0,0 +1,89 @@
+use assert_float_eq::*;
+use jiro_nn::{
+    initializers::Initializers,
+    linalg::{Matrix, MatrixTrait, Scalar},
+    random::set_seed,
+};
+
+fn mean_and_variance(mat: &Matrix) -> (Scalar, Scalar) {
+    let values = mat
+        .get_data_row_leading()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    let n = values.len() as Scalar;
+    let mean = values.iter().sum::<Scalar>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<Scalar>() / n;
+    (mean, variance)
+}
+
+fn assert_variance(initializer: Initializers, nrow: usize, ncol: usize, expected: Scalar) {
+    let mat = initializer.gen_matrix(nrow, ncol);
+    assert_eq!(mat.dim(), (nrow, ncol));
+    let (mean, variance) = mean_and_variance(&mat);
+    assert!(mean.abs() < 0.01, "{:?}: mean {}", initializer, mean);
+    assert!(
+        (variance / expected - 1.).abs() < 0.1,
+        "{:?}: variance {} instead of {}",
+        initializer,
+        variance,
+        expected
+    );
+}
+
+#[test]
+fn test_variance_scaling_initializers() {
+    set_seed(42);
+    // 200 outputs, 400 inputs
+    let (fan_out, fan_in) = (200., 400.);
+    assert_variance(Initializers::GlorotUniform, 200, 400, 2. / (fan_in + fan_out));
+    assert_variance(Initializers::GlorotNormal, 200, 400, 2. / (fan_in + fan_out));
+    assert_variance(Initializers::HeUniform, 200, 400, 2. / fan_in);
+    assert_variance(Initializers::HeNormal, 200, 400, 2. / fan_in);
+    assert_variance(Initializers::LeCunUniform, 200, 400, 1. / fan_in);
+    assert_variance(Initializers::LeCunNormal, 200, 400, 1. / fan_in);
+}
+
+#[test]
+fn test_truncated_normal_initializer() {
+    set_seed(42);
+    let mat = Initializers::TruncatedNormal { mean: 1., std_dev: 0.5 }.gen_matrix(100, 50);
+    assert_eq!(mat.dim(), (100, 50));
+    assert!(mat
+        .get_data_row_leading()
+        .iter()
+        .flatten()
+        .all(|v| (v - 1.).abs() <= 1.));
+    let (mean, _) = mean_and_variance(&mat);
+    assert!((mean - 1.).abs() < 0.05);
+}
+
+#[test]
+fn test_constant_initializer() {
+    let mat = Initializers::Constant(0.3).gen_vector(5);
+    assert_eq!(mat.dim(), (5, 1));
+    assert!(mat.get_data_row_leading().iter().flatten().all(|v| *v == 0.3));
+}
+
+#[test]
+fn test_orthogonal_initializer() {
+    set_seed(42);
+    for (nrow, ncol) in [(8, 3), (3, 8), (5, 5)] {
+        let mat = Initializers::Orthogonal.gen_matrix(nrow, ncol);
+        assert_eq!(mat.dim(), (nrow, ncol));
+
+        // The fewer of rows or columns are orthonormal vectors
+        let vectors = if nrow >= ncol {
+            mat.get_data_col_leading()
+        } else {
+            mat.get_data_row_leading()
+        };
+        for (i, a) in vectors.iter().enumerate() {
+            for (j, b) in vectors.iter().enumerate() {
+                let dot: Scalar = a.iter().zip(b).map(|(a, b)| a * b).sum();
+                let expected = if i == j { 1. } else { 0. };
+                assert_float_absolute_eq!(dot, expected, 0.0001);
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use assert_float_eq::*;
+use jiro_nn::{
+    learning_rate::{exponential_decay::ExponentialDecay, LearningRateSchedule},
+    linalg::Scalar,
+};
+
+#[test]
+fn test_exponential_decay() {
+    let decay = ExponentialDecay::new(0.1, 10., 0.5, false);
+    assert_float_relative_eq!(decay.get_learning_rate(0), 0.1, 0.00001);
+    assert_float_relative_eq!(decay.get_learning_rate(5), 0.1 * (0.5 as Scalar).sqrt(), 0.00001);
+    assert_float_relative_eq!(decay.get_learning_rate(10), 0.05, 0.00001);
+    assert_float_relative_eq!(decay.get_learning_rate(20), 0.025, 0.00001);
+}
+
+#[test]
+fn test_exponential_decay_staircase() {
+    let decay = ExponentialDecay::new(0.1, 10., 0.5, true);
+    assert_float_relative_eq!(decay.get_learning_rate(0), 0.1, 0.00001);
+    assert_float_relative_eq!(decay.get_learning_rate(9), 0.1, 0.00001);
+    assert_float_relative_eq!(decay.get_learning_rate(10), 0.05, 0.00001);
+    assert_float_relative_eq!(decay.get_learning_rate(19), 0.05, 0.00001);
+}
+
+#[test]
+fn test_exponential_growth_schedule() {
+    // A decay rate above 1 makes the learning rate grow, as in the learning rate range test
+    let schedule = LearningRateSchedule::ExponentialDecay(ExponentialDecay::new(1e-4, 4., 1e4, false));
+    assert_float_relative_eq!(schedule.get_learning_rate(0), 1e-4, 0.0001);
+    assert_float_relative_eq!(schedule.get_learning_rate(2), 1e-2, 0.0001);
+    assert_float_relative_eq!(schedule.get_learning_rate(4), 1., 0.0001);
+}
@@ -0,0 +1,186 @@
+use jiro_nn::{
+    linalg::{MatrixTrait, Scalar},
+    loader::{DataLoader, VecLoader},
+    loss::Losses,
+    model::network_model::NetworkModelBuilder,
+    network::Network,
+    random::set_seed,
+};
+#[cfg(feature = "data")]
+use jiro_nn::{
+    dataset::{Dataset, FeatureTags},
+    datatable::DataTable,
+    model::{Model, ModelBuilder},
+    trainers::{kfolds::KFolds, split::SplitTraining},
+};
+
+fn samples() -> (Vec<Vec<Scalar>>, Vec<Vec<Scalar>>) {
+    let x = (0..10).map(|i| vec![i as Scalar / 10., 1. - i as Scalar / 10.]).collect();
+    let y = (0..10).map(|i| vec![(i % 2) as Scalar]).collect();
+    (x, y)
+}
+
+fn network() -> Network {
+    set_seed(0);
+    NetworkModelBuilder::new()
+        .full_dense(3)
+            .tanh()
+        .end()
+        .full_dense(1)
+            .sigmoid()
+        .end()
+        .build()
+        .to_network(2)
+}
+
+#[test]
+fn test_vec_loader_shuffles_and_drops_last_batch() {
+    let (x, y) = samples();
+    let mut loader = VecLoader::new(x, y, 3).shuffle().drop_last();
+
+    set_seed(1);
+    let batches = loader.batches().collect::<Vec<_>>();
+    assert_eq!(batches.len(), 3);
+    assert!(batches.iter().all(|(x, y)| x.dim() == (2, 3) && y.dim() == (1, 3)));
+
+    let mut first_inputs = batches
+        .iter()
+        .flat_map(|(x, _)| x.get_data_col_leading().into_iter().map(|sample| sample[0]))
+        .collect::<Vec<_>>();
+    first_inputs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    first_inputs.dedup();
+    assert_eq!(first_inputs.len(), 9);
+
+    // The prefetching thread yields the same batches
+    let mut loader = loader.prefetch(2);
+    set_seed(1);
+    let prefetched = loader.batches().collect::<Vec<_>>();
+    for ((x, y), (px, py)) in batches.iter().zip(prefetched.iter()) {
+        assert_eq!(x.get_data_col_leading(), px.get_data_col_leading());
+        assert_eq!(y.get_data_col_leading(), py.get_data_col_leading());
+    }
+}
+
+#[test]
+fn test_train_loader_matches_train() {
+    let (x, y) = samples();
+    let loss = Losses::BCE.to_loss();
+
+    let mut vec_network = network();
+    let losses = (0..3).map(|e| vec_network.train(e, &x, &y, &loss, 4)).collect::<Vec<_>>();
+
+    let mut loader_network = network();
+    let mut loader = VecLoader::new(x, y, 4).prefetch(1);
+    let loader_losses = (0..3)
+        .map(|e| loader_network.train_loader(e, &mut loader, &loss))
+        .collect::<Vec<_>>();
+
+    assert_eq!(losses, loader_losses);
+    assert_eq!(vec_network.get_params().0, loader_network.get_params().0);
+}
+
+// Learns y = 2x - 1 over 40 rows
+#[cfg(feature = "data")]
+fn model_and_data(batch_size: Option<usize>) -> (Model, DataTable) {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    let rows = (0..40)
+        .map(|i| {
+            let x = i as Scalar / 39.;
+            vec![i as Scalar, x, 2. * x - 1.]
+        })
+        .collect::<Vec<_>>();
+    let data = DataTable::from_vectors(&["id", "x", "y"], &rows);
+    let mut model = ModelBuilder::new(dataset_config)
+        .epochs(20)
+        .neural_network()
+            .full_dense(4)
+                .tanh()
+            .end()
+            .full_dense(1)
+                .linear()
+            .end()
+        .end()
+        .build();
+    model.batch_size = batch_size;
+    (model, data)
+}
+
+#[cfg(feature = "data")]
+#[test]
+fn test_kfolds_trains_each_fold_from_a_loader() {
+    let (model, data) = model_and_data(Some(8));
+
+    let mut kfolds = KFolds::new(4);
+    kfolds.seed(0);
+    let (preds, eval) = kfolds.run_loader(&model, &data, |train| {
+        assert_eq!(train.num_rows(), 30);
+        VecLoader::from_table(&model, train, 8).shuffle()
+    });
+
+    assert_eq!(preds.num_rows(), 40);
+    assert_eq!(eval.get_n_folds(), 4);
+    assert!(eval.folds.iter().all(|fold| fold.epochs.len() == 20));
+    let train_losses = eval.epochs_avg_train_loss();
+    assert!(train_losses[19] < train_losses[0]);
+}
+
+#[cfg(feature = "data")]
+#[test]
+#[should_panic(expected = "The model's batch size must be the loader's")]
+fn test_loader_training_rejects_another_batch_size() {
+    let (model, data) = model_and_data(Some(8));
+    let (train, validation) = data.split(30, 10);
+    let mut loader = VecLoader::from_table(&model, &train, 4);
+    SplitTraining::new(0.75).run_loader(&model, &mut loader, &validation);
+}
+
+#[cfg(feature = "data")]
+#[test]
+#[should_panic(expected = "The loaders don't apply the class weights")]
+fn test_loader_training_rejects_class_weights() {
+    let (mut model, data) = model_and_data(None);
+    model.class_weights = Some(vec![1., 2.]);
+    let (train, validation) = data.split(30, 10);
+    let mut loader = VecLoader::from_table(&model, &train, 8);
+    SplitTraining::new(0.75).run_loader(&model, &mut loader, &validation);
+}
+
+#[cfg(feature = "images")]
+#[test]
+fn test_image_folder_loader_loads_classes() {
+    use jiro_nn::loader::ImageFolderLoader;
+
+    let root = std::env::temp_dir().join("jiro_nn_test_image_folder");
+    for (class, value) in [("cat", 0u8), ("dog", 255u8)] {
+        std::fs::create_dir_all(root.join(class)).unwrap();
+        let image = image::GrayImage::from_fn(2, 2, |x, y| image::Luma([if x == 1 && y == 0 { 51 } else { value }]));
+        image.save(root.join(class).join("0.png")).unwrap();
+    }
+
+    let mut loader = ImageFolderLoader::new(&root, 2, 2).unwrap().grayscale();
+    assert_eq!(loader.classes, vec!["cat", "dog"]);
+    let (x, y) = loader.batches().next().unwrap();
+    // Columns first like `Image::flatten`, the pixel of the first row and second column is third
+    assert_eq!(x.get_data_col_leading(), vec![vec![0., 0., 0.2, 0.], vec![1., 1., 0.2, 1.]]);
+    assert_eq!(y.get_data_col_leading(), vec![vec![1., 0.], vec![0., 1.]]);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(feature = "images")]
+#[test]
+fn test_image_folder_loader_rejects_missing_folders_and_corrupt_images() {
+    use jiro_nn::loader::ImageFolderLoader;
+
+    let root = std::env::temp_dir().join("jiro_nn_test_corrupt_image_folder");
+    assert!(ImageFolderLoader::new(&root, 2, 2).is_err());
+
+    std::fs::create_dir_all(root.join("cat")).unwrap();
+    std::fs::write(root.join("cat").join("0.png"), b"not an image").unwrap();
+    let error = ImageFolderLoader::new(&root, 2, 2).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    std::fs::remove_dir_all(root).unwrap();
+}
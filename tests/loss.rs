@@ -0,0 +1,266 @@
+use jiro_nn::{
+    linalg::{Matrix, MatrixTrait, Scalar},
+    loss::{register_loss, Loss, LossFunction, Losses},
+    model::{network_model::NetworkModelBuilder, Model},
+};
+
+#[test]
+fn test_sparse_categorical_cross_entropy_matches_one_hot() {
+    // 3 classes, 2 samples (one per column)
+    let y_pred = Matrix::from_column_leading_vector2(&vec![vec![0.7, 0.2, 0.1], vec![0.1, 0.1, 0.8]]);
+    let y_one_hot = Matrix::from_column_leading_vector2(&vec![vec![1., 0., 0.], vec![0., 0., 1.]]);
+    let y_sparse = Matrix::from_column_leading_vector2(&vec![vec![0.], vec![2.]]);
+
+    let cce = Losses::CategoricalCrossEntropy { label_smoothing: 0.1 }.to_loss();
+    let sparse_cce = Losses::SparseCategoricalCrossEntropy { label_smoothing: 0.1 }.to_loss();
+
+    let expected = cce.loss(&y_one_hot, &y_pred);
+    assert!((sparse_cce.loss(&y_sparse, &y_pred) - expected).abs() < 1e-6);
+
+    let no_smoothing = Losses::CategoricalCrossEntropy { label_smoothing: 0. }.to_loss();
+    let expected = -((0.7 as Scalar).ln() + (0.8 as Scalar).ln()) / 2.;
+    assert!((no_smoothing.loss(&y_one_hot, &y_pred) - expected).abs() < 1e-6);
+}
+
+#[test]
+fn test_categorical_cross_entropy_gradient_does_not_depend_on_batch_size() {
+    let sample = vec![0.7, 0.2, 0.1];
+    let target = vec![1., 0., 0.];
+    let y_pred = Matrix::from_column_leading_vector2(&vec![sample.clone()]);
+    let y_true = Matrix::from_column_leading_vector2(&vec![target.clone()]);
+    let batch_pred = Matrix::from_column_leading_vector2(&vec![sample.clone(), sample]);
+    let batch_true = Matrix::from_column_leading_vector2(&vec![target.clone(), target]);
+
+    // Like the MSE, the gradient of each sample is summed by the layers
+    let loss = Losses::CategoricalCrossEntropy { label_smoothing: 0. }.to_loss();
+    let prime = loss.loss_prime(&y_true, &y_pred).get_column(0);
+    assert!((prime[0] + 1. / 0.7).abs() < 1e-5 && prime[1] == 0. && prime[2] == 0.);
+    assert_eq!(loss.loss_prime(&batch_true, &batch_pred).get_column(1), prime);
+
+    let softmax_prime = loss.softmax_loss_prime(&batch_true, &batch_pred).unwrap();
+    for j in 0..2 {
+        let column = softmax_prime.get_column(j);
+        assert!((column[0] + 0.3).abs() < 1e-6 && (column[1] - 0.2).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_softmax_classifier_learns_with_categorical_cross_entropy() {
+    let x = vec![vec![0., 0.], vec![1., 0.], vec![0., 1.], vec![1., 1.]];
+    // the class is the first input
+    let y = vec![vec![1., 0.], vec![0., 1.], vec![1., 0.], vec![0., 1.]];
+
+    let mut network = NetworkModelBuilder::new()
+        .full_dense(8)
+            .tanh()
+            .adam()
+        .end()
+        .full_dense(2)
+            .softmax()
+            .adam()
+        .end()
+        .build()
+        .to_network(2);
+    let loss = Losses::CategoricalCrossEntropy { label_smoothing: 0. }.to_loss();
+
+    let first_loss = network.train(0, &x, &y, &loss, 4);
+    let mut last_loss = first_loss;
+    for epoch in 1..300 {
+        last_loss = network.train(epoch, &x, &y, &loss, 4);
+    }
+
+    assert!(last_loss < first_loss / 2.);
+}
+
+// Like the MSE, the regression losses return the derivative of each element's loss,
+// which is the derivative of their mean times the number of elements.
+fn assert_loss_prime_matches_finite_differences(losses: Losses, y_true: &Matrix, y_pred: &Matrix) {
+    let loss = losses.to_loss();
+    let prime = loss.loss_prime(y_true, y_pred);
+    let (nrow, ncol) = y_pred.dim();
+    let h = 1e-2;
+
+    for i in 0..nrow {
+        for j in 0..ncol {
+            let mut plus = y_pred.clone();
+            *plus.index_mut(i, j) += h;
+            let mut minus = y_pred.clone();
+            *minus.index_mut(i, j) -= h;
+            let numerical = (loss.loss(y_true, &plus) - loss.loss(y_true, &minus)) / (2. * h)
+                * (nrow * ncol) as Scalar;
+            assert!(
+                (prime.index(i, j) - numerical).abs() < 1e-2,
+                "{:?} at ({}, {}): {} != {}",
+                losses,
+                i,
+                j,
+                prime.index(i, j),
+                numerical
+            );
+        }
+    }
+}
+
+#[test]
+fn test_regression_losses_derivatives() {
+    let y_true = Matrix::from_column_leading_vector2(&vec![vec![1.0, -2.0], vec![0.5, 3.0], vec![0.0, 0.0]]);
+    let y_pred = Matrix::from_column_leading_vector2(&vec![vec![1.3, 2.0], vec![-0.5, 2.9], vec![0.2, -4.0]]);
+
+    assert_loss_prime_matches_finite_differences(Losses::MAE, &y_true, &y_pred);
+    assert_loss_prime_matches_finite_differences(Losses::Huber { delta: 1.0 }, &y_true, &y_pred);
+    assert_loss_prime_matches_finite_differences(Losses::LogCosh, &y_true, &y_pred);
+    assert_loss_prime_matches_finite_differences(Losses::Quantile { quantiles: vec![0.9] }, &y_true, &y_pred);
+}
+
+#[test]
+fn test_multiple_quantiles_loss() {
+    // one target, two samples
+    let y_true = Matrix::from_column_leading_vector2(&vec![vec![1.0], vec![2.0]]);
+    // quantiles 0.1 and 0.9 of the target for each sample
+    let y_pred = Matrix::from_column_leading_vector2(&vec![vec![0.0, 2.0], vec![3.0, 4.0]]);
+
+    let loss = Losses::Quantile { quantiles: vec![0.1, 0.9] }.to_loss();
+
+    // errors (y - pred): [1, -1], [-1, -2]
+    // losses: [0.1, 0.1], [0.9, 0.2]
+    let expected = (0.1 + 0.1 + 0.9 + 0.2) / 4.;
+    assert!((loss.loss(&y_true, &y_pred) - expected).abs() < 1e-6);
+    assert_loss_prime_matches_finite_differences(Losses::Quantile { quantiles: vec![0.1, 0.9] }, &y_true, &y_pred);
+}
+
+struct ScaledMSE {
+    scale: Scalar,
+}
+
+impl LossFunction for ScaledMSE {
+    fn loss(&self, y_true: &Matrix, y_pred: &Matrix) -> Scalar {
+        y_pred.component_sub(y_true).square().mean() * self.scale
+    }
+
+    fn loss_prime(&self, y_true: &Matrix, y_pred: &Matrix) -> Matrix {
+        y_pred.component_sub(y_true).scalar_mul(2. * self.scale)
+    }
+}
+
+#[test]
+fn test_custom_loss_is_serialized_by_name_and_params() {
+    register_loss("scaled_mse", |params| {
+        Box::new(ScaledMSE {
+            scale: params["scale"].as_f64().unwrap() as Scalar,
+        })
+    });
+
+    let model = NetworkModelBuilder::new()
+        .full_dense(1)
+            .linear()
+        .end()
+        .build();
+    let model_json = serde_json::json!({
+        "epochs": 1,
+        "loss": Losses::custom("scaled_mse", serde_json::json!({ "scale": 3.0 })),
+        "batch_size": null,
+        "dataset_config": { "features": [] },
+        "network": model,
+    });
+    let model = Model::from_json_string(model_json.to_string());
+    let model = Model::from_json_string(serde_json::to_string(&model).unwrap());
+
+    let y_true = Matrix::from_column_leading_vector2(&vec![vec![1.0], vec![2.0]]);
+    let y_pred = Matrix::from_column_leading_vector2(&vec![vec![2.0], vec![2.0]]);
+    assert!((model.loss.to_loss().loss(&y_true, &y_pred) - 1.5).abs() < 1e-6);
+}
+
+#[test]
+fn test_custom_loss_without_softmax_derivative_trains_softmax_network() {
+    let x = vec![vec![0., 0.], vec![1., 0.], vec![0., 1.], vec![1., 1.]];
+    let y = vec![vec![1., 0.], vec![0., 1.], vec![1., 0.], vec![0., 1.]];
+
+    let mut network = NetworkModelBuilder::new()
+        .full_dense(2)
+            .softmax()
+        .end()
+        .build()
+        .to_network(2);
+    let loss = Loss::new(ScaledMSE { scale: 1.0 });
+
+    let y_true = Matrix::from_column_leading_vector2(&y);
+    let y_pred = Matrix::from_column_leading_vector2(&network.predict_many(&x, 4));
+    assert!(loss.softmax_loss_prime(&y_true, &y_pred).is_none());
+
+    // The gradient goes through the softmax's jacobian instead of the fused derivative
+    let error = network.train(0, &x, &y, &loss, 4);
+    assert!(error.is_finite());
+}
+
+#[test]
+fn test_weighted_loss_ignores_zero_weighted_samples() {
+    let y_true = Matrix::from_column_leading_vector2(&vec![vec![1.0], vec![2.0]]);
+    let y_pred = Matrix::from_column_leading_vector2(&vec![vec![3.0], vec![10.0]]);
+    let loss = Losses::MSE.to_loss();
+
+    assert_eq!(loss.weighted_loss(&y_true, &y_pred, &[2.0, 0.0]), 4.0);
+    assert_eq!(loss.weighted_loss(&y_true, &y_pred, &[1.0, 1.0]), loss.loss(&y_true, &y_pred));
+
+    let gradient = loss.weighted_loss_prime(&y_true, &y_pred, &[2.0, 0.0]);
+    assert_eq!(gradient.get_column(1), vec![0.0]);
+    assert_eq!(gradient.get_column(0), vec![8.0]);
+}
+
+#[test]
+fn test_missing_targets_are_masked() {
+    let nan = Scalar::NAN;
+    let y_true = Matrix::from_column_leading_vector2(&vec![vec![1.0, nan], vec![2.0, 4.0]]);
+    let y_pred = Matrix::from_column_leading_vector2(&vec![vec![3.0, 7.0], vec![2.0, 5.0]]);
+    let loss = Losses::MSE.to_loss();
+
+    // Only the 3 known targets count: (4 + 0 + 1) / 3
+    assert!((loss.loss(&y_true, &y_pred) - 5.0 / 3.0).abs() < 1e-6);
+
+    let gradient = loss.loss_prime(&y_true, &y_pred);
+    assert_eq!(gradient.index(1, 0), 0.0);
+    assert!(!gradient.index(0, 0).is_nan());
+
+    let targets_losses = loss.targets_losses(&y_true, &y_pred);
+    assert!((targets_losses[0] - 2.0).abs() < 1e-6);
+    assert!((targets_losses[1] - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_focal_loss() {
+    let y_true = Matrix::from_column_leading_vector2(&vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+    let y_pred = Matrix::from_column_leading_vector2(&vec![vec![0.8, 0.3], vec![0.1, 0.4]]);
+
+    assert_loss_prime_matches_finite_differences(Losses::Focal { gamma: 2.0, alpha: 0.25 }, &y_true, &y_pred);
+
+    // Without focusing nor weighting, it is half the binary cross entropy
+    let focal = Losses::Focal { gamma: 0.0, alpha: 0.5 }.to_loss().loss(&y_true, &y_pred);
+    let bce = Losses::BCE.to_loss().loss(&y_true, &y_pred);
+    assert!((focal - bce / 2.).abs() < 1e-5);
+}
+
+#[test]
+fn test_gaussian_nll_loss() {
+    // one target, two samples, predicted as (mean, variance)
+    let y_true = Matrix::from_column_leading_vector2(&vec![vec![1.0], vec![-2.0]]);
+    let y_pred = Matrix::from_column_leading_vector2(&vec![vec![0.5, 2.0], vec![-1.0, 0.5]]);
+    let loss = Losses::GaussianNLL.to_loss();
+
+    let expected = (0.5 * ((2.0 as Scalar).ln() + 0.25 / 2.0) + 0.5 * ((0.5 as Scalar).ln() + 1.0 / 0.5)) / 2.0;
+    assert!((loss.loss(&y_true, &y_pred) - expected).abs() < 1e-5);
+
+    // The derivatives are the ones of each target's loss, like the regression losses
+    let prime = loss.loss_prime(&y_true, &y_pred);
+    let h = 1e-2;
+    for i in 0..2 {
+        for j in 0..2 {
+            let mut plus = y_pred.clone();
+            *plus.index_mut(i, j) += h;
+            let mut minus = y_pred.clone();
+            *minus.index_mut(i, j) -= h;
+            let numerical = (loss.loss(&y_true, &plus) - loss.loss(&y_true, &minus)) / (2. * h) * 2.;
+            assert!((prime.index(i, j) - numerical).abs() < 1e-2);
+        }
+    }
+
+    assert_eq!(loss.point_predictions_vec(&vec![vec![0.5, 2.0], vec![-1.0, 0.5]]), vec![vec![0.5], vec![-1.0]]);
+}
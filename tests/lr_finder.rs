@@ -0,0 +1,83 @@
+#![cfg(feature = "data")]
+
+use assert_float_eq::*;
+use jiro_nn::{
+    dataset::{Dataset, FeatureTags},
+    datatable::DataTable,
+    linalg::Scalar,
+    model::{Model, ModelBuilder},
+    trainers::lr_finder::LearningRateFinder,
+};
+
+fn model_and_data() -> (Model, DataTable) {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    let rows = (0..32)
+        .map(|i| {
+            let x = i as Scalar / 32.;
+            vec![i as Scalar, x, 2. * x - 0.5]
+        })
+        .collect::<Vec<_>>();
+    let data = DataTable::from_vectors(&["id", "x", "y"], &rows);
+    let model = ModelBuilder::new(dataset_config)
+        .batch_size(4)
+        .seed(7)
+        .neural_network()
+            .full_dense(1)
+                .linear()
+                .sgd()
+            .end()
+        .end()
+        .build();
+    (model, data)
+}
+
+#[test]
+fn test_lr_finder_sweeps_learning_rates() {
+    let (model, data) = model_and_data();
+
+    let report = LearningRateFinder::new()
+        .learning_rate_range(1e-5, 1.)
+        .steps(20)
+        .divergence_threshold(Scalar::MAX)
+        .run(&model, &data);
+
+    assert_eq!(report.learning_rates.len(), 20);
+    assert_eq!(report.losses.len(), 20);
+    assert_eq!(report.smoothed_losses.len(), 20);
+
+    // The learning rate grows geometrically from the minimum towards the maximum
+    assert_float_relative_eq!(report.learning_rates[0], 1e-5, 0.0001);
+    assert_float_relative_eq!(report.learning_rates[10], 1e-5 * (1e5 as Scalar).sqrt(), 0.0001);
+    for pair in report.learning_rates.windows(2) {
+        assert_float_relative_eq!(pair[1] / pair[0], (1e5 as Scalar).powf(1. / 20.), 0.0001);
+    }
+
+    // The suggestion is the learning rate of an interior step where the smoothed loss decreases
+    let step = report
+        .learning_rates
+        .iter()
+        .position(|lr| *lr == report.suggested_learning_rate)
+        .expect("the suggested learning rate is one of the tested learning rates");
+    assert!(step > 0 && step < 19);
+    assert!(report.smoothed_losses[step + 1] < report.smoothed_losses[step - 1]);
+}
+
+#[test]
+fn test_lr_finder_stops_when_loss_diverges() {
+    let (model, data) = model_and_data();
+
+    // No smoothed loss is below zero times the best loss, so the test stops after the first step
+    let report = LearningRateFinder::new()
+        .learning_rate_range(1e-3, 1.)
+        .steps(20)
+        .divergence_threshold(0.)
+        .run(&model, &data);
+
+    assert_eq!(report.learning_rates.len(), 1);
+    // With too few steps the suggestion falls back to a tenth of the best learning rate
+    assert_float_relative_eq!(report.suggested_learning_rate, 1e-4, 0.0001);
+}
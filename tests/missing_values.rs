@@ -0,0 +1,60 @@
+#![cfg(feature = "data")]
+
+use jiro_nn::{
+    dataset::{Dataset, FeatureTags},
+    datatable::DataTable,
+    model::{Model, ModelBuilder},
+};
+use polars::prelude::*;
+
+fn model() -> Model {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    ModelBuilder::new(dataset_config)
+        .neural_network()
+            .full_dense(1)
+                .linear()
+            .end()
+        .end()
+        .build()
+}
+
+#[test]
+fn test_missing_targets_are_trained_on() {
+    let data = DataTable::from_dataframe(
+        df!(
+            "id" => &[0, 1, 2],
+            "x" => &[0.5, 1.0, 1.5],
+            "y" => &[Some(1.0), None, Some(3.0)]
+        )
+        .unwrap(),
+    );
+
+    let y = data.select_columns(&["y"]).to_vectors_with_missing();
+    assert!(y[1][0].is_nan());
+
+    let model = model();
+    let mut network = model.to_network();
+    let loss = model.train_epoch(0, &mut network, &data, "id");
+    assert!(loss.is_finite());
+}
+
+#[test]
+#[should_panic(expected = "The column x has missing values")]
+fn test_missing_inputs_are_rejected() {
+    let data = DataTable::from_dataframe(
+        df!(
+            "id" => &[0, 1, 2],
+            "x" => &[Some(0.5), None, Some(1.5)],
+            "y" => &[1.0, 2.0, 3.0]
+        )
+        .unwrap(),
+    );
+
+    let model = model();
+    let mut network = model.to_network();
+    model.train_epoch(0, &mut network, &data, "id");
+}
@@ -0,0 +1,337 @@
+use jiro_nn::{
+    linalg::Scalar,
+    loss::Losses,
+    model::network_model::{NetworkModel, NetworkModelBuilder},
+    network::{
+        ensemble::{Ensemble, EnsembleMethod},
+        params::NetworkParams,
+        params_averaging::{ParamsAverager, ParamsAveraging},
+    },
+    optimizer::{sgd::SGD, Optimizers},
+    random::set_seed,
+};
+
+fn xor_data() -> (Vec<Vec<Scalar>>, Vec<Vec<Scalar>>) {
+    let x = vec![
+        vec![0.0, 0.0],
+        vec![1.0, 0.0],
+        vec![0.0, 1.0],
+        vec![1.0, 1.0],
+    ];
+    let y = vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]];
+    (x, y)
+}
+
+#[test]
+fn test_frozen_layer_is_not_updated() {
+    let (x, y) = xor_data();
+    let mut network = NetworkModelBuilder::new()
+        .full_dense(3)
+            .tanh()
+            .frozen()
+        .end()
+        .full_dense(1)
+            .tanh()
+        .end()
+        .build()
+        .to_network(2);
+
+    assert!(!network.is_layer_trainable(0));
+    assert!(network.is_layer_trainable(1));
+
+    let before = network.get_params();
+    let loss = Losses::MSE.to_loss();
+    for epoch in 0..10 {
+        network.train(epoch, &x, &y, &loss, 2);
+    }
+    let after = network.get_params();
+
+    assert_eq!(before.0[0], after.0[0]);
+    assert_ne!(before.0[1], after.0[1]);
+}
+
+#[test]
+fn test_freeze_and_unfreeze_layers_at_runtime() {
+    let (x, y) = xor_data();
+    let mut network = NetworkModelBuilder::new()
+        .full_dense(3)
+            .tanh()
+        .end()
+        .full_dense(1)
+            .tanh()
+        .end()
+        .build()
+        .to_network(2);
+    let loss = Losses::MSE.to_loss();
+
+    network.freeze_layers_until(1);
+    let before = network.get_params();
+    network.train(0, &x, &y, &loss, 4);
+    assert_eq!(before.0[0], network.get_params().0[0]);
+
+    network.unfreeze_layer(0);
+    network.train(1, &x, &y, &loss, 4);
+    assert_ne!(before.0[0], network.get_params().0[0]);
+}
+
+#[test]
+fn test_learning_rate_multiplier_scales_layer_update() {
+    let (x, y) = xor_data();
+    let network = || {
+        set_seed(0);
+        NetworkModelBuilder::new()
+            .full_dense(3)
+                .tanh()
+                .optimizer(Optimizers::SGD(SGD::with_const_lr(0.1)))
+            .end()
+            .full_dense(1)
+                .tanh()
+                .optimizer(Optimizers::SGD(SGD::with_const_lr(0.1)))
+            .end()
+            .build()
+            .to_network(2)
+    };
+    let loss = Losses::MSE.to_loss();
+
+    let mut full_rate = network();
+    let mut half_rate = network();
+    half_rate.set_layer_learning_rate_multiplier(0, 0.5);
+
+    let before = full_rate.get_params();
+    full_rate.train(0, &x, &y, &loss, 4);
+    half_rate.train(0, &x, &y, &loss, 4);
+    let (full_rate, half_rate) = (full_rate.get_params(), half_rate.get_params());
+
+    // The first layer moves half as far, the second one as far
+    for (layer, multiplier) in [(0, 0.5), (1, 1.0)] {
+        for (i, params) in before.0[layer].iter().enumerate() {
+            for (j, param) in params.iter().enumerate() {
+                let full_step = full_rate.0[layer][i][j] - param;
+                let half_step = half_rate.0[layer][i][j] - param;
+                assert!((half_step - multiplier * full_step).abs() < 1e-6);
+            }
+        }
+    }
+    assert_ne!(before.0[0], full_rate.0[0]);
+}
+
+#[test]
+fn test_params_averaging() {
+    let params = |value: Scalar| NetworkParams(vec![vec![vec![value, -value]]]);
+
+    let mut swa = ParamsAverager::new(ParamsAveraging::StochasticWeightAveraging { epochs: 2 });
+    let mut ema = ParamsAverager::new(ParamsAveraging::ExponentialMovingAverage { decay: 0.5 });
+    for (epoch, value) in [10., 2., 4.].into_iter().enumerate() {
+        swa.update(epoch, 3, params(value));
+        ema.update(epoch, 3, params(value));
+    }
+
+    assert_eq!(swa.get_params().unwrap().0, params(3.).0);
+    assert_eq!(ema.get_params().unwrap().0, params(5.).0);
+}
+
+#[test]
+fn test_networks_params_average() {
+    let params = |value: Scalar| NetworkParams(vec![vec![vec![value, -value]]]);
+
+    let avg = NetworkParams::average(&vec![params(3.), params(6.), params(9.)]);
+
+    assert_eq!(avg.0, params(6.).0);
+}
+
+#[test]
+fn test_ensemble_combines_members_predictions() {
+    // A single linear unit without bias outputs its weight times the input
+    let network_model = NetworkModelBuilder::new()
+        .full_dense(1)
+            .linear()
+        .end()
+        .build();
+    let member = |weight: Scalar| NetworkParams(vec![vec![vec![weight], vec![0.0]]]);
+    let members = vec![member(0.2), member(0.4), member(0.9)];
+    let x = vec![vec![1.0]];
+
+    let ensemble = Ensemble::new(network_model, 1, members);
+    let avg = ensemble.predict_many(&x, 1);
+    assert!((avg[0][0] - 0.5).abs() < 1e-6);
+
+    let weighted = ensemble.clone().weights(vec![1.0, 1.0, 2.0]);
+    assert!((weighted.predict_many(&x, 1)[0][0] - 0.6).abs() < 1e-6);
+
+    // Only the last member predicts the class 1
+    let vote = weighted.clone().method(EnsembleMethod::Vote);
+    assert!((vote.predict_many(&x, 1)[0][0] - 0.5).abs() < 1e-6);
+
+    let path = std::env::temp_dir().join("jiro_nn_test_ensemble.json");
+    vote.to_json(&path);
+    let loaded = Ensemble::from_json(&path);
+    assert_eq!(loaded.method, EnsembleMethod::Vote);
+    assert_eq!(loaded.predict_many(&x, 1), vote.predict_many(&x, 1));
+
+    // Without a meta network too
+    let path = std::env::temp_dir().join("jiro_nn_test_average_ensemble.gz");
+    weighted.to_binary_compressed(&path);
+    let loaded = Ensemble::from_binary_compressed(&path);
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(loaded.method, EnsembleMethod::Average);
+    assert!(loaded.meta.is_none());
+    assert_eq!(loaded.weights, weighted.weights);
+    assert_eq!(loaded.predict_many(&x, 1), weighted.predict_many(&x, 1));
+}
+
+#[test]
+fn test_ensemble_weights_from_scores() {
+    let weights = Ensemble::weights_from_scores(&[0.5, Scalar::NAN, -0.2, 1.5], true);
+    assert_eq!(weights, vec![0.5, 0.0, 0.0, 1.5]);
+
+    // A perfect error outweighs the others instead of being dropped
+    let weights = Ensemble::weights_from_scores(&[0.0, 0.5, Scalar::NAN], false);
+    assert_eq!(weights[2], 0.0);
+    assert!((weights[1] - 2.0).abs() < 1e-3);
+    assert!(weights[0] > 1000.0 * weights[1]);
+
+    // Equal weights when no member gets one
+    assert_eq!(Ensemble::weights_from_scores(&[Scalar::NAN, 0.0], true), vec![1.0, 1.0]);
+}
+
+#[test]
+fn test_ensemble_stacking() {
+    let network_model = NetworkModelBuilder::new()
+        .full_dense(1)
+            .linear()
+        .end()
+        .build();
+    let member = |weight: Scalar| NetworkParams(vec![vec![vec![weight], vec![0.0]]]);
+    let mut ensemble = Ensemble::new(network_model, 1, vec![member(0.2), member(0.4), member(0.9)]);
+
+    // The meta network learns to undo the members' scaling
+    set_seed(0);
+    let x = (0..20).map(|i| vec![i as Scalar / 20.]).collect::<Vec<_>>();
+    let y = x.clone();
+    let meta_network = NetworkModelBuilder::new()
+        .full_dense(1)
+            .linear()
+            .init_zeros()
+            .momentum()
+        .end()
+        .build();
+    ensemble.fit_stacking(meta_network, &x, &y, Losses::MSE, 500, 4);
+    assert_eq!(ensemble.method, EnsembleMethod::Stacking);
+
+    let preds = ensemble.predict_many(&x, 4);
+    let mse = preds
+        .iter()
+        .zip(y.iter())
+        .map(|(p, y)| (p[0] - y[0]).powi(2))
+        .sum::<Scalar>()
+        / y.len() as Scalar;
+    assert!(mse < 1e-3, "mse {}", mse);
+
+    let path = std::env::temp_dir().join("jiro_nn_test_stacking_ensemble.gz");
+    ensemble.to_binary_compressed(&path);
+    let loaded = Ensemble::from_binary_compressed(&path);
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(loaded.method, EnsembleMethod::Stacking);
+    assert_eq!(loaded.weights, ensemble.weights);
+    assert_eq!(loaded.predict_many(&x, 4), preds);
+}
+
+// Trains a network with one thread and a copy of it with 3 replicas, and compares their parameters
+fn assert_data_parallel_matches_single_thread(
+    network_model: NetworkModel,
+    x: &Vec<Vec<Scalar>>,
+    y: &Vec<Vec<Scalar>>,
+    weights: Option<&[Scalar]>,
+    loss: Losses,
+    batch_size: usize,
+) {
+    let loss = loss.to_loss();
+    let in_dims = x[0].len();
+    let mut network = network_model.clone().to_network(in_dims);
+    let mut parallel_network = network_model.clone().to_network(in_dims);
+    parallel_network.load_params(&network.get_params());
+    let mut replicas = (0..3)
+        .map(|_| network_model.clone().to_network(in_dims))
+        .collect::<Vec<_>>();
+
+    for epoch in 0..5 {
+        let error = network.train_weighted_with_hook(epoch, x, y, weights, &loss, batch_size, &mut |_, _, _| false);
+        let parallel_error = parallel_network.train_data_parallel_with_hook(
+            &mut replicas,
+            epoch,
+            x,
+            y,
+            weights,
+            &loss,
+            batch_size,
+            &mut |_, _, _| false,
+        );
+        assert!((error - parallel_error).abs() < 1e-5);
+    }
+
+    let params = network.get_params().0.concat().concat();
+    let parallel_params = parallel_network.get_params().0.concat().concat();
+    assert!(params.iter().all(|p| p.is_finite()));
+    for (a, b) in params.iter().zip(parallel_params.iter()) {
+        assert!((a - b).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_data_parallel_training_matches_single_thread() {
+    let (x, y) = xor_data();
+    let network_model = NetworkModelBuilder::new()
+        .full_dense(3)
+            .tanh()
+            .adam()
+        .end()
+        .full_dense(1)
+            .sigmoid()
+            .adam()
+        .end()
+        .build();
+    assert_data_parallel_matches_single_thread(network_model, &x, &y, None, Losses::BCE, 4);
+}
+
+#[test]
+fn test_data_parallel_sgd_with_categorical_cross_entropy_matches_single_thread() {
+    // 6 samples, split into 3 shards of 2
+    let x = (0..6).map(|i| vec![i as Scalar / 6., (i % 2) as Scalar]).collect::<Vec<_>>();
+    let y = (0..6)
+        .map(|i| if i % 3 == 0 { vec![1., 0.] } else { vec![0., 1.] })
+        .collect::<Vec<_>>();
+    let network_model = NetworkModelBuilder::new()
+        .full_dense(4)
+            .tanh()
+            .sgd()
+        .end()
+        .full_dense(2)
+            .softmax()
+            .sgd()
+        .end()
+        .build();
+    let loss = Losses::CategoricalCrossEntropy { label_smoothing: 0. };
+    assert_data_parallel_matches_single_thread(network_model, &x, &y, None, loss, 6);
+}
+
+#[test]
+fn test_data_parallel_sgd_with_weighted_loss_matches_single_thread() {
+    let x = (0..6).map(|i| vec![i as Scalar / 6.]).collect::<Vec<_>>();
+    // The last target is missing
+    let y = (0..6)
+        .map(|i| if i == 5 { vec![Scalar::NAN] } else { vec![i as Scalar / 3. - 1.] })
+        .collect::<Vec<_>>();
+    // The first shard has no weight
+    let weights = vec![0., 0., 1., 3., 0.5, 2.];
+    let network_model = NetworkModelBuilder::new()
+        .full_dense(4)
+            .tanh()
+            .sgd()
+        .end()
+        .full_dense(1)
+            .linear()
+            .sgd()
+        .end()
+        .build();
+    assert_data_parallel_matches_single_thread(network_model, &x, &y, Some(&weights), Losses::MSE, 6);
+}
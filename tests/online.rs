@@ -0,0 +1,100 @@
+#![cfg(feature = "data")]
+
+use jiro_nn::{
+    dataset::{Dataset, FeatureTags},
+    datatable::DataTable,
+    linalg::Scalar,
+    model::{
+        online::{OnlineModel, ReplayBuffer},
+        ModelBuilder,
+    },
+    preprocessing::{normalize::Normalize, Pipeline},
+};
+
+fn rows(ids: std::ops::Range<usize>) -> DataTable {
+    let rows = ids
+        .map(|i| vec![i as Scalar, (i % 50) as Scalar / 50., 2. * (i % 50) as Scalar / 50.])
+        .collect::<Vec<_>>();
+    DataTable::from_vectors(&["id", "x", "y"], &rows)
+}
+
+#[test]
+fn test_partial_fit_resumes_training() {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    let model = ModelBuilder::new(dataset_config)
+        .epochs(5)
+        .batch_size(4)
+        .seed(0)
+        .neural_network()
+            .full_dense(4)
+                .tanh()
+                .adam()
+            .end()
+            .full_dense(1)
+                .linear()
+                .adam()
+            .end()
+        .end()
+        .build();
+
+    let mut online = OnlineModel::new(model).replay_buffer(ReplayBuffer::new(30));
+    let first_loss = online.partial_fit(&rows(0..50));
+    let mut last_loss = first_loss;
+    for i in 1..4 {
+        last_loss = online.partial_fit(&rows(i * 50..(i + 1) * 50));
+    }
+
+    assert_eq!(online.epochs, 20);
+    assert_eq!(online.replay.as_ref().unwrap().len(), 30);
+    assert!(last_loss < first_loss);
+
+    // The optimizers' state goes through the network and the serialization
+    let path = std::env::temp_dir().join("jiro_nn_test_online_model.json");
+    online.to_json(&path);
+    let loaded = OnlineModel::from_json(&path);
+    let state = loaded.to_network().get_optimizers_state();
+    assert!(!state.0.is_empty() && state.0.iter().all(|layer| !layer.is_empty()));
+    assert_eq!(
+        serde_json::to_string(&state).unwrap(),
+        serde_json::to_string(&online.optimizers_state).unwrap()
+    );
+    std::fs::remove_file(path).unwrap();
+
+    let path = std::env::temp_dir().join("jiro_nn_test_online_model.gz");
+    online.to_binary_compressed(&path);
+    let loaded = OnlineModel::from_binary_compressed(&path);
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(loaded.model.seed, Some(0));
+    assert_eq!(loaded.epochs, 20);
+    assert_eq!(loaded.replay.as_ref().unwrap().len(), 30);
+    assert_eq!(loaded.params.0, online.params.0);
+    assert_eq!(
+        serde_json::to_string(&loaded.optimizers_state).unwrap(),
+        serde_json::to_string(&online.optimizers_state).unwrap()
+    );
+}
+
+#[test]
+fn test_pipeline_apply_reuses_fitted_bounds() {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x"), FeatureTags::Normalized],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    let path = std::env::temp_dir().join("jiro_nn_test_pipeline_apply.csv");
+    rows(0..50).to_csv_file(&path);
+
+    let mut pipeline = Pipeline::new();
+    pipeline.push(Normalize::new());
+    pipeline.load_data(path.to_str().unwrap(), Some(&dataset_config)).run();
+
+    // x is 0.98 at most in the fitted rows, so the new 0.49 is normalized to 0.5 instead of 1
+    let new_rows = DataTable::from_vectors(&["id", "x", "y"], &vec![vec![100., 0.49, 0.98]]);
+    let applied = pipeline.apply(&new_rows);
+    assert!((applied.column_to_vector("x")[0] - 0.5).abs() < 1e-5);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,160 @@
+#![cfg(feature = "data")]
+
+use assert_float_eq::*;
+use jiro_nn::{
+    dataset::{Dataset, FeatureTags},
+    datatable::DataTable,
+    linalg::Scalar,
+    loss::Losses,
+    metrics::{Mae, Rmse},
+    model::{Model, ModelBuilder},
+    preprocessing::{log_scale::LogScale10, normalize::Normalize, Pipeline},
+    trainers::split::SplitTraining,
+};
+
+fn dataset_config() -> Dataset {
+    Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x"), FeatureTags::Normalized],
+        &[
+            FeatureTags::Name("y"),
+            FeatureTags::Predicted,
+            FeatureTags::Log10,
+            FeatureTags::Normalized,
+        ],
+    ])
+}
+
+// Runs a pipeline log scaling then normalizing the target `y` = 10 + 90 * `x`
+fn run_pipeline(file_name: &str) -> (Pipeline, Dataset, DataTable) {
+    let rows = (0..40)
+        .map(|i| {
+            let x = i as Scalar / 39.;
+            vec![i as Scalar, x, 10. + 90. * x]
+        })
+        .collect::<Vec<_>>();
+    let path = std::env::temp_dir().join(file_name);
+    DataTable::from_vectors(&["id", "x", "y"], &rows).to_csv_file(&path);
+
+    let mut pipeline = Pipeline::new();
+    pipeline.push(LogScale10::new()).push(Normalize::new());
+    let (dataset_config, data) = pipeline
+        .load_data(path.to_str().unwrap(), Some(&dataset_config()))
+        .run();
+    std::fs::remove_file(path).unwrap();
+    (pipeline, dataset_config, data)
+}
+
+fn gaussian_model(dataset_config: Dataset) -> Model {
+    ModelBuilder::new(dataset_config)
+        .loss(Losses::GaussianNLL)
+        .epochs(5)
+        .batch_size(8)
+        .neural_network()
+            .full_dense(4)
+                .tanh()
+            .end()
+            .gaussian_output(1)
+        .end()
+        .build()
+}
+
+#[test]
+fn test_gaussian_preds_have_mean_and_std_columns() {
+    let model = gaussian_model(dataset_config());
+
+    assert_eq!(model.preds_columns_names(), vec!["y_mean", "y_std"]);
+
+    // The network predicts variances, the predictions hold standard deviations
+    let table = model.preds_to_table(vec![vec![0.5, 4.0], vec![0.25, 0.09]]);
+    assert_eq!(
+        table.column_to_vector("pred_y_mean"),
+        vec![0.5 as Scalar, 0.25]
+    );
+    let stds = table.column_to_vector("pred_y_std");
+    assert_float_relative_eq!(stds[0], 2.0, 0.0001);
+    assert_float_relative_eq!(stds[1], 0.3, 0.0001);
+}
+
+#[test]
+fn test_gaussian_std_is_reverted_through_normalization_and_log_scale() {
+    let (mut pipeline, _, _) = run_pipeline("jiro_nn_test_gaussian_revert.csv");
+
+    // log10(y) spans [1, 2], so it is normalized by (log10(y) - 1) / (2 - 1)
+    let preds = DataTable::from_vectors(&["id", "y_mean", "y_std"], &vec![vec![0., 0.5, 0.1]]);
+    let reverted = pipeline.revert(&preds);
+
+    let mean = reverted.column_to_vector("y_mean")[0];
+    let std = reverted.column_to_vector("y_std")[0];
+    // The normalized mean 0.5 is 1.5 in log10 units, and its std 0.1 stays 0.1 (range of 1)
+    assert_float_relative_eq!(mean, (10 as Scalar).powf(1.5), 0.001);
+    // The std goes through the exponential with the delta method
+    assert_float_relative_eq!(std, (10 as Scalar).ln() * (10 as Scalar).powf(1.5) * 0.1, 0.001);
+
+    // The thread-safe reverter of the trainers gives the same result
+    let reverted = pipeline.reverter()(&preds);
+    assert_float_relative_eq!(reverted.column_to_vector("y_std")[0], std, 0.0001);
+}
+
+#[test]
+fn test_trained_gaussian_preds_are_reverted() {
+    let (mut pipeline, dataset_config, data) = run_pipeline("jiro_nn_test_gaussian_training.csv");
+    let model = gaussian_model(dataset_config);
+
+    let mut training = SplitTraining::new(0.75);
+    training.seed(3);
+    let (preds, _) = training.run(&model, &data);
+    assert_eq!(preds.num_rows(), 10);
+
+    let normalized_stds = preds.column_to_vector("y_std");
+    assert!(normalized_stds.iter().all(|std| *std > 0.));
+
+    let reverted = pipeline.revert(&preds);
+    let means = reverted.column_to_vector("y_mean");
+    let stds = reverted.column_to_vector("y_std");
+    for ((mean, std), normalized_std) in means.iter().zip(stds.iter()).zip(normalized_stds.iter()) {
+        assert!(*mean > 0.);
+        assert_float_relative_eq!(*std, (10 as Scalar).ln() * mean * normalized_std, 0.001);
+    }
+}
+
+#[test]
+fn test_metrics_in_original_units() {
+    let (mut pipeline, dataset_config, data) = run_pipeline("jiro_nn_test_original_units.csv");
+    let model = ModelBuilder::new(dataset_config)
+        .epochs(5)
+        .batch_size(8)
+        .neural_network()
+            .full_dense(4)
+                .tanh()
+            .end()
+            .full_dense(1)
+                .linear()
+            .end()
+        .end()
+        .build();
+
+    let mut training = SplitTraining::new(0.75);
+    training
+        .seed(3)
+        .metrics_in_original_units(pipeline.reverter())
+        .metric(Mae)
+        .metric(Rmse);
+    let (preds, eval) = training.run(&model, &data);
+
+    // The predictions reverted by the pipeline against the targets of the same ids before preprocessing
+    let reverted = pipeline.revert(&preds);
+    let errors = reverted
+        .column_to_vector("id")
+        .iter()
+        .zip(reverted.column_to_vector("y").iter())
+        .map(|(id, pred)| pred - (10. + 90. * id / 39.))
+        .collect::<Vec<_>>();
+    let n = errors.len() as Scalar;
+    let mae = errors.iter().map(|e| e.abs()).sum::<Scalar>() / n;
+    let rmse = (errors.iter().map(|e| e * e).sum::<Scalar>() / n).sqrt();
+
+    let fold = &eval.folds[0];
+    assert_float_relative_eq!(fold.get_final_metric("mae").unwrap(), mae, 0.001);
+    assert_float_relative_eq!(fold.get_final_metric("rmse").unwrap(), rmse, 0.001);
+}
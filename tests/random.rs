@@ -0,0 +1,36 @@
+use jiro_nn::{model::network_model::NetworkModelBuilder, random::set_seed, vec_utils::shuffle_vec};
+
+#[test]
+fn test_same_seed_same_initialization() {
+    let model = NetworkModelBuilder::new()
+        .full_dense(8)
+            .relu()
+            .dropout(0.5)
+        .end()
+        .full_dense(1)
+            .tanh()
+        .end()
+        .build();
+
+    set_seed(42);
+    let first = model.clone().to_network(4).get_params();
+    set_seed(42);
+    let second = model.clone().to_network(4).get_params();
+    let third = model.clone().to_network(4).get_params();
+
+    assert_eq!(first.0, second.0);
+    assert_ne!(second.0, third.0);
+}
+
+#[test]
+fn test_same_seed_same_shuffle() {
+    let mut first = (0..100).collect::<Vec<_>>();
+    let mut second = first.clone();
+
+    set_seed(7);
+    shuffle_vec(&mut first);
+    set_seed(7);
+    shuffle_vec(&mut second);
+
+    assert_eq!(first, second);
+}
@@ -0,0 +1,109 @@
+#![cfg(feature = "data")]
+
+use jiro_nn::{
+    dataset::{Dataset, FeatureTags},
+    datatable::DataTable,
+    linalg::Scalar,
+    model::{Model, ModelBuilder},
+    network::Network,
+    optimizer::{sgd::SGD, Optimizers},
+};
+
+// The even samples have a target of 1 and a weight of `even_weight`,
+// the odd ones a target of -1 and a weight of 1 - `even_weight`.
+fn model_and_data(even_weight: Scalar) -> (Model, DataTable) {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("weight"), FeatureTags::SampleWeight],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    let rows = (0..16)
+        .map(|i| {
+            let even = i % 2 == 0;
+            vec![
+                i as Scalar,
+                (i / 2) as Scalar / 8.,
+                if even { even_weight } else { 1. - even_weight },
+                if even { 1. } else { -1. },
+            ]
+        })
+        .collect::<Vec<_>>();
+    let data = DataTable::from_vectors(&["id", "x", "weight", "y"], &rows);
+    let model = ModelBuilder::new(dataset_config)
+        .batch_size(16)
+        .neural_network()
+            .full_dense(1)
+                .linear()
+                .optimizer(Optimizers::SGD(SGD::with_const_lr(0.01)))
+            .end()
+        .end()
+        .build();
+    (model, data)
+}
+
+fn mean_prediction(model: &Model, data: &DataTable, epochs: usize) -> Scalar {
+    let mut network = model.to_network();
+    for epoch in 0..epochs {
+        network_epoch(model, &mut network, data, epoch);
+    }
+    let x = model.inputs_to_vectors(&data.drop_column("y"), "id");
+    let preds = network.predict_many(&x, x.len());
+    preds.iter().map(|p| p[0]).sum::<Scalar>() / preds.len() as Scalar
+}
+
+fn network_epoch(model: &Model, network: &mut Network, data: &DataTable, epoch: usize) {
+    let loss = model.train_epoch(epoch, network, data, "id");
+    assert!(loss.is_finite());
+}
+
+#[test]
+fn test_training_follows_sample_weights() {
+    // Only the samples with a target of 1 count
+    let (model, data) = model_and_data(1.);
+    assert!(mean_prediction(&model, &data, 100) > 0.9);
+
+    // Only the samples with a target of -1 count
+    let (model, data) = model_and_data(0.);
+    assert!(mean_prediction(&model, &data, 100) < -0.9);
+
+    // Both count equally
+    let (model, data) = model_and_data(0.5);
+    assert!(mean_prediction(&model, &data, 100).abs() < 0.1);
+}
+
+#[test]
+fn test_zero_weighted_batch_leaves_network_unchanged() {
+    let (model, data) = model_and_data(0.);
+    // Only keeps the zero weighted samples
+    let data = DataTable::from_vectors(
+        &["id", "x", "weight", "y"],
+        &data
+            .to_vectors()
+            .into_iter()
+            .filter(|row| row[2] == 0.)
+            .collect::<Vec<_>>(),
+    );
+
+    let mut network = model.to_network();
+    let params = network.get_params();
+    network_epoch(&model, &mut network, &data, 0);
+    assert_eq!(network.get_params().0, params.0);
+}
+
+#[test]
+fn test_classes_weights_skip_missing_targets() {
+    let (mut model, _) = model_and_data(0.5);
+    model.class_weights = Some(vec![0.5, 2.]);
+
+    let y = vec![vec![0.], vec![1.], vec![Scalar::NAN]];
+    assert_eq!(model.classes_weights(&y), Some(vec![0.5, 2., 1.]));
+}
+
+#[test]
+#[should_panic(expected = "The class 2 has no weight, the model has 2 class weights")]
+fn test_classes_weights_reject_unknown_classes() {
+    let (mut model, _) = model_and_data(0.5);
+    model.class_weights = Some(vec![0.5, 2.]);
+    model.classes_weights(&[vec![2.]]);
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "data")]
+
+use jiro_nn::{datatable::DataTable, linalg::Scalar, preprocessing::sample::SamplingStrategy};
+
+fn imbalanced_table() -> DataTable {
+    let labels = (0..20).map(|i| if i < 4 { 1.0 } else { 0.0 }).collect::<Vec<_>>();
+    let values = (0..20).map(|i| i as Scalar).collect::<Vec<_>>();
+    DataTable::new_empty()
+        .with_column_scalar("value", &values)
+        .with_column_scalar("label", &labels)
+}
+
+fn count_positives(table: &DataTable) -> usize {
+    table
+        .column_to_vector("label")
+        .iter()
+        .filter(|l| **l == 1.0)
+        .count()
+}
+
+#[test]
+fn test_sampling_strategies_balance_classes() {
+    let table = imbalanced_table();
+
+    let oversampled = SamplingStrategy::Oversample.apply(&table, &["label"]);
+    assert_eq!(oversampled.num_rows(), 32);
+    assert_eq!(count_positives(&oversampled), 16);
+
+    let undersampled = SamplingStrategy::Undersample.apply(&table, &["label"]);
+    assert_eq!(undersampled.num_rows(), 8);
+    assert_eq!(count_positives(&undersampled), 4);
+
+    let balanced = SamplingStrategy::BalancedBatches.apply(&table, &["label"]);
+    assert_eq!(balanced.num_rows(), 20);
+    let labels = balanced.column_to_vector("label");
+    for batch in labels.chunks(4) {
+        assert_eq!(batch.iter().filter(|l| **l == 1.0).count(), 2);
+    }
+}
+
+#[test]
+fn test_sampling_strategies_keep_empty_data_empty() {
+    let empty = imbalanced_table().split(0, 0).0;
+    assert_eq!(empty.num_rows(), 0);
+
+    for strategy in [
+        SamplingStrategy::Oversample,
+        SamplingStrategy::Undersample,
+        SamplingStrategy::BalancedBatches,
+    ] {
+        assert_eq!(strategy.apply(&empty, &["label"]).num_rows(), 0);
+    }
+}
@@ -0,0 +1,142 @@
+#![cfg(feature = "data")]
+
+use jiro_nn::{
+    dataset::{Dataset, FeatureTags},
+    datatable::DataTable,
+    linalg::Scalar,
+    model::{network_model::NetworkLayerModels, Model, ModelBuilder},
+    trainers::{
+        search::{HyperparameterSearch, SearchSpace, SearchStrategy},
+        split::SplitTraining,
+    },
+};
+
+fn base_model_and_data() -> (Model, DataTable) {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    let rows = (0..40)
+        .map(|i| vec![i as Scalar, i as Scalar / 40., 2. * i as Scalar / 40.])
+        .collect::<Vec<_>>();
+    let data = DataTable::from_vectors(&["id", "x", "y"], &rows);
+    let model = ModelBuilder::new(dataset_config)
+        .epochs(4)
+        .batch_size(8)
+        .neural_network()
+            .full_dense(4)
+                .tanh()
+            .end()
+            .full_dense(1)
+                .linear()
+            .end()
+        .end()
+        .build();
+    (model, data)
+}
+
+#[test]
+fn test_grid_search_ranks_configurations() {
+    let (base, data) = base_model_and_data();
+    let space = SearchSpace::new()
+        .hidden_layers(vec![vec![2], vec![8, 4]])
+        .learning_rates(vec![0.01, 0.1]);
+
+    let mut search = HyperparameterSearch::new(base, space, SearchStrategy::Grid);
+    search.seed(0);
+    let results = search.run(|| SplitTraining::new(0.8), &data);
+
+    assert_eq!(results.num_rows(), 4);
+    let scores = results.column_to_vector("score");
+    assert!(scores.windows(2).all(|w| w[0] >= w[1] || w[1].is_nan()));
+
+    let hashes = results.column_to_strings("model");
+    let layers = search.get_model(&hashes[0]).unwrap().network.as_ref().unwrap().layers.len();
+    assert!(layers == 2 || layers == 3);
+    for hash in hashes.iter() {
+        let model = search.get_model(hash).unwrap();
+        assert_eq!(&model.hashed_repr(), hash);
+        match model.network.as_ref().unwrap().layers.last().unwrap() {
+            NetworkLayerModels::FullDense(layer) => assert_eq!(layer.size, 1),
+            _ => panic!("The output layer must be kept"),
+        }
+    }
+}
+
+#[test]
+fn test_successive_halving_trains_best_configurations_longer() {
+    let (base, data) = base_model_and_data();
+    let space = SearchSpace::new().learning_rates(vec![0.001, 0.01, 0.05, 0.1]);
+    let strategy = SearchStrategy::SuccessiveHalving {
+        candidates: 4,
+        min_epochs: 1,
+        eta: 2,
+    };
+
+    let mut search = HyperparameterSearch::new(base, space, strategy);
+    search.seed(0);
+    let results = search.run(|| SplitTraining::new(0.8), &data);
+
+    // 4 configurations trained for 1 epoch, 2 of them for 2, and the best one for 4
+    let mut epochs = results.column_to_vector("epochs");
+    epochs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(epochs, vec![1., 1., 2., 4.]);
+}
+
+fn assert_distinct_models(search: &HyperparameterSearch, results: &DataTable) {
+    let hashes = results.column_to_strings("model");
+    let mut distinct = hashes.clone();
+    distinct.sort();
+    distinct.dedup();
+    assert_eq!(distinct.len(), hashes.len());
+    assert_eq!(search.models.len(), hashes.len());
+}
+
+#[test]
+fn test_random_search_draws_distinct_configurations() {
+    let (base, data) = base_model_and_data();
+    let space = SearchSpace::new().learning_rates(vec![0.01, 0.1]);
+
+    // More trials than combinations
+    let mut search = HyperparameterSearch::new(base, space, SearchStrategy::Random { trials: 5 });
+    search.seed(0);
+    let results = search.run(|| SplitTraining::new(0.8), &data);
+
+    assert_eq!(results.num_rows(), 2);
+    assert_distinct_models(&search, &results);
+}
+
+#[test]
+fn test_random_search_samples_large_spaces() {
+    let (base, data) = base_model_and_data();
+    let space = SearchSpace::new()
+        .learning_rates((1..=100).map(|i| i as Scalar / 1000.).collect())
+        .batch_sizes((1..=50).collect());
+    assert_eq!(space.size(), 5000);
+
+    // The configurations are sampled one at a time instead of shuffling the grid
+    let mut search = HyperparameterSearch::new(base, space, SearchStrategy::Random { trials: 3 });
+    search.seed(0);
+    let results = search.run(|| SplitTraining::new(0.8), &data);
+
+    assert_eq!(results.num_rows(), 3);
+    assert_distinct_models(&search, &results);
+}
+
+#[test]
+fn test_hyperband_draws_distinct_configurations() {
+    let (base, data) = base_model_and_data();
+    let space = SearchSpace::new()
+        .learning_rates(vec![0.01, 0.1])
+        .epochs(vec![1, 2]);
+    let strategy = SearchStrategy::Hyperband { max_epochs: 4, eta: 2 };
+
+    // The brackets would draw many more candidates than the 2 combinations of learning rates
+    let mut search = HyperparameterSearch::new(base, space, strategy);
+    search.seed(0);
+    let results = search.run(|| SplitTraining::new(0.8), &data);
+
+    assert_eq!(results.num_rows(), 2);
+    assert_distinct_models(&search, &results);
+}
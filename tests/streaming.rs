@@ -0,0 +1,117 @@
+#![cfg(feature = "data")]
+
+use jiro_nn::{
+    dataset::{Dataset, FeatureTags},
+    datatable::DataTable,
+    linalg::Scalar,
+    model::{Model, ModelBuilder},
+    streaming::DataStream,
+    trainers::split::SplitTraining,
+};
+
+#[test]
+fn test_stream_batches_cover_every_row_once() {
+    let rows = (0..53).map(|i| vec![i as Scalar, 2. * i as Scalar]).collect::<Vec<_>>();
+    let path = std::env::temp_dir().join("jiro_nn_test_stream_batches.csv");
+    DataTable::from_vectors(&["id", "x"], &rows).to_csv_file(&path);
+
+    for stream in [
+        DataStream::from_file(&path).chunk_size(10),
+        DataStream::from_file(&path).chunk_size(10).shuffle_buffer(25),
+    ] {
+        assert_eq!(stream.num_rows(), 53);
+        let batches = stream.batches(8).collect::<Vec<_>>();
+        assert_eq!(batches.len(), 7);
+        assert!(batches[..6].iter().all(|b| b.num_rows() == 8));
+
+        let mut ids = batches
+            .iter()
+            .flat_map(|b| b.column_to_vector("id"))
+            .collect::<Vec<_>>();
+        ids.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(ids, (0..53).map(|i| i as Scalar).collect::<Vec<_>>());
+    }
+    std::fs::remove_file(path).unwrap();
+}
+
+fn model(dataset_config: Dataset) -> Model {
+    ModelBuilder::new(dataset_config)
+        .epochs(10)
+        .batch_size(4)
+        .neural_network()
+            .full_dense(4)
+                .tanh()
+            .end()
+            .full_dense(1)
+                .linear()
+            .end()
+        .end()
+        .build()
+}
+
+#[test]
+fn test_split_training_from_stream() {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    let rows = (0..50)
+        .map(|i| vec![i as Scalar, i as Scalar / 50., 2. * i as Scalar / 50.])
+        .collect::<Vec<_>>();
+    let data = DataTable::from_vectors(&["id", "x", "y"], &rows);
+    let (train, validation) = data.split_ratio(0.8);
+    let path = std::env::temp_dir().join("jiro_nn_test_stream_training.csv");
+    train.to_csv_file(&path);
+
+    let model = model(dataset_config);
+
+    let stream = DataStream::from_file(&path).chunk_size(16).shuffle_buffer(32);
+    let mut trainer = SplitTraining::new(0.8);
+    trainer.seed(0);
+    let (preds, model_eval) = trainer.run_streaming(&model, &stream, &validation);
+    let losses = model_eval.folds[0].epochs.iter().map(|e| e.train_loss).collect::<Vec<_>>();
+
+    assert_eq!(preds.num_rows(), validation.num_rows());
+    assert_eq!(losses.len(), 10);
+    assert!(losses[9] < losses[0]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_stream_counts_rows_once() {
+    let rows = (0..20).map(|i| vec![i as Scalar, 2. * i as Scalar]).collect::<Vec<_>>();
+    let path = std::env::temp_dir().join("jiro_nn_test_stream_count.csv");
+    DataTable::from_vectors(&["id", "x"], &rows).to_csv_file(&path);
+
+    let stream = DataStream::from_file(&path).chunk_size(8);
+    assert_eq!(stream.num_rows(), 20);
+
+    // The count is kept even once the file changes
+    DataTable::from_vectors(&["id", "x"], &rows[..5].to_vec()).to_csv_file(&path);
+    assert_eq!(stream.num_rows(), 20);
+    assert_eq!(stream.clone().num_rows(), 20);
+    assert_eq!(DataStream::from_file(&path).num_rows(), 5);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_empty_stream_epoch_has_no_loss() {
+    let dataset_config = Dataset::from_features_tags(&[
+        &[FeatureTags::Name("id"), FeatureTags::IsId],
+        &[FeatureTags::Name("x")],
+        &[FeatureTags::Name("y"), FeatureTags::Predicted],
+    ]);
+    let path = std::env::temp_dir().join("jiro_nn_test_empty_stream.csv");
+    std::fs::write(&path, "id,x,y\n").unwrap();
+
+    let model = model(dataset_config);
+    let mut network = model.to_network();
+    let params = network.get_params();
+    let stream = DataStream::from_file(&path);
+    let loss = model.train_epoch_streaming(0, &mut network, &stream, "id", &mut |_, _, _| false);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(loss, 0.);
+    assert_eq!(network.get_params().0, params.0);
+}
@@ -1,7 +1,7 @@
 #[macro_use]
 extern crate assert_float_eq;
 
-use jiro_nn::vec_utils::*;
+use jiro_nn::{linalg::Scalar, vec_utils::*};
 
 #[test]
 fn test_avg_vector_empty() {
@@ -208,3 +208,29 @@ fn test_vectors_correlation() {
     let corr = vectors_correlation(&vec1, &vec2);
     assert_float_relative_eq!(corr.unwrap(), 0.88388, 0.00001);
 }
+
+#[test]
+fn test_regression_errors_vector2() {
+    let y = vec![vec![1.0, 10.0], vec![2.0, Scalar::NAN], vec![4.0, 20.0]];
+    let y_hat = vec![vec![2.0, 10.0], vec![2.0, 7.0], vec![1.0, 16.0]];
+
+    // known errors: -1, 0, 0, 3, 4
+    assert_float_absolute_eq!(mean_absolute_error_vector2(&y, &y_hat), 8.0 / 5.0, 1e-6);
+    assert_float_absolute_eq!(root_mean_squared_error_vector2(&y, &y_hat), (26.0 as Scalar / 5.0).sqrt(), 1e-6);
+    assert_float_absolute_eq!(median_absolute_error_vector2(&y, &y_hat), 1.0, 1e-6);
+    assert_float_absolute_eq!(max_error_vector2(&y, &y_hat), 4.0, 1e-6);
+    assert_float_absolute_eq!(
+        mean_absolute_percentage_error_vector2(&y, &y_hat),
+        (1.0 + 0.0 + 0.0 + 0.75 + 0.2) / 5.0,
+        1e-6
+    );
+}
+
+#[test]
+fn test_explained_variance_ignores_bias() {
+    let y = vec![vec![1.0], vec![2.0], vec![3.0]];
+    let y_hat = vec![vec![2.0], vec![3.0], vec![4.0]];
+
+    assert_float_absolute_eq!(explained_variance_score_vector2(&y, &y_hat), 1.0, 1e-6);
+    assert!(r2_score_vector2(&y, &y_hat) < 1.0);
+}